@@ -9,4 +9,4 @@ where
 {
     let async_function_list = inputs.iter().map(|input| function(input));
     join_all(async_function_list).await
-}
\ No newline at end of file
+}
@@ -2,33 +2,45 @@ use anyhow::Context;
 use async_trait::async_trait;
 
 use reqwest::Response;
+use solipath_lib::solipath_download::artifact_cache::ArtifactCacheTrait;
 use solipath_lib::solipath_download::file_downloader::FileDownloaderTrait;
-use tokio::time::sleep;
+use solipath_lib::solipath_download::credential_retriever::authorize_request;
+use solipath_lib::solipath_download::credential_retriever::{read_netrc, CredentialRetriever};
+use solipath_lib::solipath_download::http_client_factory::HttpClientFactory;
+use solipath_lib::solipath_download::retry_policy::RetryPolicy;
+use solipath_lib::solipath_config::user_config::UserConfig;
+use solipath_lib::solipath_config::user_config_retriever::{UserConfigRetriever, UserConfigRetrieverTrait};
 use std::path::Path;
 use std::path::PathBuf;
-use std::time::Duration;
 use reqwest::Client;
 use anyhow::Result;
 
 pub struct DownloadChecker{
     reqwest_client: Client,
+    credential_retriever: CredentialRetriever,
+    retry_policy: RetryPolicy,
 }
 
 impl DownloadChecker {
     pub fn new()-> Self{
-        Self{reqwest_client: Client::new()}
+        Self::new_with_user_config(&UserConfigRetriever::new().get_user_config())
     }
-    async fn repeat_request(&self, url: &str) -> Result<Response> {
-        let mut number_of_tries = 0;
-        let max_number_of_tries = 4;
-        let mut result = self.reqwest_client.head(url).send().await;
-        while result.is_err() && number_of_tries < max_number_of_tries{
-            result = self.reqwest_client.head(url).send().await;
-            number_of_tries += 1;
-            sleep(Duration::new((1+number_of_tries)*5u64, 0)).await
+
+    pub fn new_with_user_config(user_config: &UserConfig)-> Self{
+        Self{
+            reqwest_client: HttpClientFactory::new(user_config.get_http_settings().clone()).create_client().expect("failed to create http client"),
+            credential_retriever: CredentialRetriever::new(user_config.get_credentials().clone(), read_netrc()),
+            retry_policy: RetryPolicy::new(user_config.get_retry_settings()),
         }
+    }
+    async fn repeat_request(&self, url: &str) -> Result<Response> {
+        let result = self.retry_policy.send_with_retries(url, || self.make_request(url)).await;
         Ok(result.context(format!("failed to download file: {}", url))?)
     }
+
+    async fn make_request(&self, url: &str) -> reqwest::Result<Response> {
+        authorize_request(self.reqwest_client.head(url), url, &self.credential_retriever).send().await
+    }
 }
 
 #[async_trait]
@@ -43,9 +55,18 @@ impl FileDownloaderTrait for DownloadChecker {
         println!("{} validated!", url);
         Ok(PathBuf::new())
     }
-    async fn download_file(&self, _: &str, _: &Path){
+    async fn download_file(&self, _: &str, _: &Path) -> Result<()> {
+        Ok(())
+    }
+}
 
+// nothing is kept, the checker only confirms the url responds
+#[async_trait]
+impl ArtifactCacheTrait for DownloadChecker {
+    async fn get_artifact(&self, url: &str) -> Result<PathBuf> {
+        self.download_file_to_directory(url, Path::new(".")).await
     }
+    fn remove_expired_artifacts(&self) {}
 }
 
 #[cfg(test)]
@@ -3,6 +3,7 @@ use solipath_lib::solipath_instructions::data::dependency::Dependency;
 use std::path::Path;
 use solipath_lib::solipath_instructions::data::dependency_instructions::DependencyInstructions;
 use solipath_lib::solipath_instructions::data::install_instructions::InstallInstructions;
+use solipath_lib::solipath_instructions::file_format::{has_supported_extension, parse_file};
 use std::fs::read_to_string;
 pub struct InstallFileLooper;
 
@@ -13,14 +14,14 @@ impl InstallFileLooper {
     }
 
     pub fn retrieve_all_dependency_instructions(&self, path: &Path)-> Vec<DependencyInstructions> {
-        let full_path = format!("{}/{}", path.to_str().unwrap(), "**/install_instructions.json");
+        let full_path = format!("{}/{}", path.to_str().unwrap(), "**/install_instructions.*");
         glob(&full_path).unwrap().into_iter()
-        .filter(|path| path.is_ok())
+        .filter(|path| path.as_ref().is_ok_and(|path| has_supported_extension(path)))
         .map(|path| {
             let matched_path = path.expect("one of the paths are not valid!");
             println!("found {:?}", matched_path);
             let error_string = format!("failed to parse the file at path: {}", matched_path.to_str().unwrap());
-            let instructions: InstallInstructions = serde_json::from_str(&read_to_string(&matched_path).unwrap()).expect(&error_string);
+            let instructions: InstallInstructions = parse_file(&matched_path, &read_to_string(&matched_path).unwrap());
             let version_folder = matched_path.parent().expect(&error_string);
             let version = version_folder.file_name().expect(&error_string).to_str().expect(&error_string);
             let dependency_name = version_folder.parent().expect(&error_string).file_name().expect(&error_string).to_str().expect(&error_string);
@@ -63,6 +64,7 @@ mod tests {
         create_empty_json_at_path(&temp_dir, "name1/version1/install_instructions.json");
         create_empty_json_at_path(&temp_dir, "name2/version2/install_instructions.json");
         create_empty_json_at_path(&temp_dir, "name1/template/template1.json");
+        create_empty_json_at_path(&temp_dir, "name1/version1/install_instructions.json.bak");
         let install_file_looper = InstallFileLooper::new();
         let dependency_instructions = install_file_looper.retrieve_all_dependency_instructions(&temp_dir);
         assert_eq!(dependency_instructions, vec!(
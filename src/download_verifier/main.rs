@@ -5,6 +5,7 @@ mod fake_decompressor;
 use install_file_looper::InstallFileLooper;
 use download_checker::DownloadChecker;
 use solipath_lib::async_loop::run_async;
+use solipath_lib::solipath_config::user_config_retriever::{UserConfigRetriever, UserConfigRetrieverTrait};
 use solipath_lib::solipath_directory::moveable_home_directory_finder::MoveableHomeDirectoryFinder;
 use solipath_lib::solipath_download::dependency_downloader::DependencyDownloaderTrait;
 use solipath_lib::solipath_instructions::data::dependency_instructions::VecDependencyInstructions;
@@ -17,6 +18,7 @@ use solipath_lib::solipath_template::template_retriever::TemplateRetriever;
 use solipath_lib::solipath_template::template_variable_replacer::TemplateVariableReplacer;
 use solipath_lib::solipath_download::file_to_string_downloader::FileToStringDownloader;
 use solipath_lib::solipath_download::conditional_file_downloader::ConditionalFileDownloader;
+use solipath_lib::solipath_platform::current_platform_retriever::CurrentPlatformRetriever;
 
 
 
@@ -28,18 +30,31 @@ async fn main() {
 
 async fn run_for_path(starting_path: PathBuf){
     let install_file_looper = InstallFileLooper::new();
-    let download_checker = Arc::new(DownloadChecker::new());
-    let moveable_home_directory_finder = Arc::new(MoveableHomeDirectoryFinder::new(starting_path.clone()));
+    let user_config = UserConfigRetriever::new().get_user_config();
+    let download_checker = Arc::new(DownloadChecker::new_with_user_config(&user_config));
+    let current_platform_retriever = Arc::new(CurrentPlatformRetriever::new());
+    let moveable_home_directory_finder = Arc::new(MoveableHomeDirectoryFinder::new_with_platform_retriever(
+        starting_path.clone(),
+        current_platform_retriever.clone(),
+    ));
     let file_decompressor = Arc::new(FakeDecompressor::new());
-    let conditional_file_downloader = Arc::new(ConditionalFileDownloader::new(download_checker.clone(), file_decompressor));
+    let conditional_file_downloader = Arc::new(ConditionalFileDownloader::new_with_max_parallel_downloads(
+        download_checker.clone(),
+        file_decompressor,
+        download_checker.clone(),
+        user_config.get_download_settings().max_parallel_downloads,
+    ));
     let template_variable_replacer = Arc::new(TemplateVariableReplacer::new());
     let file_to_string_downloader = Arc::new(FileToStringDownloader::new(conditional_file_downloader.clone()));
-    let template_retriever = Arc::new(TemplateRetriever::new(file_to_string_downloader.clone(), moveable_home_directory_finder.clone(), template_variable_replacer.clone()));
+    let template_retriever = Arc::new(TemplateRetriever::new(file_to_string_downloader.clone(), moveable_home_directory_finder.clone(), template_variable_replacer.clone(), current_platform_retriever));
     let dependency_downloader = Arc::new(DependencyDownloader::new(moveable_home_directory_finder.clone(), conditional_file_downloader.clone()));
     let mut dependency_instructions = install_file_looper.retrieve_all_dependency_instructions(&starting_path);
     let mut template_instructions = run_async(&dependency_instructions.get_templates(), |(dependency, template)| {
         template_retriever.retrieve_instructions_from_template(dependency, template)
-    }).await;
+    }).await
+        .into_iter()
+        .flat_map(|instructions| instructions.unwrap_or_else(|error| panic!("{:#}", error)))
+        .collect();
     dependency_instructions.append(&mut template_instructions);
     run_async(&dependency_instructions.get_downloads(), |(dependency, download_instruction)|{
         dependency_downloader.download_dependency(dependency, download_instruction)
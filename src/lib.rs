@@ -1,4 +1,5 @@
 pub mod solipath_shell;
+pub mod solipath_config;
 pub mod solipath_directory;
 pub mod solipath_download;
 pub mod solipath_environment_variable;
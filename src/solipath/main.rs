@@ -1,22 +1,33 @@
 use solipath_lib::{
     solipath_cli::cli::{is_solipath_command, SolipathCli},
+    solipath_config::user_config_retriever::{UserConfigRetriever, UserConfigRetrieverTrait},
     solipath_execute::command_with_path_executor::CommandWithPathExecutor,
+    solipath_shell::command_executor::{stop_running_commands_when_interrupted, RUNNING_COMMANDS},
 };
 
 #[tokio::main]
 async fn main() {
+    let interrupted = stop_running_commands_when_interrupted(&RUNNING_COMMANDS);
+    tokio::spawn(async move { std::process::exit(interrupted.await) });
     let arguments: Vec<String> = std::env::args().collect();
     let arguments_without_the_solipath_executable = &arguments[1..];
+    let user_config = UserConfigRetriever::new().get_user_config();
     if is_solipath_command(arguments_without_the_solipath_executable) {
-        SolipathCli::new()
+        SolipathCli::new_with_user_config(user_config)
             .run_solipath_command(&arguments_without_the_solipath_executable)
             .await
-            .expect("failed to run cli command");
+            .unwrap_or_else(|error| exit_with_error(error));
     } else {
-        let command_with_path_executor = CommandWithPathExecutor::new();
+        let command_with_path_executor = CommandWithPathExecutor::new_with_user_config(&user_config);
         let exit_status = command_with_path_executor
             .set_path_from_solipath_file_and_execute_command(arguments_without_the_solipath_executable)
-            .await;
+            .await
+            .unwrap_or_else(|error| exit_with_error(error));
         std::process::exit(exit_status.code().unwrap_or_else(|| 1));
     }
 }
+
+fn exit_with_error(error: anyhow::Error) -> ! {
+    eprintln!("solipath: {:#}", error);
+    std::process::exit(1);
+}
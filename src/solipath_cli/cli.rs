@@ -1,41 +1,180 @@
-use std::{fs, path::Path, sync::Arc};
+use std::{fs, path::PathBuf, sync::Arc};
 
 use crate::{
-    solipath_directory::solipath_directory_finder::{SolipathDirectoryFinder, SolipathDirectoryFinderTrait},
-    solipath_download::file_downloader::{FileDownloader, FileDownloaderTrait},
+    solipath_config::{
+        user_config::UserConfig,
+        user_config_editor::{hide_credentials, UserConfigEditor},
+        user_config_retriever::{UserConfigRetriever, UserConfigRetrieverTrait},
+    },
+    solipath_download::{
+        file_downloader::{FileDownloader, FileDownloaderTrait},
+        file_permissions::set_file_as_executable,
+    },
+    solipath_execute::command_with_path_executor::CommandWithPathExecutor,
     solipath_platform::{
         current_platform_retriever::{CurrentPlatformRetriever, CurrentPlatformRetrieverTrait},
         platform::Platform,
     },
 };
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+
+const SHOW_CREDENTIALS_FLAG: &str = "--show-credentials";
 
+// run and config are solipath commands, a program with either name can be run with solipath -- run
 pub fn is_solipath_command(commands: &[String]) -> bool {
-    commands[0].starts_with("--")
+    commands[0].starts_with("--") || commands[0] == "run" || commands[0] == "config"
 }
 pub struct SolipathCli {
     file_downloader: Arc<dyn FileDownloaderTrait + Sync + Send>,
-    directory_finder: Arc<dyn SolipathDirectoryFinderTrait + Sync + Send>,
+    current_executable: Option<PathBuf>,
     current_platform_retriever: Arc<dyn CurrentPlatformRetrieverTrait + Sync + Send>,
+    user_config: UserConfig,
 }
 impl SolipathCli {
     pub fn new() -> Self {
+        Self::new_with_user_config(UserConfigRetriever::new().get_user_config())
+    }
+
+    pub fn new_with_user_config(user_config: UserConfig) -> Self {
         Self {
-            file_downloader: Arc::new(FileDownloader::new()),
-            directory_finder: Arc::new(SolipathDirectoryFinder::new()),
+            file_downloader: Arc::new(FileDownloader::new_with_user_config(&user_config)),
+            current_executable: std::env::current_exe().ok(),
             current_platform_retriever: Arc::new(CurrentPlatformRetriever::new()),
+            user_config,
         }
     }
     pub async fn run_solipath_command(&self, commands: &[String])-> Result<()> {
+        self.run_solipath_command_with_config(commands, None).await
+    }
+
+    async fn run_solipath_command_with_config(&self, commands: &[String], config_path: Option<PathBuf>) -> Result<()> {
         match commands[0].as_str() {
+            "--" => self.run_command(commands, config_path).await,
+            "--config" => self.with_config(commands).await,
             "--update" => self.update_solipath().await,
+            "--which" => self.which(commands, config_path).await,
+            "--list" => self.list(config_path),
+            "--hermetic" => self.hermetic(commands, config_path).await,
+            "run" => self.run_script(commands, config_path).await,
+            "config" => self.config(commands),
             _ => {Ok(())}
         }
     }
 
+    // --config <path> can come before any other command, or before the command to run
+    async fn with_config(&self, commands: &[String]) -> Result<()> {
+        let config_path = commands
+            .get(1)
+            .map(PathBuf::from)
+            .context("--config needs the path to a solipath file, e.g. --config ../solipath.json")?;
+        let commands = commands
+            .get(2..)
+            .filter(|commands| !commands.is_empty())
+            .context("--config needs a command to run after the path")?;
+        if is_solipath_command(commands) {
+            return Box::pin(self.run_solipath_command_with_config(commands, Some(config_path))).await;
+        }
+        let exit_status = self.get_command_with_path_executor(Some(config_path))
+            .set_path_from_solipath_file_and_execute_command(commands)
+            .await?;
+        std::process::exit(exit_status.code().unwrap_or(1));
+    }
+
+    async fn run_command(&self, commands: &[String], config_path: Option<PathBuf>) -> Result<()> {
+        let commands = commands
+            .get(1..)
+            .filter(|commands| !commands.is_empty())
+            .context("-- needs a command to run after it, e.g. -- run")?;
+        let exit_status = self
+            .get_command_with_path_executor(config_path)
+            .set_path_from_solipath_file_and_execute_command(commands)
+            .await?;
+        std::process::exit(exit_status.code().unwrap_or(1));
+    }
+
+    async fn which(&self, commands: &[String], config_path: Option<PathBuf>) -> Result<()> {
+        let command = commands.get(1).context("--which needs the name of a command, e.g. --which java")?;
+        let report = self.get_command_with_path_executor(config_path)
+            .get_which_report_from_solipath_file(command)
+            .await?;
+        println!("{}", report);
+        Ok(())
+    }
+
+    fn list(&self, config_path: Option<PathBuf>) -> Result<()> {
+        println!("{}", self.get_command_with_path_executor(config_path).get_dependency_list_from_solipath_file()?);
+        Ok(())
+    }
+
+    async fn hermetic(&self, commands: &[String], config_path: Option<PathBuf>) -> Result<()> {
+        let commands = commands
+            .get(1..)
+            .filter(|commands| !commands.is_empty())
+            .context("--hermetic needs a command to run, e.g. --hermetic gradle build")?;
+        let exit_status = self.get_command_with_path_executor(config_path)
+            .set_path_from_solipath_file_and_execute_hermetic_command(commands)
+            .await?;
+        std::process::exit(exit_status.code().unwrap_or(1));
+    }
+
+    async fn run_script(&self, commands: &[String], config_path: Option<PathBuf>) -> Result<()> {
+        let name = commands.get(1).context("run needs the name of a script from solipath.json, e.g. run build")?;
+        let exit_status = self.get_command_with_path_executor(config_path)
+            .run_script_from_solipath_file(name, &commands[2..])
+            .await?;
+        std::process::exit(exit_status.code().unwrap_or(1));
+    }
+
+    // reads and edits the user config file, not solipath.json. credentials are hidden unless --show-credentials is given
+    fn config(&self, commands: &[String]) -> Result<()> {
+        let user_config_editor = UserConfigEditor::new();
+        let show_credentials = commands.iter().any(|command| command == SHOW_CREDENTIALS_FLAG);
+        let commands: Vec<String> = commands
+            .iter()
+            .filter(|command| *command != SHOW_CREDENTIALS_FLAG)
+            .cloned()
+            .collect();
+        let display = |key: &str, value: String| {
+            if show_credentials {
+                value
+            } else {
+                hide_credentials(key, value)
+            }
+        };
+        match commands.get(1).map(String::as_str) {
+            Some("get") => {
+                let key = commands.get(2).context("config get needs a key, e.g. config get http.proxy")?;
+                match user_config_editor.get(key)? {
+                    Some(value) => println!("{}", display(key, value)),
+                    None => bail!("{} is not set in {}", key, user_config_editor.get_config_path().display()),
+                }
+            }
+            Some("set") => {
+                let (key, value) = commands
+                    .get(2)
+                    .zip(commands.get(3))
+                    .context("config set needs a key and a value, e.g. config set downloads.max_parallel_downloads 8")?;
+                user_config_editor.set(key, value)?;
+            }
+            Some("list") => {
+                println!("# {}", user_config_editor.get_config_path().display());
+                user_config_editor
+                    .list()?
+                    .into_iter()
+                    .for_each(|(key, value)| println!("{} = {}", key, display(&key, value)));
+            }
+            _ => bail!("config needs get, set or list, e.g. config get http.proxy"),
+        }
+        Ok(())
+    }
+
+    // the running executable is replaced, wherever it was installed
     async fn update_solipath(&self)-> Result<()> {
         let Platform { os, arch } = self.current_platform_retriever.get_current_platform();
-        let solipath_directory = self.directory_finder.get_base_solipath_directory();
+        let original_executable = self
+            .current_executable
+            .clone()
+            .context("failed to find the path of the running solipath executable")?;
         let file_extension = get_executable_file_extension(&os);
         let solipath_url = format!(
             "https://github.com/Solipath/Solipath/releases/download/latest-{}_{}/solipath{}",
@@ -43,14 +182,11 @@ impl SolipathCli {
             arch.unwrap(),
             file_extension
         );
-        let mut original_executable = solipath_directory.clone();
-        original_executable.push(format!("solipath{}", file_extension));
-        let mut renamed_executable = solipath_directory.clone();
-        renamed_executable.push(format!("solipathold{}", file_extension));
+        let renamed_executable = original_executable.with_file_name(format!("solipathold{}", file_extension));
         fs::rename(&original_executable, &renamed_executable)
             .with_context(||"failed to move current solipath executable, this might mean a process is holding onto the file, or you don't have permission to move it.")?;
         self.file_downloader
-            .download_file_to_directory(&solipath_url, &solipath_directory)
+            .download_file(&solipath_url, &original_executable)
             .await
             .and_then(|_|{
                 set_file_as_executable(&original_executable);
@@ -62,16 +198,15 @@ impl SolipathCli {
                 Err(error)
             })
     }
-}
 
-#[cfg(not(target_os = "windows"))]
-fn set_file_as_executable(executable_path: &Path) {
-    use std::os::unix::fs::PermissionsExt;
-    fs::set_permissions(executable_path, fs::Permissions::from_mode(0o775))
-        .expect("failed to set solipath executable with execute permissions");
+    fn get_command_with_path_executor(&self, config_path: Option<PathBuf>) -> CommandWithPathExecutor {
+        let mut command_with_path_executor = CommandWithPathExecutor::new_with_user_config(&self.user_config);
+        if let Some(config_path) = config_path {
+            command_with_path_executor.set_config_path(config_path);
+        }
+        command_with_path_executor
+    }
 }
-#[cfg(target_os = "windows")]
-fn set_file_as_executable(executable_path: &Path) {}
 
 fn get_executable_file_extension(os: &str) -> String {
     if os == "windows" {
@@ -83,14 +218,13 @@ fn get_executable_file_extension(os: &str) -> String {
 
 #[cfg(test)]
 mod test {
-    use std::{fs, path::PathBuf};
+    use std::fs;
 
     use anyhow::Error;
     use mockall::predicate::eq;
     use tempfile::tempdir;
 
     use crate::{
-        solipath_directory::solipath_directory_finder::MockSolipathDirectoryFinderTrait,
         solipath_download::file_downloader::MockFileDownloaderTrait,
         solipath_platform::current_platform_retriever::MockCurrentPlatformRetrieverTrait,
     };
@@ -102,6 +236,16 @@ mod test {
         assert_eq!(is_solipath_command(&["--a-command".to_string()]), true);
     }
 
+    #[test]
+    fn is_solipath_command_returns_true_for_run() {
+        assert_eq!(is_solipath_command(&["run".to_string(), "build".to_string()]), true);
+    }
+
+    #[test]
+    fn is_solipath_command_returns_true_for_config() {
+        assert_eq!(is_solipath_command(&["config".to_string(), "list".to_string()]), true);
+    }
+
     #[test]
     fn is_solipath_command_returns_false_if_first_command_does_not_start_with_dashes() {
         assert_eq!(is_solipath_command(&["a-command".to_string()]), false);
@@ -116,16 +260,12 @@ mod test {
             get_executable_file_extension(std::env::consts::OS)
         ));
         fs::write(&solipath_executable, "not a real executable").unwrap();
-        let mut mock_directory_finder = MockSolipathDirectoryFinderTrait::new();
-        mock_directory_finder
-            .expect_get_base_solipath_directory()
-            .once()
-            .return_const(solipath_temp_dir.clone());
 
         let solipath_cli = SolipathCli {
-            file_downloader: Arc::new(FileDownloader::new()),
-            directory_finder: Arc::new(mock_directory_finder),
+            file_downloader: Arc::new(FileDownloader::new_with_user_config(&UserConfig::default())),
+            current_executable: Some(solipath_executable.clone()),
             current_platform_retriever: Arc::new(CurrentPlatformRetriever::new()),
+            user_config: UserConfig::default(),
         };
         solipath_cli.run_solipath_command(&["--update".to_string()]).await.unwrap();
 
@@ -136,12 +276,12 @@ mod test {
     #[tokio::test]
     async fn invalid_command_does_nothing() {
         let solipath_temp_dir = tempdir().unwrap().into_path();
-        let mock_directory_finder = MockSolipathDirectoryFinderTrait::new();
 
         let solipath_cli = SolipathCli {
-            file_downloader: Arc::new(FileDownloader::new()),
-            directory_finder: Arc::new(mock_directory_finder),
+            file_downloader: Arc::new(FileDownloader::new_with_user_config(&UserConfig::default())),
+            current_executable: None,
             current_platform_retriever: Arc::new(CurrentPlatformRetriever::new()),
+            user_config: UserConfig::default(),
         };
         solipath_cli
             .run_solipath_command(&["--not-real-command".to_string()])
@@ -150,17 +290,26 @@ mod test {
         assert!(directory.next().is_none())
     }
 
+    #[tokio::test]
+    async fn separator_needs_a_command_after_it() {
+        let solipath_cli = SolipathCli {
+            file_downloader: Arc::new(FileDownloader::new_with_user_config(&UserConfig::default())),
+            current_executable: None,
+            current_platform_retriever: Arc::new(CurrentPlatformRetriever::new()),
+            user_config: UserConfig::default(),
+        };
+
+        let error = solipath_cli.run_solipath_command(&["--".to_string()]).await.unwrap_err();
+
+        assert_eq!("-- needs a command to run after it, e.g. -- run", error.to_string());
+    }
+
     #[tokio::test]
     async fn linux_downloads_solipath_without_extention() {
         let solipath_temp_dir = tempdir().unwrap().into_path();
         let mut fake_solipath_executable = solipath_temp_dir.clone();
         fake_solipath_executable.push("solipath");
         fs::write(&fake_solipath_executable, "not a real executable").unwrap();
-        let mut mock_directory_finder = MockSolipathDirectoryFinderTrait::new();
-        mock_directory_finder
-            .expect_get_base_solipath_directory()
-            .once()
-            .return_const(solipath_temp_dir.clone());
         let mut mock_current_platform_retriever = MockCurrentPlatformRetrieverTrait::new();
         mock_current_platform_retriever
             .expect_get_current_platform()
@@ -168,20 +317,21 @@ mod test {
             .return_const(Platform::new("linux", "x86_64"));
         let mut mock_download = MockFileDownloaderTrait::new();
         mock_download
-            .expect_download_file_to_directory()
+            .expect_download_file()
             .with(
                 eq("https://github.com/Solipath/Solipath/releases/download/latest-linux_x86_64/solipath"),
-                eq(solipath_temp_dir.clone()),
+                eq(fake_solipath_executable.clone()),
             )
             .once()
-            .returning(move |_, _| {
-                fs::write(&fake_solipath_executable, "not a real executable").unwrap();
-                Ok(PathBuf::new())
+            .returning(|_, path| {
+                fs::write(path, "not a real executable").unwrap();
+                Ok(())
             });
         let solipath_cli = SolipathCli {
             file_downloader: Arc::new(mock_download),
-            directory_finder: Arc::new(mock_directory_finder),
+            current_executable: Some(fake_solipath_executable.clone()),
             current_platform_retriever: Arc::new(mock_current_platform_retriever),
+            user_config: UserConfig::default(),
         };
         solipath_cli.run_solipath_command(&["--update".to_string()]).await.unwrap();
     }
@@ -192,11 +342,6 @@ mod test {
         let mut fake_solipath_executable = solipath_temp_dir.clone();
         fake_solipath_executable.push("solipath");
         fs::write(&fake_solipath_executable, "not a real executable").unwrap();
-        let mut mock_directory_finder = MockSolipathDirectoryFinderTrait::new();
-        mock_directory_finder
-            .expect_get_base_solipath_directory()
-            .once()
-            .return_const(solipath_temp_dir.clone());
         let mut mock_current_platform_retriever = MockCurrentPlatformRetrieverTrait::new();
         mock_current_platform_retriever
             .expect_get_current_platform()
@@ -204,17 +349,18 @@ mod test {
             .return_const(Platform::new("linux", "x86_64"));
         let mut mock_download = MockFileDownloaderTrait::new();
         mock_download
-            .expect_download_file_to_directory()
+            .expect_download_file()
             .with(
                 eq("https://github.com/Solipath/Solipath/releases/download/latest-linux_x86_64/solipath"),
-                eq(solipath_temp_dir.clone()),
+                eq(fake_solipath_executable.clone()),
             )
             .once()
             .returning(move |_, _| Err(Error::msg("something went wrong")));
         let solipath_cli = SolipathCli {
             file_downloader: Arc::new(mock_download),
-            directory_finder: Arc::new(mock_directory_finder),
+            current_executable: Some(fake_solipath_executable.clone()),
             current_platform_retriever: Arc::new(mock_current_platform_retriever),
+            user_config: UserConfig::default(),
         };
         assert!(solipath_cli.run_solipath_command(&["--update".to_string()]).await.is_err());
         assert_eq!(
@@ -229,11 +375,6 @@ mod test {
         let mut fake_solipath_executable = solipath_temp_dir.clone();
         fake_solipath_executable.push("solipath.exe");
         fs::write(&fake_solipath_executable, "not a real executable").unwrap();
-        let mut mock_directory_finder = MockSolipathDirectoryFinderTrait::new();
-        mock_directory_finder
-            .expect_get_base_solipath_directory()
-            .once()
-            .return_const(solipath_temp_dir.clone());
         let mut mock_current_platform_retriever = MockCurrentPlatformRetrieverTrait::new();
         mock_current_platform_retriever
             .expect_get_current_platform()
@@ -241,20 +382,21 @@ mod test {
             .return_const(Platform::new("windows", "x86_64"));
         let mut mock_download = MockFileDownloaderTrait::new();
         mock_download
-            .expect_download_file_to_directory()
+            .expect_download_file()
             .with(
                 eq("https://github.com/Solipath/Solipath/releases/download/latest-windows_x86_64/solipath.exe"),
-                eq(solipath_temp_dir.clone()),
+                eq(fake_solipath_executable.clone()),
             )
             .once()
-            .returning(move |_, _| {
-                fs::write(&fake_solipath_executable, "not a real executable").unwrap();
-                Ok(PathBuf::new())
+            .returning(|_, path| {
+                fs::write(path, "not a real executable").unwrap();
+                Ok(())
             });
         let solipath_cli = SolipathCli {
             file_downloader: Arc::new(mock_download),
-            directory_finder: Arc::new(mock_directory_finder),
+            current_executable: Some(fake_solipath_executable.clone()),
             current_platform_retriever: Arc::new(mock_current_platform_retriever),
+            user_config: UserConfig::default(),
         };
         solipath_cli.run_solipath_command(&["--update".to_string()]).await.unwrap();
     }
@@ -0,0 +1,3 @@
+pub mod user_config;
+pub mod user_config_editor;
+pub mod user_config_retriever;
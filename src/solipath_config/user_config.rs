@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+pub const DEFAULT_REGISTRY: &str = "https://raw.githubusercontent.com/Solipath/Solipath-Install-Instructions/main";
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct UserConfig {
+    base_directory: Option<PathBuf>,
+    #[serde(default = "default_registries")]
+    registries: Vec<String>,
+    #[serde(default = "default_http_settings")]
+    http: HttpSettings,
+    #[serde(default = "default_credentials")]
+    credentials: HashMap<String, CredentialSettings>,
+    #[serde(default = "default_retry_settings")]
+    retry: RetrySettings,
+    #[serde(default = "default_download_settings")]
+    downloads: DownloadSettings,
+    #[serde(default = "default_cache_settings")]
+    cache: CacheSettings,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        Self {
+            base_directory: None,
+            registries: default_registries(),
+            http: default_http_settings(),
+            credentials: default_credentials(),
+            retry: default_retry_settings(),
+            downloads: default_download_settings(),
+            cache: default_cache_settings(),
+        }
+    }
+}
+
+impl UserConfig {
+    // SOLIPATH_HOME still wins over this
+    pub fn get_base_directory(&self) -> Option<&PathBuf> {
+        self.base_directory.as_ref()
+    }
+
+    // install instructions and templates are looked for in each registry in order
+    pub fn get_registries(&self) -> &Vec<String> {
+        &self.registries
+    }
+
+    pub fn get_http_settings(&self) -> &HttpSettings {
+        &self.http
+    }
+
+    pub fn get_credentials(&self) -> &HashMap<String, CredentialSettings> {
+        &self.credentials
+    }
+
+    pub fn get_retry_settings(&self) -> &RetrySettings {
+        &self.retry
+    }
+
+    pub fn get_download_settings(&self) -> &DownloadSettings {
+        &self.downloads
+    }
+
+    pub fn get_cache_settings(&self) -> &CacheSettings {
+        &self.cache
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct HttpSettings {
+    pub proxy: Option<String>,
+    pub no_proxy: Option<String>,
+    pub ca_bundle: Option<PathBuf>,
+    pub client_certificate: Option<PathBuf>,
+    pub client_key: Option<PathBuf>,
+    pub connect_timeout_seconds: Option<u64>,
+    pub read_timeout_seconds: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct RetrySettings {
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    #[serde(default = "default_initial_backoff_milliseconds")]
+    pub initial_backoff_milliseconds: u64,
+    #[serde(default = "default_max_backoff_milliseconds")]
+    pub max_backoff_milliseconds: u64,
+    #[serde(default = "default_max_total_seconds")]
+    pub max_total_seconds: u64,
+    #[serde(default = "default_jitter")]
+    pub jitter: bool,
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_max_attempts(),
+            initial_backoff_milliseconds: default_initial_backoff_milliseconds(),
+            max_backoff_milliseconds: default_max_backoff_milliseconds(),
+            max_total_seconds: default_max_total_seconds(),
+            jitter: default_jitter(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct DownloadSettings {
+    #[serde(default = "default_max_parallel_downloads")]
+    pub max_parallel_downloads: usize,
+}
+
+impl Default for DownloadSettings {
+    fn default() -> Self {
+        Self {
+            max_parallel_downloads: default_max_parallel_downloads(),
+        }
+    }
+}
+
+// archives not used for retention_days are removed from the artifact cache, 0 keeps nothing between runs
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct CacheSettings {
+    #[serde(default = "default_retention_days")]
+    pub retention_days: u64,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            retention_days: default_retention_days(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct CredentialSettings {
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub token: Option<String>,
+}
+
+fn default_registries() -> Vec<String> {
+    vec![DEFAULT_REGISTRY.to_string()]
+}
+
+fn default_http_settings() -> HttpSettings {
+    HttpSettings::default()
+}
+
+fn default_credentials() -> HashMap<String, CredentialSettings> {
+    HashMap::new()
+}
+
+fn default_retry_settings() -> RetrySettings {
+    RetrySettings::default()
+}
+
+fn default_max_attempts() -> u32 {
+    4
+}
+
+fn default_initial_backoff_milliseconds() -> u64 {
+    1000
+}
+
+fn default_max_backoff_milliseconds() -> u64 {
+    30000
+}
+
+fn default_max_total_seconds() -> u64 {
+    120
+}
+
+fn default_jitter() -> bool {
+    true
+}
+
+fn default_download_settings() -> DownloadSettings {
+    DownloadSettings::default()
+}
+
+fn default_max_parallel_downloads() -> usize {
+    4
+}
+
+fn default_cache_settings() -> CacheSettings {
+    CacheSettings::default()
+}
+
+fn default_retention_days() -> u64 {
+    30
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_config_has_no_http_settings() {
+        let user_config = toml::from_str::<UserConfig>("").unwrap();
+        assert_eq!(&HttpSettings::default(), user_config.get_http_settings());
+        assert_eq!(UserConfig::default(), user_config);
+    }
+
+    #[test]
+    fn can_parse_base_directory_and_registries() {
+        let user_config = toml::from_str::<UserConfig>(
+            r#"
+            base_directory = "/mnt/large-disk/solipath"
+            registries = ["https://mirror.example.com/solipath", "https://raw.githubusercontent.com/Solipath/Solipath-Install-Instructions/main"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(Some(&PathBuf::from("/mnt/large-disk/solipath")), user_config.get_base_directory());
+        assert_eq!("https://mirror.example.com/solipath", user_config.get_registries()[0]);
+        assert_eq!(&vec![DEFAULT_REGISTRY.to_string()], UserConfig::default().get_registries());
+    }
+
+    #[test]
+    fn can_parse_http_settings() {
+        let user_config = toml::from_str::<UserConfig>(
+            r#"
+            [http]
+            proxy = "http://proxy.example.com:8080"
+            no_proxy = "localhost,.example.com"
+            ca_bundle = "/etc/ssl/corporate.pem"
+            connect_timeout_seconds = 10
+            read_timeout_seconds = 30
+            "#,
+        )
+        .unwrap();
+        let http_settings = user_config.get_http_settings();
+        assert_eq!(Some("http://proxy.example.com:8080".to_string()), http_settings.proxy);
+        assert_eq!(Some("localhost,.example.com".to_string()), http_settings.no_proxy);
+        assert_eq!(Some(PathBuf::from("/etc/ssl/corporate.pem")), http_settings.ca_bundle);
+        assert_eq!(None, http_settings.client_certificate);
+        assert_eq!(Some(10), http_settings.connect_timeout_seconds);
+        assert_eq!(Some(30), http_settings.read_timeout_seconds);
+    }
+
+    #[test]
+    fn can_parse_credentials_per_host() {
+        let user_config = toml::from_str::<UserConfig>(
+            r#"
+            [credentials."artifactory.example.com"]
+            username = "builder"
+            password = "secret"
+
+            [credentials."api.github.com"]
+            token = "ghp_token"
+            "#,
+        )
+        .unwrap();
+        let credentials = user_config.get_credentials();
+        assert_eq!(Some("builder".to_string()), credentials["artifactory.example.com"].username);
+        assert_eq!(Some("secret".to_string()), credentials["artifactory.example.com"].password);
+        assert_eq!(Some("ghp_token".to_string()), credentials["api.github.com"].token);
+    }
+
+    #[test]
+    fn can_override_part_of_retry_settings() {
+        let user_config = toml::from_str::<UserConfig>(
+            r#"
+            [retry]
+            max_attempts = 6
+            jitter = false
+            "#,
+        )
+        .unwrap();
+        let retry_settings = user_config.get_retry_settings();
+        assert_eq!(6, retry_settings.max_attempts);
+        assert!(!retry_settings.jitter);
+        assert_eq!(RetrySettings::default().max_total_seconds, retry_settings.max_total_seconds);
+    }
+
+    #[test]
+    fn can_parse_max_parallel_downloads() {
+        let user_config = toml::from_str::<UserConfig>("[downloads]\nmax_parallel_downloads = 2").unwrap();
+        assert_eq!(2, user_config.get_download_settings().max_parallel_downloads);
+        assert_eq!(4, UserConfig::default().get_download_settings().max_parallel_downloads);
+    }
+
+    #[test]
+    fn can_parse_cache_retention() {
+        let user_config = toml::from_str::<UserConfig>("[cache]\nretention_days = 0").unwrap();
+        assert_eq!(0, user_config.get_cache_settings().retention_days);
+        assert_eq!(30, UserConfig::default().get_cache_settings().retention_days);
+    }
+}
@@ -0,0 +1,79 @@
+use std::fs::read_to_string;
+use std::path::PathBuf;
+
+use directories::BaseDirs;
+
+#[cfg(test)]
+use mockall::automock;
+
+use crate::solipath_config::user_config::UserConfig;
+
+#[cfg_attr(test, automock)]
+pub trait UserConfigRetrieverTrait {
+    fn get_user_config(&self) -> UserConfig;
+}
+
+pub struct UserConfigRetriever {
+    config_path: PathBuf,
+}
+
+impl UserConfigRetriever {
+    pub fn new() -> Self {
+        Self {
+            config_path: get_user_config_path(),
+        }
+    }
+
+    pub fn new_with_path(config_path: PathBuf) -> Self {
+        Self { config_path }
+    }
+}
+
+impl Default for UserConfigRetriever {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn get_user_config_path() -> PathBuf {
+    let mut config_path = BaseDirs::new().unwrap().config_dir().to_path_buf();
+    config_path.push("solipath");
+    config_path.push("config.toml");
+    config_path
+}
+
+impl UserConfigRetrieverTrait for UserConfigRetriever {
+    fn get_user_config(&self) -> UserConfig {
+        if !self.config_path.exists() {
+            return UserConfig::default();
+        }
+        let contents = read_to_string(&self.config_path)
+            .unwrap_or_else(|error| panic!("failed to read user config {:?}: {}", self.config_path, error));
+        toml::from_str(&contents)
+            .unwrap_or_else(|error| panic!("failed to parse user config {:?}: {}", self.config_path, error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn missing_config_file_returns_default_config() {
+        let config_path = tempdir().unwrap().into_path().join("config.toml");
+        let user_config = UserConfigRetriever::new_with_path(config_path).get_user_config();
+        assert_eq!(UserConfig::default(), user_config);
+    }
+
+    #[test]
+    fn reads_config_file() {
+        let config_path = tempdir().unwrap().into_path().join("config.toml");
+        fs::write(&config_path, "[http]\nread_timeout_seconds = 5\n").unwrap();
+        let user_config = UserConfigRetriever::new_with_path(config_path).get_user_config();
+        assert_eq!(Some(5), user_config.get_http_settings().read_timeout_seconds);
+    }
+}
@@ -0,0 +1,116 @@
+use std::fs::{create_dir_all, read_dir, read_to_string, remove_dir_all, rename, write};
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use anyhow::{Context, Result};
+
+const LAYOUT_VERSION_FILE: &str = "layout_version";
+const LAYOUT_VERSION: &str = "2";
+const LEGACY_DOWNLOADS_DIRECTORY: &str = ".legacy_downloads";
+
+// before layout 2 every version of a dependency shared <name>/downloads. Those folders can't be split by version,
+// so they are removed and each version is installed again into <name>/<version>/<os>-<arch>, reusing the archives
+// in the artifact cache. The folders are only moved aside here, deleting them happens in the background and is
+// picked up again by the next run if solipath exits first
+pub fn migrate_directory_layout(base_directory: &Path) -> Result<()> {
+    let layout_version_file = base_directory.join(LAYOUT_VERSION_FILE);
+    let legacy_downloads_directory = base_directory.join(LEGACY_DOWNLOADS_DIRECTORY);
+    if read_to_string(&layout_version_file).is_ok_and(|layout_version| layout_version.trim() == LAYOUT_VERSION) {
+        remove_in_background(legacy_downloads_directory);
+        return Ok(());
+    }
+    if base_directory.exists() {
+        let mut moved_directories = 0;
+        for entry in read_dir(base_directory)? {
+            let dependency_directory = entry?.path();
+            let shared_downloads_directory = dependency_directory.join("downloads");
+            if !shared_downloads_directory.is_dir() || !is_dependency_directory(&dependency_directory) {
+                continue;
+            }
+            create_dir_all(&legacy_downloads_directory)?;
+            let moved_directory = legacy_downloads_directory.join(dependency_directory.file_name().unwrap_or_default());
+            rename(&shared_downloads_directory, &moved_directory)
+                .with_context(|| format!("failed to move {:?} to {:?}", shared_downloads_directory, moved_directory))?;
+            moved_directories += 1;
+        }
+        if moved_directories > 0 {
+            println!(
+                "removing the shared downloads folders of {} dependencies, downloads are now kept separately for each version",
+                moved_directories
+            );
+        }
+    }
+    create_dir_all(base_directory)?;
+    write(&layout_version_file, LAYOUT_VERSION).context("failed to record solipath directory layout version")?;
+    remove_in_background(legacy_downloads_directory);
+    Ok(())
+}
+
+// a dependency folder holds <version>/install_instructions.* or templates, anything else in the solipath directory
+// isn't touched
+fn is_dependency_directory(directory: &Path) -> bool {
+    if directory.join("templates").is_dir() {
+        return true;
+    }
+    read_dir(directory)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .any(|version_directory| {
+            read_dir(version_directory.path())
+                .into_iter()
+                .flatten()
+                .flatten()
+                .any(|entry| entry.path().file_stem().is_some_and(|stem| stem == "install_instructions"))
+        })
+}
+
+fn remove_in_background(directory: PathBuf) {
+    if directory.exists() {
+        thread::spawn(move || remove_dir_all(&directory));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::create_dir_all;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn shared_downloads_directories_are_removed_once() {
+        let base_directory = tempdir().unwrap().into_path();
+        create_dir_all(base_directory.join("java/downloads/jdk-17")).unwrap();
+        create_dir_all(base_directory.join("java/21")).unwrap();
+        write(base_directory.join("java/21/install_instructions.json"), "{}").unwrap();
+        create_dir_all(base_directory.join("node/downloads")).unwrap();
+        create_dir_all(base_directory.join("node/templates")).unwrap();
+
+        migrate_directory_layout(&base_directory).unwrap();
+
+        assert!(!base_directory.join("java/downloads").exists());
+        assert!(!base_directory.join("node/downloads").exists());
+        assert!(base_directory.join("java/21/install_instructions.json").exists());
+
+        create_dir_all(base_directory.join("java/downloads")).unwrap();
+        migrate_directory_layout(&base_directory).unwrap();
+        assert!(base_directory.join("java/downloads").exists());
+    }
+
+    #[test]
+    fn downloads_directories_outside_of_dependencies_are_kept() {
+        let base_directory = tempdir().unwrap().into_path();
+        create_dir_all(base_directory.join("cache/downloads")).unwrap();
+        create_dir_all(base_directory.join("notes/1/downloads")).unwrap();
+        write(base_directory.join("notes/1/readme.txt"), "not a dependency").unwrap();
+        create_dir_all(base_directory.join("notes/downloads")).unwrap();
+
+        migrate_directory_layout(&base_directory).unwrap();
+
+        assert!(base_directory.join("cache/downloads").exists());
+        assert!(base_directory.join("notes/downloads").exists());
+    }
+}
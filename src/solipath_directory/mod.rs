@@ -1,2 +1,3 @@
+pub mod directory_layout_migration;
 pub mod solipath_directory_finder;
 pub mod moveable_home_directory_finder;
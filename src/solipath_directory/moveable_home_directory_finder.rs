@@ -1,14 +1,24 @@
 use std::path::PathBuf;
+use std::sync::Arc;
 
-use super::solipath_directory_finder::SolipathDirectoryFinderTrait;
+use super::solipath_directory_finder::{platform_directory_name, SolipathDirectoryFinderTrait};
+use crate::solipath_platform::current_platform_retriever::{CurrentPlatformRetriever, CurrentPlatformRetrieverTrait};
 
 pub struct MoveableHomeDirectoryFinder {
     base_dir: PathBuf,
+    current_platform_retriever: Arc<dyn CurrentPlatformRetrieverTrait + Sync + Send>,
 }
 
 impl MoveableHomeDirectoryFinder {
     pub fn new(base_dir: PathBuf) -> Self {
-        Self { base_dir }
+        Self::new_with_platform_retriever(base_dir, Arc::new(CurrentPlatformRetriever::new()))
+    }
+
+    pub fn new_with_platform_retriever(
+        base_dir: PathBuf,
+        current_platform_retriever: Arc<dyn CurrentPlatformRetrieverTrait + Sync + Send>,
+    ) -> Self {
+        Self { base_dir, current_platform_retriever }
     }
 }
 
@@ -16,4 +26,8 @@ impl SolipathDirectoryFinderTrait for MoveableHomeDirectoryFinder {
     fn get_base_solipath_directory(&self) -> PathBuf {
         self.base_dir.clone()
     }
+
+    fn get_platform_directory_name(&self) -> String {
+        platform_directory_name(self.current_platform_retriever.get_current_platform())
+    }
 }
\ No newline at end of file
@@ -1,8 +1,14 @@
-use std::path::PathBuf;
+use std::env::var_os;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use directories::UserDirs;
+use directories::{BaseDirs, UserDirs};
 
+use crate::solipath_config::user_config::UserConfig;
+use crate::solipath_config::user_config_retriever::{UserConfigRetriever, UserConfigRetrieverTrait};
 use crate::solipath_instructions::data::dependency::Dependency;
+use crate::solipath_platform::current_platform_retriever::{CurrentPlatformRetriever, CurrentPlatformRetrieverTrait};
+use crate::solipath_platform::platform::Platform;
 
 #[cfg(test)]
 use mockall::automock;
@@ -18,49 +24,159 @@ pub trait SolipathDirectoryFinderTrait {
         path
     }
 
+    // every version and platform gets its own directory, so installs of different versions never share files
     fn get_dependency_downloads_directory(&self, dependency: &Dependency) -> PathBuf {
-        let mut path = self.get_base_solipath_directory();
-        path.push(dependency.name.to_string());
-        path.push("downloads");
+        let mut path = self.get_dependency_version_directory(dependency);
+        path.push(self.get_platform_directory_name());
         path
     }
 
+    fn get_platform_directory_name(&self) -> String;
+
     fn get_dependency_template_directory(&self, dependency: &Dependency) -> PathBuf {
         let mut path = self.get_base_solipath_directory();
         path.push(dependency.name.to_string());
         path.push("templates");
         path
     }
+
+    // templates shared by every dependency, referenced as common:<name>
+    fn get_common_template_directory(&self) -> PathBuf {
+        let mut path = self.get_base_solipath_directory();
+        path.push("_common");
+        path.push("templates");
+        path
+    }
+
+    fn get_artifact_cache_directory(&self) -> PathBuf {
+        let mut path = self.get_base_solipath_directory();
+        path.push("cache");
+        path.push("artifacts");
+        path
+    }
+}
+
+pub fn platform_directory_name(platform: Platform) -> String {
+    let Platform { os, arch } = platform;
+    format!("{}-{}", os, arch.unwrap_or_default())
 }
 
 fn home_dir()-> PathBuf{
     UserDirs::new().unwrap().home_dir().to_path_buf()
 }
 
-pub struct SolipathDirectoryFinder {}
+pub struct SolipathDirectoryFinder {
+    base_directory: PathBuf,
+    artifact_cache_directory: PathBuf,
+    current_platform_retriever: Arc<dyn CurrentPlatformRetrieverTrait + Sync + Send>,
+}
+
+// the linux data and cache directories, which follow XDG_DATA_HOME and XDG_CACHE_HOME
+pub struct XdgDirectories {
+    pub data_directory: PathBuf,
+    pub cache_directory: PathBuf,
+}
 
 impl SolipathDirectoryFinder {
     pub fn new() -> Self {
-        Self {}
+        Self::new_with_user_config(
+            &UserConfigRetriever::new().get_user_config(),
+            Arc::new(CurrentPlatformRetriever::new()),
+        )
+    }
+
+    pub fn new_with_user_config(
+        user_config: &UserConfig,
+        current_platform_retriever: Arc<dyn CurrentPlatformRetrieverTrait + Sync + Send>,
+    ) -> Self {
+        let xdg_directories = if std::env::consts::OS == "linux" {
+            BaseDirs::new().map(|base_dirs| XdgDirectories {
+                data_directory: base_dirs.data_dir().to_path_buf(),
+                cache_directory: base_dirs.cache_dir().to_path_buf(),
+            })
+        } else {
+            None
+        };
+        Self::new_with_locations(
+            var_os("SOLIPATH_HOME").map(PathBuf::from),
+            user_config.get_base_directory().cloned(),
+            &home_dir(),
+            xdg_directories,
+            current_platform_retriever,
+        )
+    }
+
+    // SOLIPATH_HOME wins, then base_directory from the user config. An existing ~/solipath is kept so current
+    // installs don't download everything again, otherwise linux uses the XDG directories and everything else ~/solipath
+    pub fn new_with_locations(
+        solipath_home: Option<PathBuf>,
+        configured_base_directory: Option<PathBuf>,
+        home_directory: &Path,
+        xdg_directories: Option<XdgDirectories>,
+        current_platform_retriever: Arc<dyn CurrentPlatformRetrieverTrait + Sync + Send>,
+    ) -> Self {
+        let legacy_directory = home_directory.join("solipath");
+        let explicit_directory = solipath_home
+            .filter(|solipath_home| !solipath_home.as_os_str().is_empty())
+            .or(configured_base_directory);
+        match (explicit_directory, xdg_directories) {
+            (Some(base_directory), _) => Self::new_with_base_directory(base_directory, current_platform_retriever),
+            (None, Some(xdg_directories)) if !legacy_directory.exists() => Self {
+                base_directory: xdg_directories.data_directory.join("solipath"),
+                artifact_cache_directory: xdg_directories.cache_directory.join("solipath").join("artifacts"),
+                current_platform_retriever,
+            },
+            (None, _) => Self::new_with_base_directory(legacy_directory, current_platform_retriever),
+        }
+    }
+
+    fn new_with_base_directory(
+        base_directory: PathBuf,
+        current_platform_retriever: Arc<dyn CurrentPlatformRetrieverTrait + Sync + Send>,
+    ) -> Self {
+        Self {
+            artifact_cache_directory: base_directory.join("cache").join("artifacts"),
+            base_directory,
+            current_platform_retriever,
+        }
     }
 }
 
 impl SolipathDirectoryFinderTrait for SolipathDirectoryFinder {
     fn get_base_solipath_directory(&self) -> PathBuf {
-        let mut solipath = home_dir();
-        solipath.push("solipath");
-        solipath
+        self.base_directory.clone()
+    }
+
+    fn get_artifact_cache_directory(&self) -> PathBuf {
+        self.artifact_cache_directory.clone()
+    }
+
+    fn get_platform_directory_name(&self) -> String {
+        platform_directory_name(self.current_platform_retriever.get_current_platform())
     }
 }
 
 #[cfg(test)]
 mod tests {
 
+    use tempfile::tempdir;
+
     use super::*;
+    use crate::solipath_platform::current_platform_retriever::MockCurrentPlatformRetrieverTrait;
+
+    fn platform_retriever() -> Arc<MockCurrentPlatformRetrieverTrait> {
+        let mut platform_retriever = MockCurrentPlatformRetrieverTrait::new();
+        platform_retriever.expect_get_current_platform().returning(|| Platform::new("linux", "aarch64"));
+        Arc::new(platform_retriever)
+    }
+
+    fn finder_in_home(home_dir: &Path) -> SolipathDirectoryFinder {
+        SolipathDirectoryFinder::new_with_locations(None, None, home_dir, None, platform_retriever())
+    }
 
     #[test]
     fn base_solipath_directory_ends_with_solipath() {
-        let solipath_directory = SolipathDirectoryFinder::new().get_base_solipath_directory();
+        let solipath_directory = finder_in_home(&home_dir()).get_base_solipath_directory();
         let home_dir = home_dir().to_str().unwrap().to_string();
         assert_eq!(solipath_directory, PathBuf::from(format!("{}/solipath", home_dir)));
     }
@@ -68,7 +184,7 @@ mod tests {
     #[test]
     fn dependency_solipath_directory_ends_with_solipath_dependency_name_version() {
         let dependency = Dependency::new("java", "11");
-        let solipath_directory = SolipathDirectoryFinder::new().get_dependency_version_directory(&dependency);
+        let solipath_directory = finder_in_home(&home_dir()).get_dependency_version_directory(&dependency);
         let home_dir = home_dir().to_str().unwrap().to_string();
         assert_eq!(
             solipath_directory,
@@ -77,24 +193,85 @@ mod tests {
     }
 
     #[test]
-    fn dependency_downloads_solipath_directory_ends_with_solipath_dependency_name() {
+    fn dependency_downloads_solipath_directory_ends_with_solipath_dependency_name_version_platform() {
         let dependency = Dependency::new("node", "14");
-        let solipath_directory = SolipathDirectoryFinder::new().get_dependency_downloads_directory(&dependency);
+        let solipath_directory = finder_in_home(&home_dir()).get_dependency_downloads_directory(&dependency);
         let home_dir = home_dir().to_str().unwrap().to_string();
         assert_eq!(
             solipath_directory,
-            PathBuf::from(format!("{}/solipath/node/downloads", home_dir))
+            PathBuf::from(format!("{}/solipath/node/14/linux-aarch64", home_dir))
         );
     }
 
     #[test]
     fn dependency_template_solipath_directory_ends_with_solipath_dependency_name() {
         let dependency = Dependency::new("node", "14");
-        let solipath_directory = SolipathDirectoryFinder::new().get_dependency_template_directory(&dependency);
+        let solipath_directory = finder_in_home(&home_dir()).get_dependency_template_directory(&dependency);
         let home_dir = home_dir().to_str().unwrap().to_string();
         assert_eq!(
             solipath_directory,
             PathBuf::from(format!("{}/solipath/node/templates", home_dir))
         );
     }
+
+    fn xdg_directories() -> Option<XdgDirectories> {
+        Some(XdgDirectories {
+            data_directory: PathBuf::from("/home/me/.local/share"),
+            cache_directory: PathBuf::from("/home/me/.cache"),
+        })
+    }
+
+    #[test]
+    fn solipath_home_wins_over_user_config() {
+        let directory_finder = SolipathDirectoryFinder::new_with_locations(
+            Some(PathBuf::from("/mnt/large-disk/solipath")),
+            Some(PathBuf::from("/opt/solipath")),
+            Path::new("/home/me"),
+            xdg_directories(),
+            platform_retriever(),
+        );
+        assert_eq!(PathBuf::from("/mnt/large-disk/solipath"), directory_finder.get_base_solipath_directory());
+        assert_eq!(
+            PathBuf::from("/mnt/large-disk/solipath/cache/artifacts"),
+            directory_finder.get_artifact_cache_directory()
+        );
+
+        let directory_finder = SolipathDirectoryFinder::new_with_locations(
+            None,
+            Some(PathBuf::from("/opt/solipath")),
+            Path::new("/home/me"),
+            xdg_directories(),
+            platform_retriever(),
+        );
+        assert_eq!(PathBuf::from("/opt/solipath"), directory_finder.get_base_solipath_directory());
+    }
+
+    #[test]
+    fn xdg_directories_are_used_unless_home_solipath_already_exists() {
+        let home_directory = tempdir().unwrap().into_path();
+        let directory_finder =
+            SolipathDirectoryFinder::new_with_locations(
+                None,
+                None,
+                &home_directory,
+                xdg_directories(),
+                platform_retriever(),
+            );
+        assert_eq!(PathBuf::from("/home/me/.local/share/solipath"), directory_finder.get_base_solipath_directory());
+        assert_eq!(
+            PathBuf::from("/home/me/.cache/solipath/artifacts"),
+            directory_finder.get_artifact_cache_directory()
+        );
+
+        std::fs::create_dir(home_directory.join("solipath")).unwrap();
+        let directory_finder =
+            SolipathDirectoryFinder::new_with_locations(
+                None,
+                None,
+                &home_directory,
+                xdg_directories(),
+                platform_retriever(),
+            );
+        assert_eq!(home_directory.join("solipath"), directory_finder.get_base_solipath_directory());
+    }
 }
@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+use tokio::sync::{OnceCell, Semaphore};
+use tokio::task::spawn_blocking;
+
+#[cfg(test)]
+use mockall::automock;
+
+use crate::solipath_config::user_config::DownloadSettings;
+use crate::solipath_download::file_downloader::FileDownloaderTrait;
+
+const HASH_FILE_NAME: &str = ".sha256";
+const PARTIAL_DIRECTORY_PREFIX: &str = ".partial";
+
+#[cfg_attr(test, automock)]
+#[async_trait]
+pub trait ArtifactCacheTrait {
+    async fn get_artifact(&self, url: &str) -> Result<PathBuf>;
+    fn remove_expired_artifacts(&self);
+}
+
+// every downloaded archive is kept in <cache>/<sha256 of url>/<file name> next to a .sha256 file holding the hash
+// of its contents. An entry whose contents no longer match is downloaded again.
+pub struct ArtifactCache {
+    file_downloader: Arc<dyn FileDownloaderTrait + Sync + Send>,
+    cache_directory: PathBuf,
+    retention: Duration,
+    artifacts_in_flight: Mutex<HashMap<String, Arc<OnceCell<PathBuf>>>>,
+    download_permits: Arc<Semaphore>,
+}
+
+impl ArtifactCache {
+    pub fn new(
+        file_downloader: Arc<dyn FileDownloaderTrait + Sync + Send>,
+        cache_directory: PathBuf,
+        retention_days: u64,
+    ) -> Self {
+        Self::new_with_download_permits(
+            file_downloader,
+            cache_directory,
+            retention_days,
+            Arc::new(Semaphore::new(DownloadSettings::default().max_parallel_downloads)),
+        )
+    }
+
+    // the permits are shared with the other downloads, and only held while the archive comes over the network
+    pub fn new_with_download_permits(
+        file_downloader: Arc<dyn FileDownloaderTrait + Sync + Send>,
+        cache_directory: PathBuf,
+        retention_days: u64,
+        download_permits: Arc<Semaphore>,
+    ) -> Self {
+        Self {
+            file_downloader,
+            cache_directory,
+            retention: Duration::from_secs(retention_days * 24 * 60 * 60),
+            artifacts_in_flight: Mutex::new(HashMap::new()),
+            download_permits,
+        }
+    }
+
+    fn get_artifact_directory(&self, url: &str) -> PathBuf {
+        self.cache_directory.join(hash_bytes(url.as_bytes()))
+    }
+
+    async fn find_cached_artifact(&self, artifact_directory: &Path) -> Option<PathBuf> {
+        let expected_hash = fs::read_to_string(artifact_directory.join(HASH_FILE_NAME)).ok()?;
+        let artifact = fs::read_dir(artifact_directory)
+            .ok()?
+            .flatten()
+            .map(|entry| entry.path())
+            .find(|path| path.file_name() != Some(HASH_FILE_NAME.as_ref()))?;
+        let artifact_to_hash = artifact.clone();
+        let actual_hash = spawn_blocking(move || hash_file(&artifact_to_hash)).await.ok()?.ok()?;
+        (actual_hash == expected_hash.trim()).then_some(artifact)
+    }
+
+    async fn download_artifact(&self, url: &str) -> Result<PathBuf> {
+        let artifact_directory = self.get_artifact_directory(url);
+        if let Some(artifact) = self.find_cached_artifact(&artifact_directory).await {
+            mark_as_used(&artifact_directory);
+            return Ok(artifact);
+        }
+        fs::create_dir_all(&self.cache_directory).context("failed to create artifact cache directory")?;
+        let partial_directory = tempfile::Builder::new()
+            .prefix(PARTIAL_DIRECTORY_PREFIX)
+            .tempdir_in(&self.cache_directory)
+            .context("failed to create directory for download")?;
+        let permit = self.download_permits.acquire().await.context("download semaphore closed")?;
+        let downloaded_file = self
+            .file_downloader
+            .download_file_to_directory(url, partial_directory.path())
+            .await?;
+        drop(permit);
+        let downloaded_file_to_hash = downloaded_file.clone();
+        let hash = spawn_blocking(move || hash_file(&downloaded_file_to_hash)).await??;
+        fs::write(partial_directory.path().join(HASH_FILE_NAME), hash).context("failed to write artifact hash")?;
+        if artifact_directory.exists() {
+            fs::remove_dir_all(&artifact_directory).context("failed to remove outdated artifact")?;
+        }
+        fs::rename(partial_directory.into_path(), &artifact_directory).context("failed to move artifact into cache")?;
+        Ok(artifact_directory.join(downloaded_file.file_name().context("downloaded file has no name")?))
+    }
+}
+
+#[async_trait]
+impl ArtifactCacheTrait for ArtifactCache {
+    // concurrent requests for the same url share one download
+    async fn get_artifact(&self, url: &str) -> Result<PathBuf> {
+        let artifact_in_flight = self
+            .artifacts_in_flight
+            .lock()
+            .unwrap()
+            .entry(url.to_string())
+            .or_default()
+            .clone();
+        artifact_in_flight
+            .get_or_try_init(|| self.download_artifact(url))
+            .await
+            .cloned()
+    }
+
+    fn remove_expired_artifacts(&self) {
+        let Ok(entries) = fs::read_dir(&self.cache_directory) else {
+            return;
+        };
+        let now = SystemTime::now();
+        // .partial directories belong to downloads that are still running, possibly in another solipath process
+        entries
+            .flatten()
+            .filter(|entry| !entry.file_name().to_string_lossy().starts_with(PARTIAL_DIRECTORY_PREFIX))
+            .map(|entry| entry.path())
+            .for_each(|artifact_directory| {
+                let last_used = fs::metadata(artifact_directory.join(HASH_FILE_NAME))
+                    .or_else(|_| fs::metadata(&artifact_directory))
+                    .and_then(|metadata| metadata.modified());
+                let is_expired = last_used
+                    .map(|last_used| now.duration_since(last_used).unwrap_or_default() >= self.retention)
+                    .unwrap_or(true);
+                if is_expired && artifact_directory.is_dir() {
+                    fs::remove_dir_all(&artifact_directory)
+                        .unwrap_or_else(|error| println!("failed to remove {:?} from cache: {}", artifact_directory, error));
+                }
+            });
+    }
+}
+
+fn mark_as_used(artifact_directory: &Path) {
+    let _ = File::options()
+        .write(true)
+        .open(artifact_directory.join(HASH_FILE_NAME))
+        .and_then(|hash_file| hash_file.set_modified(SystemTime::now()));
+}
+
+fn hash_bytes(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+fn hash_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path).with_context(|| format!("failed to open {:?}", path))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher).with_context(|| format!("failed to hash {:?}", path))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::async_loop::run_async;
+    use crate::solipath_download::file_downloader::MockFileDownloaderTrait;
+
+    struct CountingFileDownloader {
+        number_of_downloads: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl FileDownloaderTrait for CountingFileDownloader {
+        async fn download_file_to_directory(&self, _: &str, directory_to_save_to: &Path) -> Result<PathBuf> {
+            self.number_of_downloads.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            let path = directory_to_save_to.join("archive.tar.gz");
+            fs::write(&path, "archive contents").unwrap();
+            Ok(path)
+        }
+
+        async fn download_file(&self, _: &str, _: &Path) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn counting_file_downloader() -> Arc<CountingFileDownloader> {
+        Arc::new(CountingFileDownloader {
+            number_of_downloads: AtomicUsize::new(0),
+        })
+    }
+
+    #[tokio::test]
+    async fn downloads_same_url_once_when_requested_concurrently() {
+        let file_downloader = counting_file_downloader();
+        let artifact_cache = ArtifactCache::new(file_downloader.clone(), tempdir().unwrap().into_path(), 30);
+        let urls = vec!["https://something.com/archive.tar.gz"; 5];
+
+        let artifacts = run_async(&urls, |url| artifact_cache.get_artifact(url)).await;
+
+        assert_eq!(1, file_downloader.number_of_downloads.load(Ordering::SeqCst));
+        let first_artifact = artifacts[0].as_ref().unwrap();
+        assert!(artifacts.iter().all(|artifact| artifact.as_ref().unwrap() == first_artifact));
+        assert_eq!("archive contents", fs::read_to_string(first_artifact).unwrap());
+    }
+
+    #[tokio::test]
+    async fn reuses_artifact_from_previous_run() {
+        let cache_directory = tempdir().unwrap().into_path();
+        let file_downloader = counting_file_downloader();
+        let url = "https://something.com/archive.tar.gz";
+        let first_artifact = ArtifactCache::new(file_downloader.clone(), cache_directory.clone(), 30)
+            .get_artifact(url)
+            .await
+            .unwrap();
+
+        let mut no_download_expected = MockFileDownloaderTrait::new();
+        no_download_expected.expect_download_file_to_directory().times(0);
+        let second_artifact = ArtifactCache::new(Arc::new(no_download_expected), cache_directory, 30)
+            .get_artifact(url)
+            .await
+            .unwrap();
+
+        assert_eq!(first_artifact, second_artifact);
+    }
+
+    #[tokio::test]
+    async fn downloads_again_when_cached_artifact_is_corrupted() {
+        let cache_directory = tempdir().unwrap().into_path();
+        let file_downloader = counting_file_downloader();
+        let url = "https://something.com/archive.tar.gz";
+        let artifact = ArtifactCache::new(file_downloader.clone(), cache_directory.clone(), 30)
+            .get_artifact(url)
+            .await
+            .unwrap();
+        fs::write(&artifact, "truncated").unwrap();
+
+        let artifact = ArtifactCache::new(file_downloader.clone(), cache_directory, 30)
+            .get_artifact(url)
+            .await
+            .unwrap();
+
+        assert_eq!(2, file_downloader.number_of_downloads.load(Ordering::SeqCst));
+        assert_eq!("archive contents", fs::read_to_string(artifact).unwrap());
+    }
+
+    #[tokio::test]
+    async fn removes_artifacts_older_than_retention() {
+        let cache_directory = tempdir().unwrap().into_path();
+        let url = "https://something.com/archive.tar.gz";
+        let kept_cache = ArtifactCache::new(counting_file_downloader(), cache_directory.clone(), 30);
+        let artifact = kept_cache.get_artifact(url).await.unwrap();
+
+        kept_cache.remove_expired_artifacts();
+        assert!(artifact.exists());
+
+        ArtifactCache::new(counting_file_downloader(), cache_directory, 0).remove_expired_artifacts();
+        assert!(!artifact.exists());
+    }
+
+    #[tokio::test]
+    async fn cache_hits_do_not_wait_for_a_download_permit() {
+        let cache_directory = tempdir().unwrap().into_path();
+        let url = "https://something.com/archive.tar.gz";
+        ArtifactCache::new(counting_file_downloader(), cache_directory.clone(), 30)
+            .get_artifact(url)
+            .await
+            .unwrap();
+        let download_permits = Arc::new(Semaphore::new(1));
+        let _busy_download = download_permits.clone().acquire_owned().await.unwrap();
+
+        let artifact_cache =
+            ArtifactCache::new_with_download_permits(counting_file_downloader(), cache_directory, 30, download_permits);
+        let artifact = tokio::time::timeout(Duration::from_secs(5), artifact_cache.get_artifact(url)).await;
+
+        assert!(artifact.unwrap().unwrap().exists());
+    }
+
+    #[tokio::test]
+    async fn keeps_partial_downloads_when_removing_expired_artifacts() {
+        let cache_directory = tempdir().unwrap().into_path();
+        let partial_directory = cache_directory.join(format!("{}abc123", PARTIAL_DIRECTORY_PREFIX));
+        fs::create_dir_all(&partial_directory).unwrap();
+        fs::write(partial_directory.join("archive.tar.gz"), "half an archive").unwrap();
+
+        ArtifactCache::new(counting_file_downloader(), cache_directory, 0).remove_expired_artifacts();
+
+        assert!(partial_directory.join("archive.tar.gz").exists());
+    }
+}
@@ -1,63 +1,184 @@
 use async_trait::async_trait;
-use std::{path::Path, sync::Arc};
+use std::{fs, panic::resume_unwind, path::Path, sync::Arc};
 use tempfile::tempdir;
+use tokio::sync::Semaphore;
+use tokio::task::spawn_blocking;
 
 #[cfg(test)]
 use mockall::automock;
 
+use crate::solipath_config::user_config::DownloadSettings;
+use crate::solipath_download::artifact_cache::ArtifactCacheTrait;
 use crate::solipath_download::file_decompressor::FileDecompressorTrait;
 use crate::solipath_download::file_downloader::FileDownloaderTrait;
 
 #[cfg_attr(test, automock)]
 #[async_trait]
 pub trait ConditionalFileDownloaderTrait {
-    async fn download_file_if_not_exists(&self, url: &str, path_to_save_to: &Path);
-    async fn download_and_decompress_file_if_directory_not_exists(&self, url: &str, directory_to_save_to: &Path);
+    async fn download_file_if_not_exists(&self, url: &str, path_to_save_to: &Path) -> anyhow::Result<()>;
+    // returns the name the file was downloaded as, or None when the directory already existed
+    async fn download_and_decompress_file_if_directory_not_exists(
+        &self,
+        url: &str,
+        directory_to_save_to: &Path,
+        file_name: Option<String>,
+    ) -> Option<String>;
 }
 
 pub struct ConditionalFileDownloader {
     file_downloader: Arc<dyn FileDownloaderTrait + Sync + Send>,
     file_decompressor: Arc<dyn FileDecompressorTrait + Sync + Send>,
+    artifact_cache: Arc<dyn ArtifactCacheTrait + Sync + Send>,
+    download_permits: Arc<Semaphore>,
 }
 
 impl ConditionalFileDownloader {
     pub fn new(
         file_downloader: Arc<dyn FileDownloaderTrait + Sync + Send>,
         file_decompressor: Arc<dyn FileDecompressorTrait + Sync + Send>,
+        artifact_cache: Arc<dyn ArtifactCacheTrait + Sync + Send>,
+    ) -> Self {
+        Self::new_with_max_parallel_downloads(
+            file_downloader,
+            file_decompressor,
+            artifact_cache,
+            DownloadSettings::default().max_parallel_downloads,
+        )
+    }
+
+    pub fn new_with_max_parallel_downloads(
+        file_downloader: Arc<dyn FileDownloaderTrait + Sync + Send>,
+        file_decompressor: Arc<dyn FileDecompressorTrait + Sync + Send>,
+        artifact_cache: Arc<dyn ArtifactCacheTrait + Sync + Send>,
+        max_parallel_downloads: usize,
+    ) -> Self {
+        Self::new_with_download_permits(
+            file_downloader,
+            file_decompressor,
+            artifact_cache,
+            Arc::new(Semaphore::new(max_parallel_downloads.max(1))),
+        )
+    }
+
+    // only network transfers hold a permit, the artifact cache takes its own from the same semaphore, so extracting
+    // one dependency never stops another from downloading
+    pub fn new_with_download_permits(
+        file_downloader: Arc<dyn FileDownloaderTrait + Sync + Send>,
+        file_decompressor: Arc<dyn FileDecompressorTrait + Sync + Send>,
+        artifact_cache: Arc<dyn ArtifactCacheTrait + Sync + Send>,
+        download_permits: Arc<Semaphore>,
     ) -> Self {
         Self {
             file_downloader,
             file_decompressor,
+            artifact_cache,
+            download_permits,
+        }
+    }
+
+    // decompression is CPU bound and synchronous, so it runs on the blocking pool instead of a tokio worker thread
+    async fn decompress_file_to_directory(&self, source_file: &Path, target_directory: &Path) {
+        let file_decompressor = self.file_decompressor.clone();
+        let source_file = source_file.to_path_buf();
+        let target_directory = target_directory.to_path_buf();
+        let result =
+            spawn_blocking(move || file_decompressor.decompress_file_to_directory(&source_file, &target_directory))
+                .await;
+        if let Err(error) = result {
+            resume_unwind(error.into_panic());
         }
     }
 }
 
 #[async_trait]
 impl ConditionalFileDownloaderTrait for ConditionalFileDownloader {
-    async fn download_file_if_not_exists(&self, url: &str, path_to_save_to: &Path) {
+    async fn download_file_if_not_exists(&self, url: &str, path_to_save_to: &Path) -> anyhow::Result<()> {
         if !path_to_save_to.exists() {
-            self.file_downloader.download_file(url, path_to_save_to).await;
+            let _permit = self.download_permits.acquire().await.expect("download semaphore closed");
+            self.file_downloader.download_file(url, path_to_save_to).await?;
         }
+        Ok(())
     }
-    async fn download_and_decompress_file_if_directory_not_exists(&self, url: &str, directory_to_save_to: &Path) {
-        if !directory_to_save_to.exists() {
-            let temp_dir = tempdir().unwrap().into_path();
-            let downloaded_file = self.file_downloader.download_file_to_directory(url, &temp_dir).await.expect("Something went wrong while downloading file");
-            self.file_decompressor
-                .decompress_file_to_directory(&downloaded_file, &directory_to_save_to);
+    async fn download_and_decompress_file_if_directory_not_exists(
+        &self,
+        url: &str,
+        directory_to_save_to: &Path,
+        file_name: Option<String>,
+    ) -> Option<String> {
+        if directory_to_save_to.exists() {
+            return None;
         }
+        let cached_file = self.artifact_cache.get_artifact(url).await.expect("Something went wrong while downloading file");
+        match file_name {
+            // the cached artifact is shared, so a renamed copy is decompressed instead
+            Some(file_name) => {
+                let temp_dir = tempdir().unwrap();
+                let renamed_file = temp_dir.path().join(file_name);
+                fs::copy(&cached_file, &renamed_file).expect("failed to rename downloaded file");
+                self.decompress_file_to_directory(&renamed_file, directory_to_save_to).await;
+            }
+            None => self.decompress_file_to_directory(&cached_file, directory_to_save_to).await,
+        }
+        cached_file.file_name().map(|downloaded_file_name| downloaded_file_name.to_string_lossy().to_string())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use std::fs::File;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
 
     use super::*;
+    use crate::async_loop::run_async;
+    use crate::solipath_download::artifact_cache::MockArtifactCacheTrait;
     use crate::solipath_download::file_decompressor::MockFileDecompressorTrait;
     use crate::solipath_download::file_downloader::MockFileDownloaderTrait;
     use std::path::PathBuf;
 
+    struct SlowFileDownloader {
+        downloads_in_progress: AtomicUsize,
+        most_downloads_in_progress: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl FileDownloaderTrait for SlowFileDownloader {
+        async fn download_file_to_directory(&self, _: &str, _: &Path) -> anyhow::Result<PathBuf> {
+            Ok(PathBuf::new())
+        }
+
+        async fn download_file(&self, _: &str, _: &Path) -> anyhow::Result<()> {
+            let downloads_in_progress = self.downloads_in_progress.fetch_add(1, Ordering::SeqCst) + 1;
+            self.most_downloads_in_progress.fetch_max(downloads_in_progress, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.downloads_in_progress.fetch_sub(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn limits_number_of_parallel_downloads() {
+        let file_downloader = Arc::new(SlowFileDownloader {
+            downloads_in_progress: AtomicUsize::new(0),
+            most_downloads_in_progress: AtomicUsize::new(0),
+        });
+        let conditional_file_downloader = ConditionalFileDownloader::new_with_max_parallel_downloads(
+            file_downloader.clone(),
+            Arc::new(MockFileDecompressorTrait::new()),
+            Arc::new(MockArtifactCacheTrait::new()),
+            2,
+        );
+        let temp_dir = tempdir().unwrap();
+        let paths: Vec<PathBuf> = (0..6).map(|index| temp_dir.path().join(index.to_string())).collect();
+
+        run_async(&paths, |path| {
+            conditional_file_downloader.download_file_if_not_exists("https://something.com", path)
+        })
+        .await;
+
+        assert_eq!(2, file_downloader.most_downloads_in_progress.load(Ordering::SeqCst));
+    }
+
     #[tokio::test]
     async fn calls_file_downloader_when_file_does_not_exist() {
         let url = "https://something.com/";
@@ -70,14 +191,19 @@ mod tests {
             .expect_download_file()
             .withf(move |actual_url, actual_path| actual_url == url && actual_path == copy_path_to_save_to)
             .times(1)
-            .return_const(());
+            .returning(|_, _| Ok(()));
         let file_decompressor = MockFileDecompressorTrait::new();
-        let conditional_file_downloader =
-            ConditionalFileDownloader::new(Arc::new(file_downloader), Arc::new(file_decompressor));
+        let artifact_cache = MockArtifactCacheTrait::new();
+        let conditional_file_downloader = ConditionalFileDownloader::new(
+            Arc::new(file_downloader),
+            Arc::new(file_decompressor),
+            Arc::new(artifact_cache),
+        );
 
         conditional_file_downloader
             .download_file_if_not_exists(url, &path_to_save_to.clone())
-            .await;
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
@@ -88,14 +214,19 @@ mod tests {
         path.push("randomfile.txt");
         File::create(path.clone()).expect("failed to create tempfile");
         let mut file_downloader = MockFileDownloaderTrait::new();
-        file_downloader.expect_download_file().times(0).return_const(());
+        file_downloader.expect_download_file().times(0);
         let file_decompressor = MockFileDecompressorTrait::new();
-        let conditional_file_downloader =
-            ConditionalFileDownloader::new(Arc::new(file_downloader), Arc::new(file_decompressor));
+        let artifact_cache = MockArtifactCacheTrait::new();
+        let conditional_file_downloader = ConditionalFileDownloader::new(
+            Arc::new(file_downloader),
+            Arc::new(file_decompressor),
+            Arc::new(artifact_cache),
+        );
 
         conditional_file_downloader
             .download_file_if_not_exists(url, &path)
-            .await;
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
@@ -106,12 +237,13 @@ mod tests {
         path_to_save_to.push("directory_that_should_not_exist");
         let copy_path_to_save_to = path_to_save_to.clone();
 
-        let mut file_downloader = MockFileDownloaderTrait::new();
-        file_downloader
-            .expect_download_file_to_directory()
-            .withf(move |actual_url, _| actual_url == url)
+        let file_downloader = MockFileDownloaderTrait::new();
+        let mut artifact_cache = MockArtifactCacheTrait::new();
+        artifact_cache
+            .expect_get_artifact()
+            .withf(move |actual_url| actual_url == url)
             .times(1)
-            .returning(|_,_|Ok(PathBuf::from("/output_path/download.zip")));
+            .returning(|_| Ok(PathBuf::from("/output_path/download.zip")));
         let mut file_decompressor = MockFileDecompressorTrait::new();
         file_decompressor
             .expect_decompress_file_to_directory()
@@ -121,11 +253,50 @@ mod tests {
             })
             .times(1)
             .return_const(());
-        let conditional_file_downloader =
-            ConditionalFileDownloader::new(Arc::new(file_downloader), Arc::new(file_decompressor));
+        let conditional_file_downloader = ConditionalFileDownloader::new(
+            Arc::new(file_downloader),
+            Arc::new(file_decompressor),
+            Arc::new(artifact_cache),
+        );
+        conditional_file_downloader
+            .download_and_decompress_file_if_directory_not_exists(url, &path_to_save_to, None)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn renames_downloaded_file_before_decompressing_when_file_name_is_set() {
+        let url = "https://something.com/jq-linux-amd64";
+        let temp_dir = tempdir().unwrap();
+        let download_dir = tempdir().unwrap().into_path();
+        let downloaded_file = download_dir.join("jq-linux-amd64");
+        File::create(&downloaded_file).expect("failed to create tempfile");
+        let mut path_to_save_to = temp_dir.path().to_path_buf();
+        path_to_save_to.push("bin");
+
+        let file_downloader = MockFileDownloaderTrait::new();
+        let mut artifact_cache = MockArtifactCacheTrait::new();
+        let cached_file = downloaded_file.clone();
+        artifact_cache
+            .expect_get_artifact()
+            .times(1)
+            .returning(move |_| Ok(cached_file.clone()));
+        let mut file_decompressor = MockFileDecompressorTrait::new();
+        file_decompressor
+            .expect_decompress_file_to_directory()
+            .withf(|source_file_path, _| {
+                source_file_path.file_name().unwrap() == "jq" && source_file_path.exists()
+            })
+            .times(1)
+            .return_const(());
+        let conditional_file_downloader = ConditionalFileDownloader::new(
+            Arc::new(file_downloader),
+            Arc::new(file_decompressor),
+            Arc::new(artifact_cache),
+        );
         conditional_file_downloader
-            .download_and_decompress_file_if_directory_not_exists(url, &path_to_save_to)
+            .download_and_decompress_file_if_directory_not_exists(url, &path_to_save_to, Some("jq".to_string()))
             .await;
+        assert!(downloaded_file.exists());
     }
 
     #[tokio::test]
@@ -134,16 +305,21 @@ mod tests {
         let path_to_save_to = tempdir().unwrap().into_path();
 
         let mut file_downloader = MockFileDownloaderTrait::new();
-        file_downloader.expect_download_file().times(0).return_const(());
+        file_downloader.expect_download_file().times(0);
         let mut file_decompressor = MockFileDecompressorTrait::new();
         file_decompressor
             .expect_decompress_file_to_directory()
             .times(0)
             .return_const(());
-        let conditional_file_downloader =
-            ConditionalFileDownloader::new(Arc::new(file_downloader), Arc::new(file_decompressor));
+        let mut artifact_cache = MockArtifactCacheTrait::new();
+        artifact_cache.expect_get_artifact().times(0);
+        let conditional_file_downloader = ConditionalFileDownloader::new(
+            Arc::new(file_downloader),
+            Arc::new(file_decompressor),
+            Arc::new(artifact_cache),
+        );
         conditional_file_downloader
-            .download_and_decompress_file_if_directory_not_exists(url, &path_to_save_to)
+            .download_and_decompress_file_if_directory_not_exists(url, &path_to_save_to, None)
             .await;
     }
 }
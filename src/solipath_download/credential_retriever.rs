@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs::read_to_string;
+use std::path::PathBuf;
+
+use directories::UserDirs;
+use reqwest::{RequestBuilder, Url};
+
+#[cfg(test)]
+use mockall::automock;
+
+use crate::solipath_config::user_config::CredentialSettings;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Credential {
+    Basic { username: String, password: Option<String> },
+    Bearer(String),
+}
+
+impl Credential {
+    pub fn apply_to(&self, request: RequestBuilder) -> RequestBuilder {
+        match self {
+            Credential::Basic { username, password } => request.basic_auth(username, password.as_ref()),
+            Credential::Bearer(token) => request.bearer_auth(token),
+        }
+    }
+}
+
+#[cfg_attr(test, automock)]
+pub trait CredentialRetrieverTrait {
+    fn get_credential(&self, host: &str) -> Option<Credential>;
+}
+
+// credentials are only attached to the host they were configured for. reqwest strips the
+// Authorization header when a redirect leaves that host, so they are never forwarded.
+pub fn authorize_request(
+    request: RequestBuilder,
+    url: &str,
+    credential_retriever: &(dyn CredentialRetrieverTrait + Sync + Send),
+) -> RequestBuilder {
+    let credential = Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host.to_string()))
+        .and_then(|host| credential_retriever.get_credential(&host));
+    match credential {
+        Some(credential) => credential.apply_to(request),
+        None => request,
+    }
+}
+
+pub struct CredentialRetriever {
+    configured_credentials: HashMap<String, CredentialSettings>,
+    netrc_entries: Vec<NetrcEntry>,
+}
+
+impl CredentialRetriever {
+    pub fn new(configured_credentials: HashMap<String, CredentialSettings>, netrc_contents: Option<String>) -> Self {
+        Self {
+            configured_credentials,
+            netrc_entries: netrc_contents.map(|contents| parse_netrc(&contents)).unwrap_or_default(),
+        }
+    }
+
+    fn get_environment_credential(&self, host: &str) -> Option<Credential> {
+        env::var(get_environment_variable_name(host))
+            .ok()
+            .map(|value| parse_environment_credential(&value))
+    }
+
+    fn get_configured_credential(&self, host: &str) -> Option<Credential> {
+        self.configured_credentials.get(host).and_then(|settings| {
+            if let Some(token) = &settings.token {
+                Some(Credential::Bearer(token.clone()))
+            } else {
+                settings.username.as_ref().map(|username| Credential::Basic {
+                    username: username.clone(),
+                    password: settings.password.clone(),
+                })
+            }
+        })
+    }
+
+    fn get_netrc_credential(&self, host: &str) -> Option<Credential> {
+        self.netrc_entries
+            .iter()
+            .find(|entry| entry.machine.as_deref() == Some(host))
+            .or_else(|| self.netrc_entries.iter().find(|entry| entry.machine.is_none()))
+            .map(|entry| Credential::Basic {
+                username: entry.login.clone().unwrap_or_default(),
+                password: entry.password.clone(),
+            })
+    }
+}
+
+impl CredentialRetrieverTrait for CredentialRetriever {
+    fn get_credential(&self, host: &str) -> Option<Credential> {
+        self.get_environment_credential(host)
+            .or_else(|| self.get_configured_credential(host))
+            .or_else(|| self.get_netrc_credential(host))
+    }
+}
+
+pub fn get_environment_variable_name(host: &str) -> String {
+    let normalized_host: String = host
+        .chars()
+        .map(|character| if character.is_ascii_alphanumeric() { character.to_ascii_uppercase() } else { '_' })
+        .collect();
+    format!("SOLIPATH_AUTH_{}", normalized_host)
+}
+
+// "Bearer <token>" or a bare token is sent as a bearer token, "<username>:<password>" as basic auth
+fn parse_environment_credential(value: &str) -> Credential {
+    if let Some(token) = value.strip_prefix("Bearer ") {
+        Credential::Bearer(token.trim().to_string())
+    } else if let Some((username, password)) = value.split_once(':') {
+        Credential::Basic {
+            username: username.to_string(),
+            password: Some(password.to_string()),
+        }
+    } else {
+        Credential::Bearer(value.to_string())
+    }
+}
+
+pub fn read_netrc() -> Option<String> {
+    netrc_path().and_then(|path| read_to_string(path).ok())
+}
+
+fn netrc_path() -> Option<PathBuf> {
+    if let Ok(path) = env::var("NETRC") {
+        return Some(PathBuf::from(path));
+    }
+    let file_name = if std::env::consts::OS == "windows" { "_netrc" } else { ".netrc" };
+    UserDirs::new().map(|user_dirs| user_dirs.home_dir().join(file_name))
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+struct NetrcEntry {
+    machine: Option<String>,
+    login: Option<String>,
+    password: Option<String>,
+}
+
+fn parse_netrc(contents: &str) -> Vec<NetrcEntry> {
+    let mut entries: Vec<NetrcEntry> = Vec::new();
+    let mut tokens = contents.split_whitespace();
+    while let Some(token) = tokens.next() {
+        match token {
+            "machine" => entries.push(NetrcEntry {
+                machine: tokens.next().map(|machine| machine.to_string()),
+                ..NetrcEntry::default()
+            }),
+            "default" => entries.push(NetrcEntry::default()),
+            "login" => {
+                let login = tokens.next().map(|login| login.to_string());
+                if let Some(entry) = entries.last_mut() {
+                    entry.login = login;
+                }
+            }
+            "password" => {
+                let password = tokens.next().map(|password| password.to_string());
+                if let Some(entry) = entries.last_mut() {
+                    entry.password = password;
+                }
+            }
+            "account" => {
+                tokens.next();
+            }
+            // macro definitions run until a blank line, which split_whitespace can't see, so stop here
+            "macdef" => break,
+            _ => {}
+        }
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn configured_credentials() -> HashMap<String, CredentialSettings> {
+        let mut credentials = HashMap::new();
+        credentials.insert(
+            "artifactory.example.com".to_string(),
+            CredentialSettings {
+                username: Some("builder".to_string()),
+                password: Some("secret".to_string()),
+                token: None,
+            },
+        );
+        credentials.insert(
+            "api.github.com".to_string(),
+            CredentialSettings {
+                username: None,
+                password: None,
+                token: Some("ghp_token".to_string()),
+            },
+        );
+        credentials
+    }
+
+    #[test]
+    fn environment_variable_name_is_upper_case_with_underscores() {
+        assert_eq!(
+            "SOLIPATH_AUTH_NEXUS_EXAMPLE_COM",
+            get_environment_variable_name("nexus.example-com")
+        );
+    }
+
+    #[test]
+    fn uses_configured_credential_for_matching_host() {
+        let credential_retriever = CredentialRetriever::new(configured_credentials(), None);
+        assert_eq!(
+            Some(Credential::Basic {
+                username: "builder".to_string(),
+                password: Some("secret".to_string())
+            }),
+            credential_retriever.get_credential("artifactory.example.com")
+        );
+        assert_eq!(
+            Some(Credential::Bearer("ghp_token".to_string())),
+            credential_retriever.get_credential("api.github.com")
+        );
+        assert_eq!(None, credential_retriever.get_credential("objects.githubusercontent.com"));
+    }
+
+    #[test]
+    fn uses_netrc_machine_then_default() {
+        let netrc = r#"
+            machine nexus.example.com login nexus-user password nexus-password
+            default login anonymous password guest
+        "#;
+        let credential_retriever = CredentialRetriever::new(HashMap::new(), Some(netrc.to_string()));
+        assert_eq!(
+            Some(Credential::Basic {
+                username: "nexus-user".to_string(),
+                password: Some("nexus-password".to_string())
+            }),
+            credential_retriever.get_credential("nexus.example.com")
+        );
+        assert_eq!(
+            Some(Credential::Basic {
+                username: "anonymous".to_string(),
+                password: Some("guest".to_string())
+            }),
+            credential_retriever.get_credential("other.example.com")
+        );
+    }
+
+    #[test]
+    fn environment_variable_takes_precedence_over_config_and_netrc() {
+        env::set_var(get_environment_variable_name("env-precedence.example.com"), "Bearer from-env");
+        let mut credentials = HashMap::new();
+        credentials.insert(
+            "env-precedence.example.com".to_string(),
+            CredentialSettings {
+                username: None,
+                password: None,
+                token: Some("from-config".to_string()),
+            },
+        );
+        let netrc = "machine env-precedence.example.com login netrc password netrc";
+        let credential_retriever = CredentialRetriever::new(credentials, Some(netrc.to_string()));
+        assert_eq!(
+            Some(Credential::Bearer("from-env".to_string())),
+            credential_retriever.get_credential("env-precedence.example.com")
+        );
+    }
+
+    #[test]
+    fn environment_credential_with_colon_is_basic_auth() {
+        assert_eq!(
+            Credential::Basic {
+                username: "user".to_string(),
+                password: Some("pass:word".to_string())
+            },
+            parse_environment_credential("user:pass:word")
+        );
+        assert_eq!(Credential::Bearer("token".to_string()), parse_environment_credential("token"));
+    }
+}
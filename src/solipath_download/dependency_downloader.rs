@@ -6,6 +6,7 @@ use mockall::automock;
 
 use crate::solipath_directory::solipath_directory_finder::SolipathDirectoryFinderTrait;
 use crate::solipath_download::conditional_file_downloader::ConditionalFileDownloaderTrait;
+use crate::solipath_download::file_permissions::set_matching_files_as_executable;
 use crate::solipath_instructions::data::download_instruction::DownloadInstruction;
 use crate::solipath_instructions::data::dependency::Dependency;
 
@@ -38,9 +39,21 @@ impl DependencyDownloaderTrait for DependencyDownloader {
     async fn download_dependency(&self, dependency: &Dependency, download_instruction: &DownloadInstruction) {
         let mut downloads_directory = self.directory_finder.get_dependency_downloads_directory(&dependency);
         downloads_directory.push(download_instruction.get_destination_directory());
-        self.conditional_file_downloader
-            .download_and_decompress_file_if_directory_not_exists(&download_instruction.get_url(), &downloads_directory)
+        let downloaded_file_name = self
+            .conditional_file_downloader
+            .download_and_decompress_file_if_directory_not_exists(
+                &download_instruction.get_url(),
+                &downloads_directory,
+                download_instruction.get_file_name(),
+            )
             .await;
+        // permissions are only set on a fresh download, they stay set afterwards
+        if let Some(downloaded_file_name) = downloaded_file_name {
+            set_matching_files_as_executable(
+                &downloads_directory,
+                &download_instruction.get_executable_patterns(&downloaded_file_name),
+            );
+        }
     }
 }
 
@@ -68,16 +81,87 @@ mod tests {
         let mut conditional_file_downloader = MockConditionalFileDownloaderTrait::new();
         conditional_file_downloader
             .expect_download_and_decompress_file_if_directory_not_exists()
-            .withf(|actual_url, actual_path| {
+            .withf(|actual_url, actual_path, actual_file_name| {
                 actual_url == "www.github.com/download.zip"
                     && actual_path == PathBuf::from("downloads/directory/destination")
+                    && actual_file_name.is_none()
             })
             .times(1)
-            .return_const(());
+            .return_const(None);
+        let dependency_downloader =
+            DependencyDownloader::new(Arc::new(directory_finder), Arc::new(conditional_file_downloader));
+        dependency_downloader
+            .download_dependency(&dependency, &download_instruction)
+            .await;
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[tokio::test]
+    async fn renamed_single_file_download_is_set_as_executable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dependency = Dependency::new("jq", "1.7.1");
+        let download_instruction: DownloadInstruction = serde_json::from_str(
+            r#"{"url": "www.github.com/jq-linux-amd64", "destination_directory": "bin", "rename_to": "jq", "executable": true}"#,
+        )
+        .unwrap();
+        let downloads_directory = tempfile::tempdir().unwrap().into_path();
+        let expected_executable = downloads_directory.join("bin/jq");
+        let mut directory_finder = MockSolipathDirectoryFinderTrait::new();
+        directory_finder
+            .expect_get_dependency_downloads_directory()
+            .return_const(downloads_directory.clone());
+        let mut conditional_file_downloader = MockConditionalFileDownloaderTrait::new();
+        conditional_file_downloader
+            .expect_download_and_decompress_file_if_directory_not_exists()
+            .withf(|_, _, actual_file_name| actual_file_name == &Some("jq".to_string()))
+            .times(1)
+            .returning(|_, directory, _| {
+                std::fs::create_dir_all(directory).unwrap();
+                std::fs::write(directory.join("jq"), "binary").unwrap();
+                Some("jq-linux-amd64".to_string())
+            });
         let dependency_downloader =
             DependencyDownloader::new(Arc::new(directory_finder), Arc::new(conditional_file_downloader));
         dependency_downloader
             .download_dependency(&dependency, &download_instruction)
             .await;
+
+        let mode = std::fs::metadata(expected_executable).unwrap().permissions().mode();
+        assert_eq!(0o775, mode & 0o777);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[tokio::test]
+    async fn executable_uses_the_name_the_file_was_downloaded_as() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dependency = Dependency::new("kubectl", "1.31.0");
+        let download_instruction: DownloadInstruction = serde_json::from_str(
+            r#"{"url": "www.example.com/download?id=kubectl", "destination_directory": "bin", "executable": true}"#,
+        )
+        .unwrap();
+        let downloads_directory = tempfile::tempdir().unwrap().into_path();
+        let mut directory_finder = MockSolipathDirectoryFinderTrait::new();
+        directory_finder
+            .expect_get_dependency_downloads_directory()
+            .return_const(downloads_directory.clone());
+        let mut conditional_file_downloader = MockConditionalFileDownloaderTrait::new();
+        conditional_file_downloader
+            .expect_download_and_decompress_file_if_directory_not_exists()
+            .times(1)
+            .returning(|_, directory, _| {
+                std::fs::create_dir_all(directory).unwrap();
+                std::fs::write(directory.join("kubectl"), "binary").unwrap();
+                Some("kubectl".to_string())
+            });
+        let dependency_downloader =
+            DependencyDownloader::new(Arc::new(directory_finder), Arc::new(conditional_file_downloader));
+        dependency_downloader
+            .download_dependency(&dependency, &download_instruction)
+            .await;
+
+        let mode = std::fs::metadata(downloads_directory.join("bin/kubectl")).unwrap().permissions().mode();
+        assert_eq!(0o775, mode & 0o777);
     }
 }
@@ -3,16 +3,20 @@ use async_trait::async_trait;
 use reqwest::Client;
 use reqwest::Error;
 use reqwest::Response;
-use tokio::time::sleep;
 use std::path::Path;
 use std::path::PathBuf;
-use std::time::Duration;
+use std::sync::Arc;
 use tokio::{
     fs::{create_dir_all, File},
     io::AsyncWriteExt,
 };
 use anyhow::Result;
+use crate::solipath_config::user_config::UserConfig;
+use crate::solipath_config::user_config_retriever::{UserConfigRetriever, UserConfigRetrieverTrait};
+use crate::solipath_download::credential_retriever::{authorize_request, read_netrc, CredentialRetriever, CredentialRetrieverTrait};
 use crate::solipath_download::file_name_retriever::*;
+use crate::solipath_download::http_client_factory::HttpClientFactory;
+use crate::solipath_download::retry_policy::RetryPolicy;
 
 #[cfg(test)]
 use mockall::{automock, predicate::*};
@@ -21,39 +25,55 @@ use mockall::{automock, predicate::*};
 #[async_trait]
 pub trait FileDownloaderTrait {
     async fn download_file_to_directory(&self, url: &str, directory_to_save_to: &Path) -> Result<PathBuf>;
-    async fn download_file(&self, url: &str, path: &Path);
+    async fn download_file(&self, url: &str, path: &Path) -> Result<()>;
 }
 
 pub struct FileDownloader {
     reqwest_client: Client,
+    credential_retriever: Arc<dyn CredentialRetrieverTrait + Sync + Send>,
+    retry_policy: RetryPolicy,
 }
 
 impl FileDownloader {
     pub fn new() -> Self {
+        Self::new_with_user_config(&UserConfigRetriever::new().get_user_config())
+    }
+
+    pub fn new_with_user_config(user_config: &UserConfig) -> Self {
+        Self::new_with_client(
+            HttpClientFactory::new(user_config.get_http_settings().clone())
+                .create_client()
+                .expect("failed to create http client"),
+            Arc::new(CredentialRetriever::new(user_config.get_credentials().clone(), read_netrc())),
+            RetryPolicy::new(user_config.get_retry_settings()),
+        )
+    }
+
+    pub fn new_with_client(
+        reqwest_client: Client,
+        credential_retriever: Arc<dyn CredentialRetrieverTrait + Sync + Send>,
+        retry_policy: RetryPolicy,
+    ) -> Self {
         Self {
-            reqwest_client: Client::new(),
+            reqwest_client,
+            credential_retriever,
+            retry_policy,
         }
     }
 
-
     async fn repeat_request(&self, url: &str) -> Result<Response> {
-        let mut number_of_tries = 0;
-        let max_number_of_tries = 3;
-        let mut result = self.make_request(url).await;
-        while result.is_err() && number_of_tries < max_number_of_tries{
-            println!("Error downloading {}, trying again...", url);
-            result = self.make_request(url).await;
-            number_of_tries += 1;
-            sleep(Duration::new(2*number_of_tries, 0)).await
-        }
+        let result = self
+            .retry_policy
+            .send_with_retries(url, || self.make_request(url))
+            .await
+            .and_then(|response| response.error_for_status());
         Ok(result.context(format!("failed to download file: {}", url))?)
     }
 
     async fn make_request(&self, url: &str) -> Result<Response, Error> {
-        self.reqwest_client
-                    .get(url)
+        authorize_request(self.reqwest_client.get(url), url, self.credential_retriever.as_ref())
                     .send()
-                    .await.and_then(|response| response.error_for_status())
+                    .await
     }
 
     async fn stream_response_output_to_file(&self, response: &mut Response, file: &mut File) -> Result<()> {
@@ -87,24 +107,29 @@ impl FileDownloaderTrait for FileDownloader {
         Ok(path_to_save_to)
     }
 
-    async fn download_file(&self, url: &str, path_to_save_to: &Path) {
+    async fn download_file(&self, url: &str, path_to_save_to: &Path) -> Result<()> {
         println!("downloading {}...", url);
-        let mut response = self.repeat_request(url).await.unwrap();
+        let mut response = self.repeat_request(url).await?;
         let parent_directory = path_to_save_to.parent().unwrap();
         create_dir_all(&parent_directory)
             .await
-            .expect("failed to create parent directories");
-        let mut file = File::create(path_to_save_to).await.expect("could not create file");
-        self.stream_response_output_to_file(&mut response, &mut file).await.unwrap();
+            .with_context(|| "failed to create parent directories")?;
+        let mut file = File::create(path_to_save_to)
+            .await
+            .with_context(|| format!("could not create file: {}", path_to_save_to.display()))?;
+        self.stream_response_output_to_file(&mut response, &mut file).await?;
         println!("finished downloading {}", url);
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::solipath_download::credential_retriever::{Credential, MockCredentialRetrieverTrait};
     use tempfile::tempdir;
     use tokio::fs::read_to_string;
+    use warp::Filter;
 
     const DOWNLOAD_CONTENT: &str = r#"Copyright (c) The Rust Project Contributors
 
@@ -137,14 +162,15 @@ DEALINGS IN THE SOFTWARE.
     async fn can_download_a_file() {
         let temp_dir = tempdir().unwrap().into_path();
         let expected_file_path = temp_dir.join("LICENSE-MIT".to_string());
-        let file_downloader = FileDownloader::new();
+        let file_downloader = FileDownloader::new_with_user_config(&UserConfig::default());
 
         file_downloader
             .download_file(
                 "https://raw.githubusercontent.com/rust-lang/rust/master/LICENSE-MIT",
                 &expected_file_path,
             )
-            .await;
+            .await
+            .unwrap();
 
         let file_contents = read_to_string(expected_file_path.to_str().unwrap())
             .await
@@ -156,16 +182,61 @@ DEALINGS IN THE SOFTWARE.
     #[tokio::test]
     #[should_panic(expected="failed to download file: https://raw.githubusercontent.com/rust-lang/rust/master/NONEXISTENT_FILE")]
     async fn a_404_throws_an_exception(){
-        let file_downloader = FileDownloader::new();
+        let file_downloader = FileDownloader::new_with_user_config(&UserConfig::default());
         file_downloader.repeat_request("https://raw.githubusercontent.com/rust-lang/rust/master/NONEXISTENT_FILE").await.unwrap();
     }
 
 
 
+    fn start_authorization_echo_server() -> (u16, tokio::task::JoinHandle<()>) {
+        let echo_authorization = warp::path("echo")
+            .and(warp::header::optional::<String>("authorization"))
+            .map(|authorization: Option<String>| authorization.unwrap_or_else(|| "no authorization".to_string()));
+        let redirect_to_other_host = warp::path("redirect").and(warp::header::<String>("host")).map(|host: String| {
+            let other_host = host.replace("127.0.0.1", "localhost");
+            warp::redirect::temporary(warp::http::Uri::try_from(format!("http://{}/echo", other_host)).unwrap())
+        });
+        let (address, server) =
+            warp::serve(echo_authorization.or(redirect_to_other_host)).bind_ephemeral(([127, 0, 0, 1], 0));
+        (address.port(), tokio::spawn(server))
+    }
+
+    fn file_downloader_with_credential_for_host(host: &'static str) -> FileDownloader {
+        let mut credential_retriever = MockCredentialRetrieverTrait::new();
+        credential_retriever
+            .expect_get_credential()
+            .returning(move |requested_host| (requested_host == host).then(|| Credential::Bearer("secret".to_string())));
+        FileDownloader::new_with_client(Client::new(), Arc::new(credential_retriever), RetryPolicy::default())
+    }
+
+    #[tokio::test]
+    async fn applies_credential_for_matching_host() {
+        let (port, server) = start_authorization_echo_server();
+        let output_file = tempdir().unwrap().into_path().join("echo");
+        file_downloader_with_credential_for_host("127.0.0.1")
+            .download_file(&format!("http://127.0.0.1:{}/echo", port), &output_file)
+            .await
+            .unwrap();
+        server.abort();
+        assert_eq!("Bearer secret", read_to_string(output_file).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn does_not_forward_credential_across_hosts_on_redirect() {
+        let (port, server) = start_authorization_echo_server();
+        let output_file = tempdir().unwrap().into_path().join("echo");
+        file_downloader_with_credential_for_host("127.0.0.1")
+            .download_file(&format!("http://127.0.0.1:{}/redirect", port), &output_file)
+            .await
+            .unwrap();
+        server.abort();
+        assert_eq!("no authorization", read_to_string(output_file).await.unwrap());
+    }
+
     #[tokio::test]
     async fn can_download_a_file_to_directory() {
         let temp_dir = tempdir().unwrap().into_path();
-        let file_downloader = FileDownloader::new();
+        let file_downloader = FileDownloader::new_with_user_config(&UserConfig::default());
 
         let actual_path = file_downloader
             .download_file_to_directory(
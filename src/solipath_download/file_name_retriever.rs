@@ -25,7 +25,7 @@ fn get_file_name_from_content_disposition(header: &HeaderMap) -> Option<String>
 }
 
 
-fn get_string_after_last_forward_slash(url: &str) -> String {
+pub fn get_string_after_last_forward_slash(url: &str) -> String {
     let index_of_forward_slash: usize = url.rfind('/').expect("could not find a forward slash in url");
     let (_, string_after_last_slash) = url.split_at(index_of_forward_slash + 1);
     if let Some(index_of_question_mark) = string_after_last_slash.rfind('?') {
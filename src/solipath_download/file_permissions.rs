@@ -0,0 +1,54 @@
+use std::path::Path;
+
+use glob::glob;
+
+#[cfg(not(target_os = "windows"))]
+pub fn set_file_as_executable(executable_path: &Path) {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(executable_path, fs::Permissions::from_mode(0o775))
+        .unwrap_or_else(|error| panic!("failed to set {:?} with execute permissions: {}", executable_path, error));
+}
+#[cfg(target_os = "windows")]
+pub fn set_file_as_executable(_executable_path: &Path) {}
+
+pub fn set_matching_files_as_executable(directory: &Path, patterns: &[String]) {
+    patterns.iter().for_each(|pattern| {
+        let full_pattern = directory.join(pattern);
+        glob(full_pattern.to_str().expect("executable pattern should be valid utf-8"))
+            .unwrap_or_else(|error| panic!("invalid executable pattern '{}': {}", pattern, error))
+            .flatten()
+            .filter(|path| path.is_file())
+            .for_each(|path| set_file_as_executable(&path));
+    });
+}
+
+#[cfg(all(test, not(target_os = "windows")))]
+mod test {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn is_executable(path: &Path) -> bool {
+        fs::metadata(path).unwrap().permissions().mode() & 0o111 != 0
+    }
+
+    #[test]
+    fn sets_only_files_matching_pattern_as_executable() {
+        let temp_dir = tempdir().unwrap();
+        let bin_directory = temp_dir.path().join("tool/bin");
+        fs::create_dir_all(&bin_directory).unwrap();
+        fs::write(bin_directory.join("tool"), "binary").unwrap();
+        fs::write(temp_dir.path().join("tool/README"), "docs").unwrap();
+        fs::set_permissions(bin_directory.join("tool"), fs::Permissions::from_mode(0o644)).unwrap();
+        fs::set_permissions(temp_dir.path().join("tool/README"), fs::Permissions::from_mode(0o644)).unwrap();
+
+        set_matching_files_as_executable(temp_dir.path(), &["tool/bin/*".to_string()]);
+
+        assert!(is_executable(&bin_directory.join("tool")));
+        assert!(!is_executable(&temp_dir.path().join("tool/README")));
+    }
+}
@@ -1,4 +1,4 @@
-use std::{path::Path, sync::Arc};
+use std::{path::PathBuf, sync::Arc};
 
 use async_trait::async_trait;
 use tokio::fs::read_to_string;
@@ -11,7 +11,7 @@ use mockall::automock;
 #[cfg_attr(test, automock)]
 #[async_trait]
 pub trait FileToStringDownloaderTrait {
-    async fn download_file_then_parse_to_string(&self, url: &str, path_to_save_to: &Path) -> String;
+    async fn download_first_available_file_then_parse_to_string(&self, files: &[(String, PathBuf)]) -> (PathBuf, String);
 }
 
 pub struct FileToStringDownloader {
@@ -28,11 +28,24 @@ impl FileToStringDownloader {
 
 #[async_trait]
 impl FileToStringDownloaderTrait for FileToStringDownloader {
-    async fn download_file_then_parse_to_string(&self, url: &str, path_to_save_to: &Path) -> String {
-        self.conditional_file_downloader
-            .download_file_if_not_exists(url, path_to_save_to)
-            .await;
-        read_to_string(path_to_save_to).await.expect("failed to read file")
+    // a file that was already downloaded wins, otherwise each url is tried in order until one downloads
+    async fn download_first_available_file_then_parse_to_string(&self, files: &[(String, PathBuf)]) -> (PathBuf, String) {
+        let mut errors = Vec::new();
+        let existing_file = files.iter().find(|(_, path_to_save_to)| path_to_save_to.exists());
+        for (url, path_to_save_to) in existing_file.into_iter().chain(files) {
+            match self
+                .conditional_file_downloader
+                .download_file_if_not_exists(url, path_to_save_to)
+                .await
+            {
+                Ok(()) => {
+                    let contents = read_to_string(path_to_save_to).await.expect("failed to read file");
+                    return (path_to_save_to.clone(), contents);
+                }
+                Err(error) => errors.push(format!("{:#}", error)),
+            }
+        }
+        panic!("could not download any of these files:\n{}", errors.join("\n"))
     }
 }
 
@@ -62,12 +75,47 @@ mod test {
             .expect_download_file_if_not_exists()
             .withf(move |url, path| url == passed_in_url && path == copy_path_to_downloaded_file)
             .times(1)
-            .return_const(());
+            .returning(|_, _| Ok(()));
 
         let file_retriever = FileToStringDownloader::new(Arc::new(mock_file_downloader));
-        let actual = file_retriever
-            .download_file_then_parse_to_string(passed_in_url, &path_to_downloaded_file.clone())
+        let (actual_path, actual) = file_retriever
+            .download_first_available_file_then_parse_to_string(&[(
+                passed_in_url.to_string(),
+                path_to_downloaded_file.clone(),
+            )])
             .await;
         assert_eq!(actual, "the file contents");
+        assert_eq!(actual_path, path_to_downloaded_file);
+    }
+
+    #[tokio::test]
+    async fn tries_next_file_when_download_fails() {
+        let dependency_directory = tempdir().unwrap().into_path();
+        let json_path = dependency_directory.join("install_instructions.json");
+        let yaml_path = dependency_directory.join("install_instructions.yaml");
+        let copy_yaml_path = yaml_path.clone();
+        let mut mock_file_downloader = MockConditionalFileDownloaderTrait::new();
+        mock_file_downloader
+            .expect_download_file_if_not_exists()
+            .withf(|url, _| url.ends_with(".json"))
+            .times(1)
+            .returning(|_, _| Err(anyhow::anyhow!("404 Not Found")));
+        mock_file_downloader
+            .expect_download_file_if_not_exists()
+            .withf(|url, _| url.ends_with(".yaml"))
+            .times(1)
+            .returning(move |_, _| {
+                std::fs::write(&copy_yaml_path, "downloads: []").unwrap();
+                Ok(())
+            });
+
+        let file_retriever = FileToStringDownloader::new(Arc::new(mock_file_downloader));
+        let (actual_path, actual) = file_retriever
+            .download_first_available_file_then_parse_to_string(&[
+                ("http://example.com/install_instructions.json".to_string(), json_path),
+                ("http://example.com/install_instructions.yaml".to_string(), yaml_path.clone()),
+            ])
+            .await;
+        assert_eq!((yaml_path, "downloads: []".to_string()), (actual_path, actual));
     }
 }
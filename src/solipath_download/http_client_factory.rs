@@ -0,0 +1,108 @@
+use std::fs;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use reqwest::{Certificate, Client, Identity, NoProxy, Proxy};
+
+use crate::solipath_config::user_config::HttpSettings;
+
+// every reqwest client in solipath should come from here so proxy, TLS and timeout settings apply everywhere.
+// HTTPS_PROXY, HTTP_PROXY and NO_PROXY are honoured by reqwest unless a proxy is set in the user config.
+pub struct HttpClientFactory {
+    http_settings: HttpSettings,
+}
+
+impl HttpClientFactory {
+    pub fn new(http_settings: HttpSettings) -> Self {
+        Self { http_settings }
+    }
+
+    pub fn create_client(&self) -> Result<Client> {
+        let mut client_builder = Client::builder();
+        if let Some(proxy_url) = &self.http_settings.proxy {
+            let no_proxy = match &self.http_settings.no_proxy {
+                Some(no_proxy) => NoProxy::from_string(no_proxy),
+                None => NoProxy::from_env(),
+            };
+            let proxy = Proxy::all(proxy_url)
+                .with_context(|| format!("invalid proxy url: {}", proxy_url))?
+                .no_proxy(no_proxy);
+            client_builder = client_builder.proxy(proxy);
+        }
+        if let Some(ca_bundle) = &self.http_settings.ca_bundle {
+            let pem_bundle = fs::read(ca_bundle).with_context(|| format!("failed to read CA bundle {:?}", ca_bundle))?;
+            let certificates = Certificate::from_pem_bundle(&pem_bundle)
+                .with_context(|| format!("failed to parse CA bundle {:?}", ca_bundle))?;
+            for certificate in certificates {
+                client_builder = client_builder.add_root_certificate(certificate);
+            }
+        }
+        if let Some(client_certificate) = &self.http_settings.client_certificate {
+            let mut pem = fs::read(client_certificate)
+                .with_context(|| format!("failed to read client certificate {:?}", client_certificate))?;
+            if let Some(client_key) = &self.http_settings.client_key {
+                pem.extend(fs::read(client_key).with_context(|| format!("failed to read client key {:?}", client_key))?);
+            }
+            let identity = Identity::from_pem(&pem)
+                .with_context(|| format!("failed to parse client certificate {:?}", client_certificate))?;
+            client_builder = client_builder.identity(identity);
+        }
+        if let Some(connect_timeout_seconds) = self.http_settings.connect_timeout_seconds {
+            client_builder = client_builder.connect_timeout(Duration::from_secs(connect_timeout_seconds));
+        }
+        if let Some(read_timeout_seconds) = self.http_settings.read_timeout_seconds {
+            client_builder = client_builder.read_timeout(Duration::from_secs(read_timeout_seconds));
+        }
+        client_builder.build().context("failed to create http client")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use warp::Filter;
+
+    use super::*;
+
+    #[test]
+    fn can_create_client_with_default_settings() {
+        assert!(HttpClientFactory::new(HttpSettings::default()).create_client().is_ok());
+    }
+
+    #[test]
+    fn missing_ca_bundle_is_an_error() {
+        let http_settings = HttpSettings {
+            ca_bundle: Some(PathBuf::from("/does/not/exist/ca.pem")),
+            ..HttpSettings::default()
+        };
+        let error = HttpClientFactory::new(http_settings).create_client().unwrap_err();
+        assert!(error.to_string().starts_with("failed to read CA bundle"));
+    }
+
+    #[test]
+    fn invalid_proxy_is_an_error() {
+        let http_settings = HttpSettings {
+            proxy: Some("not a url".to_string()),
+            ..HttpSettings::default()
+        };
+        assert!(HttpClientFactory::new(http_settings).create_client().is_err());
+    }
+
+    #[tokio::test]
+    async fn requests_go_through_configured_proxy() {
+        let route = warp::path("proxied").map(|| "through the proxy");
+        let (address, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        let server_handle = tokio::spawn(server);
+        let http_settings = HttpSettings {
+            proxy: Some(format!("http://{}", address)),
+            ..HttpSettings::default()
+        };
+        let client = HttpClientFactory::new(http_settings).create_client().unwrap();
+
+        let response = client.get("http://solipath.invalid/proxied").send().await.unwrap();
+        server_handle.abort();
+
+        assert_eq!("through the proxy", response.text().await.unwrap());
+    }
+}
@@ -1,6 +1,11 @@
+pub mod artifact_cache;
 pub mod conditional_file_downloader;
+pub mod credential_retriever;
 pub mod file_decompressor;
 pub mod file_downloader;
 pub mod file_to_string_downloader;
 pub mod file_name_retriever;
+pub mod file_permissions;
+pub mod http_client_factory;
+pub mod retry_policy;
 pub mod dependency_downloader;
@@ -0,0 +1,273 @@
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Response, StatusCode};
+use tokio::time::sleep;
+
+use crate::solipath_config::user_config::RetrySettings;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff_milliseconds: u64,
+    pub max_backoff_milliseconds: u64,
+    pub max_total_seconds: u64,
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(&RetrySettings::default())
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(retry_settings: &RetrySettings) -> Self {
+        Self {
+            max_attempts: retry_settings.max_attempts,
+            initial_backoff_milliseconds: retry_settings.initial_backoff_milliseconds,
+            max_backoff_milliseconds: retry_settings.max_backoff_milliseconds,
+            max_total_seconds: retry_settings.max_total_seconds,
+            jitter: retry_settings.jitter,
+        }
+    }
+
+    // client errors won't change by asking again, except for a timed out or rate limited request
+    pub fn should_retry_status(status: StatusCode) -> bool {
+        status == StatusCode::REQUEST_TIMEOUT || status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+    }
+
+    pub fn get_backoff(&self, attempt: u32) -> Duration {
+        let multiplier = 1u64.checked_shl(attempt.saturating_sub(1)).unwrap_or(u64::MAX);
+        let backoff_milliseconds = self
+            .initial_backoff_milliseconds
+            .saturating_mul(multiplier)
+            .min(self.max_backoff_milliseconds);
+        if self.jitter {
+            let half_backoff = backoff_milliseconds / 2;
+            Duration::from_millis(half_backoff + random_number() % (half_backoff + 1))
+        } else {
+            Duration::from_millis(backoff_milliseconds)
+        }
+    }
+
+    pub fn get_delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        retry_after.unwrap_or_else(|| self.get_backoff(attempt))
+    }
+
+    // returns the first successful or non retryable response. Once attempts or total time run out, the last response
+    // or error is returned so the caller decides what a failure looks like.
+    pub async fn send_with_retries<FUNCTION, FUTURE>(&self, url: &str, send_request: FUNCTION) -> reqwest::Result<Response>
+    where
+        FUNCTION: Fn() -> FUTURE,
+        FUTURE: Future<Output = reqwest::Result<Response>>,
+    {
+        let start_time = Instant::now();
+        let max_total_time = Duration::from_secs(self.max_total_seconds);
+        let mut attempt = 1;
+        loop {
+            let result = send_request().await;
+            let retry_after = match &result {
+                Ok(response) if !Self::should_retry_status(response.status()) => return result,
+                Ok(response) => parse_retry_after(response.headers()),
+                Err(_) => None,
+            };
+            let delay = self.get_delay(attempt, retry_after);
+            if attempt >= self.max_attempts || start_time.elapsed() + delay > max_total_time {
+                return result;
+            }
+            println!("Error downloading {}, trying again in {:?}...", url, delay);
+            sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+pub fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let retry_after = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = retry_after.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let retry_time = parse_http_date(retry_after)?;
+    Some(retry_time.duration_since(SystemTime::now()).unwrap_or(Duration::ZERO))
+}
+
+// IMF-fixdate, e.g. "Sun, 06 Nov 1994 08:49:37 GMT", which is what servers send in Retry-After
+fn parse_http_date(value: &str) -> Option<SystemTime> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [_, day, month, year, time, "GMT"] = parts.as_slice() else {
+        return None;
+    };
+    let months = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+    let month = months.iter().position(|name| name == month)? as i64 + 1;
+    let day = day.parse::<i64>().ok()?;
+    let year = year.parse::<i64>().ok()?;
+    let time_parts = time
+        .split(':')
+        .map(|part| part.parse::<i64>().ok())
+        .collect::<Option<Vec<i64>>>()?;
+    let [hours, minutes, seconds] = time_parts.as_slice() else {
+        return None;
+    };
+    let seconds_since_epoch = days_since_epoch(year, month, day) * 86400 + hours * 3600 + minutes * 60 + seconds;
+    Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(seconds_since_epoch).ok()?))
+}
+
+fn days_since_epoch(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_from_march = (month + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn random_number() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use reqwest::header::HeaderValue;
+    use reqwest::Client;
+    use tokio::task::JoinHandle;
+    use warp::Filter;
+
+    use super::*;
+
+    fn fast_retry_policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 4,
+            initial_backoff_milliseconds: 10,
+            max_backoff_milliseconds: 40,
+            max_total_seconds: 10,
+            jitter: false,
+        }
+    }
+
+    fn start_scripted_server(
+        statuses: Vec<u16>,
+        retry_after: Option<&'static str>,
+    ) -> (String, Arc<AtomicUsize>, JoinHandle<()>) {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let server_attempts = attempts.clone();
+        let route = warp::path("scripted").map(move || {
+            let attempt = server_attempts.fetch_add(1, Ordering::SeqCst);
+            let status = statuses.get(attempt).copied().unwrap_or(200);
+            let mut response = warp::http::Response::builder().status(status);
+            if let Some(retry_after) = retry_after {
+                response = response.header("retry-after", retry_after);
+            }
+            response.body("scripted body").unwrap()
+        });
+        let (address, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        (format!("http://{}/scripted", address), attempts, tokio::spawn(server))
+    }
+
+    async fn send(retry_policy: &RetryPolicy, url: &str) -> reqwest::Result<Response> {
+        let client = Client::new();
+        retry_policy.send_with_retries(url, || client.get(url).send()).await
+    }
+
+    #[test]
+    fn retries_only_timeouts_rate_limits_and_server_errors() {
+        assert!(RetryPolicy::should_retry_status(StatusCode::REQUEST_TIMEOUT));
+        assert!(RetryPolicy::should_retry_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(RetryPolicy::should_retry_status(StatusCode::BAD_GATEWAY));
+        assert!(!RetryPolicy::should_retry_status(StatusCode::NOT_FOUND));
+        assert!(!RetryPolicy::should_retry_status(StatusCode::UNAUTHORIZED));
+    }
+
+    #[test]
+    fn backoff_doubles_until_max() {
+        let retry_policy = fast_retry_policy();
+        assert_eq!(Duration::from_millis(10), retry_policy.get_backoff(1));
+        assert_eq!(Duration::from_millis(20), retry_policy.get_backoff(2));
+        assert_eq!(Duration::from_millis(40), retry_policy.get_backoff(3));
+        assert_eq!(Duration::from_millis(40), retry_policy.get_backoff(4));
+        assert_eq!(Duration::from_millis(40), retry_policy.get_backoff(100));
+    }
+
+    #[test]
+    fn jitter_stays_between_half_and_full_backoff() {
+        let retry_policy = RetryPolicy {
+            jitter: true,
+            ..fast_retry_policy()
+        };
+        for _ in 0..20 {
+            let backoff = retry_policy.get_backoff(3);
+            assert!(backoff >= Duration::from_millis(20) && backoff <= Duration::from_millis(40));
+        }
+    }
+
+    #[test]
+    fn can_parse_retry_after_seconds_and_dates() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("7"));
+        assert_eq!(Some(Duration::from_secs(7)), parse_retry_after(&headers));
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("Sun, 06 Nov 1994 08:49:37 GMT"));
+        assert_eq!(Some(Duration::ZERO), parse_retry_after(&headers));
+        assert_eq!(
+            Some(UNIX_EPOCH + Duration::from_secs(784111777)),
+            parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT")
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_until_success() {
+        let (url, attempts, server) = start_scripted_server(vec![503, 502, 200], None);
+        let response = send(&fast_retry_policy(), &url).await.unwrap();
+        server.abort();
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(3, attempts.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_not_found() {
+        let (url, attempts, server) = start_scripted_server(vec![404], None);
+        let response = send(&fast_retry_policy(), &url).await.unwrap();
+        server.abort();
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+        assert_eq!(1, attempts.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let (url, attempts, server) = start_scripted_server(vec![500, 500, 500, 500, 500], None);
+        let response = send(&fast_retry_policy(), &url).await.unwrap();
+        server.abort();
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, response.status());
+        assert_eq!(4, attempts.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn waits_for_retry_after_on_rate_limit() {
+        let (url, attempts, server) = start_scripted_server(vec![429], Some("1"));
+        let start_time = Instant::now();
+        let response = send(&fast_retry_policy(), &url).await.unwrap();
+        server.abort();
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(2, attempts.load(Ordering::SeqCst));
+        assert!(start_time.elapsed() >= Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn does_not_wait_past_max_total_time() {
+        let (url, attempts, server) = start_scripted_server(vec![429], Some("5"));
+        let retry_policy = RetryPolicy {
+            max_total_seconds: 1,
+            ..fast_retry_policy()
+        };
+        let response = send(&retry_policy, &url).await.unwrap();
+        server.abort();
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, response.status());
+        assert_eq!(1, attempts.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::path::Path;
+
+use anyhow::{bail, Result};
+
+use crate::solipath_environment_variable::resolved_environment::ResolvedEnvironment;
+use crate::solipath_instructions::data::environment_variable::EnvironmentVariable;
+
+// a missing file is skipped, so optional files like .env.local can always be listed
+pub fn read_env_file(path: &Path, environment: &ResolvedEnvironment) -> Result<Vec<EnvironmentVariable>> {
+    match read_to_string(path) {
+        Ok(contents) => parse_env_file(path, &contents, environment),
+        Err(_) => Ok(Vec::new()),
+    }
+}
+
+// KEY=VALUE lines, with optional `export` prefixes, surrounding quotes and # comments. Like dotenv, a plain ${VAR}
+// is the value of VAR set earlier in the file or in the environment, and single quoted values are kept as written.
+// solipath's own ${env:...}, ${dep:...} and ${SOLIPATH_HOME} are left for the variable interpolator.
+pub fn parse_env_file(path: &Path, contents: &str, environment: &ResolvedEnvironment) -> Result<Vec<EnvironmentVariable>> {
+    let mut file_values: HashMap<String, String> = HashMap::new();
+    let mut environment_variables = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((name, value)) = line.split_once('=') else {
+            continue;
+        };
+        let name = name.trim();
+        let value = value.trim();
+        let literal = value.strip_prefix('\'').and_then(|value| value.strip_suffix('\''));
+        let expanded_value = match literal {
+            Some(literal) => literal.to_string(),
+            None => expand(unquote(value), &file_values, environment)
+                .map_err(|error| error.context(format!("{}:{}", path.display(), index + 1)))?,
+        };
+        file_values.insert(name.to_string(), expanded_value.clone());
+        let environment_variable = EnvironmentVariable::new(name, Some(expanded_value), None, None, 0, Vec::new());
+        environment_variables.push(if literal.is_some() {
+            environment_variable.as_literal()
+        } else {
+            environment_variable
+        });
+    }
+    Ok(environment_variables)
+}
+
+fn expand(value: &str, file_values: &HashMap<String, String>, environment: &ResolvedEnvironment) -> Result<String> {
+    let mut expanded = String::new();
+    let mut remaining = value;
+    while let Some(start) = remaining.find("${") {
+        let Some(length) = remaining[start..].find('}') else {
+            bail!("unterminated ${{ in {}", value);
+        };
+        let end = start + length;
+        let expression = &remaining[start + 2..end];
+        expanded.push_str(&remaining[..start]);
+        if is_solipath_variable(expression) {
+            expanded.push_str(&remaining[start..=end]);
+        } else if !is_variable_name(expression) {
+            bail!("cannot resolve ${{{}}}", expression);
+        } else if let Some(file_value) = file_values.get(expression) {
+            expanded.push_str(file_value);
+        } else if let Some(environment_value) = environment.get(expression) {
+            expanded.push_str(&environment_value.to_string_lossy());
+        } else {
+            bail!("cannot resolve ${{{}}}, {} is not set", expression, expression);
+        }
+        remaining = &remaining[end + 1..];
+    }
+    expanded.push_str(remaining);
+    Ok(expanded)
+}
+
+fn is_solipath_variable(expression: &str) -> bool {
+    expression == "SOLIPATH_HOME" || expression.starts_with("env:") || expression.starts_with("dep:")
+}
+
+fn is_variable_name(expression: &str) -> bool {
+    !expression.is_empty()
+        && !expression.starts_with(|character: char| character.is_ascii_digit())
+        && expression.chars().all(|character| character.is_ascii_alphanumeric() || character == '_')
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsString;
+
+    fn parse(contents: &str) -> Result<Vec<(String, Option<String>)>> {
+        let environment = ResolvedEnvironment::new(vec![(OsString::from("HOME"), OsString::from("/home/user"))]);
+        Ok(parse_env_file(Path::new("project/.env"), contents, &environment)?
+            .iter()
+            .map(|environment_variable| (environment_variable.get_name(), environment_variable.get_value().clone()))
+            .collect())
+    }
+
+    #[test]
+    fn can_parse_env_file() {
+        let parsed = parse(
+            r#"
+            # build settings
+            NODE_ENV=development
+            export GRADLE_OPTS="-Xmx2g -Dfile.encoding=UTF-8"
+            GREETING='hello world'
+            CONNECTION=postgres://localhost:5432/db?sslmode=disable
+            not a variable
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![
+                ("NODE_ENV".to_string(), Some("development".to_string())),
+                ("GRADLE_OPTS".to_string(), Some("-Xmx2g -Dfile.encoding=UTF-8".to_string())),
+                ("GREETING".to_string(), Some("hello world".to_string())),
+                ("CONNECTION".to_string(), Some("postgres://localhost:5432/db?sslmode=disable".to_string())),
+            ],
+            parsed
+        );
+    }
+
+    #[test]
+    fn plain_variables_come_from_the_file_then_the_environment() {
+        let parsed = parse(
+            r#"
+            CACHE=${HOME}/.cache
+            GRADLE_USER_HOME="${CACHE}/gradle"
+            LITERAL='${HOME}'
+            JAVA=${dep:java:env:JAVA_HOME}/bin
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![
+                ("CACHE".to_string(), Some("/home/user/.cache".to_string())),
+                ("GRADLE_USER_HOME".to_string(), Some("/home/user/.cache/gradle".to_string())),
+                ("LITERAL".to_string(), Some("${HOME}".to_string())),
+                ("JAVA".to_string(), Some("${dep:java:env:JAVA_HOME}/bin".to_string())),
+            ],
+            parsed
+        );
+    }
+
+    #[test]
+    fn unresolvable_variables_are_errors_naming_the_file_and_line() {
+        let error = parse("NODE_ENV=development\nCACHE=${NOT_SET}/.cache").unwrap_err();
+        assert_eq!(
+            "project/.env:2: cannot resolve ${NOT_SET}, NOT_SET is not set",
+            format!("{:#}", error)
+        );
+        assert!(format!("{:#}", parse("BROKEN=${HOME").unwrap_err()).starts_with("project/.env:1: unterminated"));
+        assert!(parse("ODD=${what:ever}").is_err());
+    }
+
+    #[test]
+    fn missing_env_file_has_no_variables() {
+        assert!(read_env_file(Path::new("does/not/exist/.env.local"), &ResolvedEnvironment::new(Vec::new()))
+            .unwrap()
+            .is_empty());
+    }
+}
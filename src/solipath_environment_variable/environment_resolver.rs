@@ -0,0 +1,545 @@
+use crate::solipath_instructions::data::dependency::Dependency;
+use crate::solipath_directory::solipath_directory_finder::SolipathDirectoryFinderTrait;
+use crate::solipath_environment_variable::resolved_environment::ResolvedEnvironment;
+use crate::solipath_environment_variable::variable_interpolator::{order_by_references, VariableInterpolator};
+use crate::solipath_instructions::data::environment_variable::{EnvironmentVariable, EnvironmentVariableOperation};
+use anyhow::Result;
+use std::cmp::Reverse;
+use std::env::current_dir;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[cfg(test)]
+use mockall::automock;
+
+#[cfg_attr(test, automock)]
+pub trait EnvironmentResolverTrait {
+    fn resolve_variable(
+        &self,
+        base_environment: ResolvedEnvironment,
+        dependency: &Dependency,
+        environment_variable: &EnvironmentVariable,
+    ) -> Result<ResolvedEnvironment>;
+    // project variables from solipath.json are applied after every dependency, their relative paths are relative to
+    // SOLIPATH_PROJECT_ROOT, or the current directory when it isn't set
+    fn resolve_variables<'a>(
+        &self,
+        base_environment: ResolvedEnvironment,
+        dependencies: &[Dependency],
+        environment_variables: &[(&'a Dependency, &'a EnvironmentVariable)],
+        project_variables: &[EnvironmentVariable],
+    ) -> Result<ResolvedEnvironment>;
+    // the value each dependency variable resolves to on its own, unset variables have no value and are left out
+    fn get_dependency_values<'a>(
+        &self,
+        base_environment: &ResolvedEnvironment,
+        dependencies: &[Dependency],
+        environment_variables: &[(&'a Dependency, &'a EnvironmentVariable)],
+    ) -> Result<Vec<DependencyValue>>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DependencyValue {
+    pub dependency: Dependency,
+    pub name: String,
+    pub operation: EnvironmentVariableOperation,
+    pub value: PathBuf,
+}
+
+pub struct EnvironmentResolver {
+    directory_finder: Arc<dyn SolipathDirectoryFinderTrait + Sync + Send>,
+}
+
+impl EnvironmentResolver {
+    pub fn new(directory_finder: Arc<dyn SolipathDirectoryFinderTrait + Sync + Send>) -> Self {
+        Self { directory_finder }
+    }
+
+    fn convert_relative_path_from_downloads_directory_to_absolute_path(
+        &self,
+        interpolator: &VariableInterpolator,
+        dependency: &Dependency,
+        environment_variable: &EnvironmentVariable,
+    ) -> Result<PathBuf> {
+        let mut download_directory = self.directory_finder.get_dependency_downloads_directory(&dependency);
+        let relative_path = environment_variable.get_relative_path().as_ref().expect("expected relative path to be defined");
+        download_directory.push(interpolator.interpolate(Some(dependency), relative_path)?);
+        Ok(download_directory)
+    }
+
+    fn get_value(
+        &self,
+        interpolator: &VariableInterpolator,
+        dependency: &Dependency,
+        environment_variable: &EnvironmentVariable,
+    ) -> Result<PathBuf> {
+        if let Some(value) = environment_variable.get_value() {
+            Ok(PathBuf::from(interpolator.interpolate(Some(dependency), value)?))
+        } else {
+            self.convert_relative_path_from_downloads_directory_to_absolute_path(interpolator, dependency, environment_variable)
+        }
+    }
+
+    // path list entries are returned instead of applied, so they can be added in a deterministic order
+    fn apply_variable(
+        &self,
+        environment: &mut ResolvedEnvironment,
+        interpolator: &mut VariableInterpolator,
+        dependency: &Dependency,
+        environment_variable: &EnvironmentVariable,
+    ) -> Result<Option<PathListEntry>> {
+        let name = environment_variable.get_name();
+        let operation = environment_variable.get_operation();
+        if operation == EnvironmentVariableOperation::Unset {
+            environment.apply(&name, operation, None);
+            return Ok(None);
+        }
+        let value = self.get_value(interpolator, dependency, environment_variable)?;
+        interpolator.add_resolved_value(dependency, &name, &value.to_string_lossy());
+        if is_path_list_operation(operation) {
+            return Ok(Some(PathListEntry {
+                name,
+                operation,
+                value,
+                priority: environment_variable.get_priority(),
+            }));
+        }
+        environment.apply(&name, operation, Some(value.into_os_string()));
+        Ok(None)
+    }
+
+    fn apply_project_variable(
+        &self,
+        environment: &mut ResolvedEnvironment,
+        interpolator: &VariableInterpolator,
+        project_root: &Path,
+        project_variable: &EnvironmentVariable,
+    ) -> Result<Option<PathListEntry>> {
+        let name = project_variable.get_name();
+        let operation = project_variable.get_operation();
+        let interpolate = |text: &str| {
+            if project_variable.is_literal() {
+                Ok(text.to_string())
+            } else {
+                interpolator.interpolate(None, text)
+            }
+        };
+        let value = if let Some(value) = project_variable.get_value() {
+            PathBuf::from(interpolate(value)?)
+        } else if let Some(relative_path) = project_variable.get_relative_path() {
+            project_root.join(interpolate(relative_path)?)
+        } else {
+            PathBuf::new()
+        };
+        if is_path_list_operation(operation) {
+            return Ok(Some(PathListEntry {
+                name,
+                operation,
+                value,
+                priority: project_variable.get_priority(),
+            }));
+        }
+        let value = (operation != EnvironmentVariableOperation::Unset).then(|| value.into_os_string());
+        environment.apply(&name, operation, value);
+        Ok(None)
+    }
+}
+
+struct PathListEntry {
+    name: String,
+    operation: EnvironmentVariableOperation,
+    value: PathBuf,
+    priority: i32,
+}
+
+fn is_path_list_operation(operation: EnvironmentVariableOperation) -> bool {
+    operation == EnvironmentVariableOperation::PrependPath || operation == EnvironmentVariableOperation::AppendPath
+}
+
+// entries are expected in precedence order: earlier entries end up earlier in the variable, whether they are
+// prepended in front of the existing value or appended after it
+fn apply_path_list_entries(environment: &mut ResolvedEnvironment, entries: Vec<PathListEntry>) {
+    let (prepends, appends): (Vec<PathListEntry>, Vec<PathListEntry>) = entries
+        .into_iter()
+        .partition(|entry| entry.operation == EnvironmentVariableOperation::PrependPath);
+    prepends
+        .into_iter()
+        .rev()
+        .chain(appends)
+        .for_each(|entry| environment.apply(&entry.name, entry.operation, Some(entry.value.into_os_string())));
+}
+
+impl EnvironmentResolverTrait for EnvironmentResolver {
+    fn resolve_variable(
+        &self,
+        base_environment: ResolvedEnvironment,
+        dependency: &Dependency,
+        environment_variable: &EnvironmentVariable,
+    ) -> Result<ResolvedEnvironment> {
+        self.resolve_variables(
+            base_environment,
+            std::slice::from_ref(dependency),
+            &[(dependency, environment_variable)],
+            &[],
+        )
+    }
+
+    // path lists are ordered by priority, then project variables, then the order of dependencies in solipath.json,
+    // then the order the variables are written in
+    fn resolve_variables<'a>(
+        &self,
+        base_environment: ResolvedEnvironment,
+        dependencies: &[Dependency],
+        environment_variables: &[(&'a Dependency, &'a EnvironmentVariable)],
+        project_variables: &[EnvironmentVariable],
+    ) -> Result<ResolvedEnvironment> {
+        let mut environment = base_environment.clone();
+        let mut interpolator = VariableInterpolator::new(self.directory_finder.clone(), dependencies, &base_environment);
+        let mut path_list_entries: Vec<(usize, PathListEntry)> = Vec::new();
+        for (dependency, environment_variable) in order_by_references(environment_variables)? {
+            if let Some(entry) = self.apply_variable(&mut environment, &mut interpolator, dependency, environment_variable)? {
+                path_list_entries.push((get_position(dependencies, dependency), entry));
+            }
+        }
+        let project_root = base_environment
+            .get("SOLIPATH_PROJECT_ROOT")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| current_dir().expect("failed to get current directory"));
+        for project_variable in project_variables {
+            if let Some(entry) = self.apply_project_variable(&mut environment, &interpolator, &project_root, project_variable)? {
+                path_list_entries.push((0, entry));
+            }
+        }
+        path_list_entries.sort_by_key(|(position, entry)| (Reverse(entry.priority), *position));
+        apply_path_list_entries(&mut environment, path_list_entries.into_iter().map(|(_, entry)| entry).collect());
+        Ok(environment)
+    }
+
+    fn get_dependency_values<'a>(
+        &self,
+        base_environment: &ResolvedEnvironment,
+        dependencies: &[Dependency],
+        environment_variables: &[(&'a Dependency, &'a EnvironmentVariable)],
+    ) -> Result<Vec<DependencyValue>> {
+        let mut interpolator = VariableInterpolator::new(self.directory_finder.clone(), dependencies, base_environment);
+        order_by_references(environment_variables)?
+            .into_iter()
+            .filter(|(_, environment_variable)| environment_variable.get_operation() != EnvironmentVariableOperation::Unset)
+            .map(|(dependency, environment_variable)| {
+                let name = environment_variable.get_name();
+                let value = self.get_value(&interpolator, dependency, environment_variable)?;
+                interpolator.add_resolved_value(dependency, &name, &value.to_string_lossy());
+                Ok(DependencyValue {
+                    dependency: dependency.clone(),
+                    name,
+                    operation: environment_variable.get_operation(),
+                    value,
+                })
+            })
+            .collect()
+    }
+}
+
+fn get_position(dependencies: &[Dependency], dependency: &Dependency) -> usize {
+    dependencies
+        .iter()
+        .position(|other_dependency| other_dependency.name == dependency.name)
+        .map(|position| position + 1)
+        .unwrap_or(dependencies.len() + 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::solipath_directory::solipath_directory_finder::MockSolipathDirectoryFinderTrait;
+    use crate::solipath_environment_variable::env_file::parse_env_file;
+    use mockall::predicate::*;
+    use std::env::join_paths;
+    use std::ffi::OsString;
+
+    fn base_environment(variables: &[(&str, OsString)]) -> ResolvedEnvironment {
+        ResolvedEnvironment::new(
+            variables
+                .iter()
+                .map(|(name, value)| (OsString::from(name), value.clone()))
+                .collect(),
+        )
+    }
+
+    fn resolve_variable_from_json(base_environment: ResolvedEnvironment, json: &str) -> ResolvedEnvironment {
+        let environment_variable = serde_json::from_str::<EnvironmentVariable>(json).unwrap();
+        let environment_resolver = EnvironmentResolver::new(Arc::new(MockSolipathDirectoryFinderTrait::new()));
+        environment_resolver
+            .resolve_variable(base_environment, &Dependency::new("dependency", "1.0"), &environment_variable)
+            .unwrap()
+    }
+
+    fn join(paths: &[&str]) -> OsString {
+        join_paths(paths).unwrap()
+    }
+
+    fn get(environment: &ResolvedEnvironment, name: &str) -> String {
+        environment.get(name).unwrap().to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn can_prepend_to_any_path_list_without_duplicates() {
+        let environment = resolve_variable_from_json(
+            base_environment(&[("LIBRARY_PATH", join(&["/usr/lib", "/opt/lib"]))]),
+            r#"{"name": "LIBRARY_PATH", "value": "/opt/lib", "operation": "prepend_path"}"#,
+        );
+        assert_eq!(join(&["/opt/lib", "/usr/lib"]), environment.get("LIBRARY_PATH").unwrap());
+    }
+
+    #[test]
+    fn can_append_to_path_list() {
+        let environment = resolve_variable_from_json(
+            base_environment(&[("LIBRARY_PATH", join(&["/usr/lib"]))]),
+            r#"{"name": "LIBRARY_PATH", "value": "/opt/lib", "operation": "append_path"}"#,
+        );
+        let environment = resolve_variable_from_json(
+            environment,
+            r#"{"name": "LIBRARY_PATH", "value": "/usr/lib", "operation": "append_path"}"#,
+        );
+        assert_eq!(join(&["/usr/lib", "/opt/lib"]), environment.get("LIBRARY_PATH").unwrap());
+    }
+
+    #[test]
+    fn path_list_operations_create_missing_variable() {
+        let environment = resolve_variable_from_json(
+            base_environment(&[]),
+            r#"{"name": "LIBRARY_PATH", "value": "/opt/lib", "operation": "append_path"}"#,
+        );
+        assert_eq!("/opt/lib", get(&environment, "LIBRARY_PATH"));
+    }
+
+    #[test]
+    fn set_if_absent_keeps_existing_value() {
+        let environment = resolve_variable_from_json(
+            base_environment(&[("EXISTING", OsString::from("original"))]),
+            r#"{"name": "EXISTING", "value": "new", "operation": "set_if_absent"}"#,
+        );
+        let environment =
+            resolve_variable_from_json(environment, r#"{"name": "ABSENT", "value": "new", "operation": "set_if_absent"}"#);
+        assert_eq!("original", get(&environment, "EXISTING"));
+        assert_eq!("new", get(&environment, "ABSENT"));
+    }
+
+    #[test]
+    fn can_point_variable_at_value_set_by_another_dependency() {
+        let gradle = Dependency::new("gradle", "8.10");
+        let java = Dependency::new("java", "21");
+        let gradle_java_home = serde_json::from_str::<EnvironmentVariable>(
+            r#"{"name": "GRADLE_JAVA_HOME", "value": "${dep:java:env:JAVA_HOME}"}"#,
+        )
+        .unwrap();
+        let java_home =
+            serde_json::from_str::<EnvironmentVariable>(r#"{"name": "JAVA_HOME", "relative_path": "jdk-21"}"#).unwrap();
+        let mut directory_finder = MockSolipathDirectoryFinderTrait::new();
+        directory_finder
+            .expect_get_dependency_downloads_directory()
+            .return_const(PathBuf::from("solipath/java/downloads"));
+        let environment_resolver = EnvironmentResolver::new(Arc::new(directory_finder));
+
+        let environment = environment_resolver.resolve_variables(
+            base_environment(&[]),
+            &[gradle.clone(), java.clone()],
+            &[(&gradle, &gradle_java_home), (&java, &java_home)],
+            &[],
+        ).unwrap();
+
+        let expected_path = PathBuf::from("solipath/java/downloads").join("jdk-21");
+        assert_eq!(expected_path, PathBuf::from(get(&environment, "GRADLE_JAVA_HOME")));
+    }
+
+    #[test]
+    fn project_variables_are_set_after_dependencies_and_can_reference_them() {
+        let java = Dependency::new("java", "21");
+        let java_home =
+            serde_json::from_str::<EnvironmentVariable>(r#"{"name": "JAVA_HOME", "value": "/jdk-21"}"#).unwrap();
+        let project_variables = vec![
+            EnvironmentVariable::new(
+                "JAVA_HOME",
+                Some("${dep:java:env:JAVA_HOME}/jre".to_string()),
+                None,
+                None,
+                0,
+                Vec::new(),
+            ),
+            EnvironmentVariable::new("SCRIPTS", None, Some("scripts".to_string()), None, 0, Vec::new()),
+        ];
+        let environment_resolver = EnvironmentResolver::new(Arc::new(MockSolipathDirectoryFinderTrait::new()));
+
+        let environment = environment_resolver.resolve_variables(
+            base_environment(&[]),
+            std::slice::from_ref(&java),
+            &[(&java, &java_home)],
+            &project_variables,
+        ).unwrap();
+
+        assert_eq!("/jdk-21/jre", get(&environment, "JAVA_HOME"));
+        assert_eq!(current_dir().unwrap().join("scripts"), PathBuf::from(get(&environment, "SCRIPTS")));
+    }
+
+    #[test]
+    fn single_quoted_env_file_values_are_not_interpolated() {
+        let project_variables = parse_env_file(
+            Path::new(".env"),
+            "LITERAL='${dep:missing:downloads} costs $5'\nHOME_BIN=${env:HOME}/bin",
+            &base_environment(&[]),
+        )
+        .unwrap();
+        let environment_resolver = EnvironmentResolver::new(Arc::new(MockSolipathDirectoryFinderTrait::new()));
+
+        let environment = environment_resolver
+            .resolve_variables(base_environment(&[("HOME", "/home/me".into())]), &[], &[], &project_variables)
+            .unwrap();
+
+        assert_eq!("${dep:missing:downloads} costs $5", get(&environment, "LITERAL"));
+        assert_eq!("/home/me/bin", get(&environment, "HOME_BIN"));
+    }
+
+    #[test]
+    fn project_relative_paths_are_relative_to_project_root() {
+        let project_variables =
+            vec![EnvironmentVariable::new("PATH", None, Some("scripts".to_string()), None, 0, Vec::new())];
+        let environment_resolver = EnvironmentResolver::new(Arc::new(MockSolipathDirectoryFinderTrait::new()));
+
+        let environment = environment_resolver.resolve_variables(
+            base_environment(&[("SOLIPATH_PROJECT_ROOT", OsString::from("/projects/monorepo"))]),
+            &[],
+            &[],
+            &project_variables,
+        ).unwrap();
+
+        assert_eq!(
+            vec![PathBuf::from("/projects/monorepo").join("scripts")],
+            environment.get_path_list("PATH")
+        );
+    }
+
+    #[test]
+    fn path_entries_follow_dependency_order_then_priority() {
+        let java = Dependency::new("java", "21");
+        let gradle = Dependency::new("gradle", "8.10");
+        let node = Dependency::new("node", "20");
+        let java_bin = serde_json::from_str::<EnvironmentVariable>(r#"{"name": "PATH", "value": "/java/bin"}"#).unwrap();
+        let gradle_bin =
+            serde_json::from_str::<EnvironmentVariable>(r#"{"name": "PATH", "value": "/gradle/bin"}"#).unwrap();
+        let node_bin = serde_json::from_str::<EnvironmentVariable>(
+            r#"{"name": "PATH", "value": "/node/bin", "priority": 10}"#,
+        )
+        .unwrap();
+        let environment_resolver = EnvironmentResolver::new(Arc::new(MockSolipathDirectoryFinderTrait::new()));
+
+        let environment = environment_resolver.resolve_variables(
+            base_environment(&[("PATH", join(&["/usr/bin"]))]),
+            &[java.clone(), gradle.clone(), node.clone()],
+            &[(&gradle, &gradle_bin), (&node, &node_bin), (&java, &java_bin)],
+            &[],
+        ).unwrap();
+
+        assert_eq!(
+            join(&["/node/bin", "/java/bin", "/gradle/bin", "/usr/bin"]),
+            environment.get("PATH").unwrap()
+        );
+    }
+
+    #[test]
+    fn can_unset_variable() {
+        let environment = resolve_variable_from_json(
+            base_environment(&[("UNSET_ME", OsString::from("value"))]),
+            r#"{"name": "UNSET_ME", "operation": "unset"}"#,
+        );
+        assert!(environment.get("UNSET_ME").is_none());
+    }
+
+    #[test]
+    fn resolving_does_not_change_process_environment() {
+        let environment = resolve_variable_from_json(
+            ResolvedEnvironment::from_current_process(),
+            r#"{"name": "SOLIPATH_TEST_NOT_IN_PROCESS", "value": "value"}"#,
+        );
+        assert_eq!("value", get(&environment, "SOLIPATH_TEST_NOT_IN_PROCESS"));
+        assert!(std::env::var_os("SOLIPATH_TEST_NOT_IN_PROCESS").is_none());
+    }
+
+    #[test]
+    fn can_set_variable_for_rust_test() {
+        let dependency = Dependency::new("dependency", "123.12");
+        let environment_variable = serde_json::from_str::<EnvironmentVariable>(
+            r#"{"name": "RUST_TEST", "relative_path": "some/path/location"}"#,
+        )
+        .unwrap();
+        let mut directory_finder = MockSolipathDirectoryFinderTrait::new();
+        directory_finder
+            .expect_get_dependency_downloads_directory()
+            .with(eq(dependency.clone()))
+            .return_const(PathBuf::from("solipath/home/downloads/dir"));
+        let environment_resolver = EnvironmentResolver::new(Arc::new(directory_finder));
+        let environment = environment_resolver.resolve_variable(base_environment(&[]), &dependency, &environment_variable).unwrap();
+        assert_eq!(
+            PathBuf::from(get(&environment, "RUST_TEST")),
+            PathBuf::from("solipath/home/downloads/dir/some/path/location")
+        );
+    }
+
+    #[test]
+    fn can_set_variable_as_value() {
+        let dependency = Dependency::new("dependency", "123.12");
+        let environment_variable = serde_json::from_str::<EnvironmentVariable>(
+            r#"{"name": "RUST_TEST", "value": "someValue"}"#,
+        )
+        .unwrap();
+        let directory_finder = MockSolipathDirectoryFinderTrait::new();
+        let environment_resolver = EnvironmentResolver::new(Arc::new(directory_finder));
+        let environment = environment_resolver.resolve_variable(base_environment(&[]), &dependency, &environment_variable).unwrap();
+        assert_eq!(
+            PathBuf::from(get(&environment, "RUST_TEST")),
+            PathBuf::from("someValue")
+        );
+    }
+    #[test]
+    fn can_append_value_to_path() {
+        let original_path = join(&["/usr/bin", "/bin"]);
+        let dependency = Dependency::new("dependency", "555.213");
+        let environment_variable =
+            serde_json::from_str::<EnvironmentVariable>(r#"{"name": "PATH", "value": "~/path/location"}"#)
+                .unwrap();
+        let directory_finder = MockSolipathDirectoryFinderTrait::new();
+        let environment_resolver = EnvironmentResolver::new(Arc::new(directory_finder));
+        let environment = environment_resolver.resolve_variable(
+            base_environment(&[("PATH", original_path.clone())]),
+            &dependency,
+            &environment_variable,
+        ).unwrap();
+        let expected_path = PathBuf::from("~/path/location");
+        assert!(get(&environment, "PATH").starts_with(expected_path.to_str().unwrap()));
+        assert!(get(&environment, "PATH").ends_with(original_path.to_str().unwrap()));
+    }
+
+
+    #[test]
+    fn can_append_to_path() {
+        let original_path = join(&["/usr/bin", "/bin"]);
+        let dependency = Dependency::new("dependency", "555.213");
+        let environment_variable =
+            serde_json::from_str::<EnvironmentVariable>(r#"{"name": "PATH", "relative_path": "some/path/location"}"#)
+                .unwrap();
+        let mut directory_finder = MockSolipathDirectoryFinderTrait::new();
+        directory_finder
+            .expect_get_dependency_downloads_directory()
+            .with(eq(dependency.clone()))
+            .return_const(PathBuf::from("solipath/home/downloads"));
+        let environment_resolver = EnvironmentResolver::new(Arc::new(directory_finder));
+        let environment = environment_resolver.resolve_variable(
+            base_environment(&[("PATH", original_path.clone())]),
+            &dependency,
+            &environment_variable,
+        ).unwrap();
+        let mut expected_path = PathBuf::from("solipath/home/downloads");
+        expected_path.push("some/path/location");
+        assert!(get(&environment, "PATH").starts_with(expected_path.to_str().unwrap()));
+        assert!(get(&environment, "PATH").ends_with(original_path.to_str().unwrap()));
+    }
+}
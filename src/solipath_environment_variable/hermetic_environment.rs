@@ -0,0 +1,87 @@
+use std::ffi::OsString;
+
+const ALLOWED_VARIABLES: [&str; 4] = ["HOME", "USER", "TERM", "LANG"];
+const WINDOWS_ALLOWED_VARIABLES: [&str; 7] = ["SYSTEMROOT", "WINDIR", "COMSPEC", "PATHEXT", "TEMP", "TMP", "USERPROFILE"];
+
+fn get_base_path() -> &'static str {
+    if std::env::consts::OS == "windows" {
+        r"C:\Windows\system32;C:\Windows"
+    } else {
+        "/usr/bin:/bin"
+    }
+}
+
+fn is_allowed(name: &OsString, pass_env: &[String]) -> bool {
+    let name = name.to_string_lossy();
+    let matches = |allowed: &str| {
+        if std::env::consts::OS == "windows" {
+            allowed.eq_ignore_ascii_case(&name)
+        } else {
+            allowed == name
+        }
+    };
+    ALLOWED_VARIABLES.iter().any(|allowed| matches(allowed))
+        || (std::env::consts::OS == "windows" && WINDOWS_ALLOWED_VARIABLES.iter().any(|allowed| matches(allowed)))
+        || pass_env.iter().any(|allowed| matches(allowed))
+}
+
+// the allow-listed variables that survive from the invoking shell, PATH is replaced by a base system path
+// unless it is explicitly passed through
+pub fn get_hermetic_environment(
+    current_environment: Vec<(OsString, OsString)>,
+    pass_env: &[String],
+) -> Vec<(OsString, OsString)> {
+    let mut hermetic_environment: Vec<(OsString, OsString)> = current_environment
+        .into_iter()
+        .filter(|(name, _)| is_allowed(name, pass_env))
+        .collect();
+    if !hermetic_environment.iter().any(|(name, _)| name.eq_ignore_ascii_case("PATH")) {
+        hermetic_environment.push((OsString::from("PATH"), OsString::from(get_base_path())));
+    }
+    hermetic_environment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn environment(variables: &[(&str, &str)]) -> Vec<(OsString, OsString)> {
+        variables
+            .iter()
+            .map(|(name, value)| (OsString::from(name), OsString::from(value)))
+            .collect()
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn keeps_only_allowed_variables_and_base_path() {
+        let hermetic_environment = get_hermetic_environment(
+            environment(&[
+                ("HOME", "/home/me"),
+                ("PATH", "/home/me/bin:/usr/bin"),
+                ("AWS_SECRET_ACCESS_KEY", "secret"),
+                ("CI", "true"),
+                ("LANG", "en_US.UTF-8"),
+            ]),
+            &["CI".to_string()],
+        );
+
+        assert_eq!(
+            environment(&[
+                ("HOME", "/home/me"),
+                ("CI", "true"),
+                ("LANG", "en_US.UTF-8"),
+                ("PATH", "/usr/bin:/bin")
+            ]),
+            hermetic_environment
+        );
+    }
+
+    #[test]
+    fn path_can_be_passed_through() {
+        let hermetic_environment =
+            get_hermetic_environment(environment(&[("PATH", "/custom/bin")]), &["PATH".to_string()]);
+
+        assert_eq!(environment(&[("PATH", "/custom/bin")]), hermetic_environment);
+    }
+}
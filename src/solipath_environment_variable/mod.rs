@@ -1 +1,5 @@
-pub mod environment_setter;
+pub mod env_file;
+pub mod environment_resolver;
+pub mod hermetic_environment;
+pub mod resolved_environment;
+pub mod variable_interpolator;
@@ -0,0 +1,171 @@
+use std::env::{join_paths, split_paths, vars_os};
+use std::ffi::{OsStr, OsString};
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::solipath_environment_variable::hermetic_environment::get_hermetic_environment;
+use crate::solipath_instructions::data::environment_variable::EnvironmentVariableOperation;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvironmentOperation {
+    pub name: String,
+    pub operation: EnvironmentVariableOperation,
+    pub value: Option<OsString>,
+}
+
+// the complete environment a command is started with, solipath's own process environment is never changed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedEnvironment {
+    operations: Vec<EnvironmentOperation>,
+    variables: Vec<(OsString, OsString)>,
+}
+
+impl ResolvedEnvironment {
+    pub fn new(variables: Vec<(OsString, OsString)>) -> Self {
+        Self {
+            operations: Vec::new(),
+            variables,
+        }
+    }
+
+    pub fn from_current_process() -> Self {
+        Self::new(vars_os().collect())
+    }
+
+    pub fn hermetic(pass_env: &[String]) -> Self {
+        Self::new(get_hermetic_environment(vars_os().collect(), pass_env))
+    }
+
+    pub fn get_operations(&self) -> &Vec<EnvironmentOperation> {
+        &self.operations
+    }
+
+    pub fn get_variables(&self) -> &Vec<(OsString, OsString)> {
+        &self.variables
+    }
+
+    pub fn get(&self, name: &str) -> Option<&OsStr> {
+        self.variables
+            .iter()
+            .find(|(variable_name, _)| is_same_name(variable_name, name))
+            .map(|(_, value)| value.as_os_str())
+    }
+
+    pub fn get_path_list(&self, name: &str) -> Vec<PathBuf> {
+        self.get(name)
+            .map(|value| split_paths(value).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn apply(&mut self, name: &str, operation: EnvironmentVariableOperation, value: Option<OsString>) {
+        match operation {
+            EnvironmentVariableOperation::Set => self.insert(name, value.clone().unwrap_or_default()),
+            EnvironmentVariableOperation::SetIfAbsent => {
+                if self.get(name).is_none() {
+                    self.insert(name, value.clone().unwrap_or_default())
+                }
+            }
+            EnvironmentVariableOperation::PrependPath => {
+                let mut paths: Vec<PathBuf> = value.iter().map(PathBuf::from).collect();
+                paths.append(&mut self.get_path_list(name));
+                self.set_path_list(name, paths);
+            }
+            EnvironmentVariableOperation::AppendPath => {
+                let mut paths = self.get_path_list(name);
+                paths.extend(value.iter().map(PathBuf::from));
+                self.set_path_list(name, paths);
+            }
+            EnvironmentVariableOperation::Unset => self
+                .variables
+                .retain(|(variable_name, _)| !is_same_name(variable_name, name)),
+        }
+        self.operations.push(EnvironmentOperation {
+            name: name.to_string(),
+            operation,
+            value,
+        });
+    }
+
+    pub fn apply_to(&self, command: &mut Command) {
+        command.env_clear().envs(self.variables.iter().map(|(name, value)| (name, value)));
+    }
+
+    fn insert(&mut self, name: &str, value: OsString) {
+        match self
+            .variables
+            .iter_mut()
+            .find(|(variable_name, _)| is_same_name(variable_name, name))
+        {
+            Some((_, existing_value)) => *existing_value = value,
+            None => self.variables.push((OsString::from(name), value)),
+        }
+    }
+
+    // the first occurrence of a path wins, so a prepended path moves to the front and an appended path that is
+    // already present stays where it was
+    fn set_path_list(&mut self, name: &str, paths: Vec<PathBuf>) {
+        let mut unique_paths: Vec<PathBuf> = Vec::new();
+        paths
+            .into_iter()
+            .filter(|path| !path.as_os_str().is_empty())
+            .for_each(|path| {
+                if !unique_paths.contains(&path) {
+                    unique_paths.push(path);
+                }
+            });
+        self.insert(name, join_paths(unique_paths).expect("failed to combine paths"));
+    }
+}
+
+fn is_same_name(variable_name: &OsStr, name: &str) -> bool {
+    if std::env::consts::OS == "windows" {
+        variable_name.eq_ignore_ascii_case(name)
+    } else {
+        variable_name == name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn join(paths: &[&str]) -> OsString {
+        join_paths(paths).unwrap()
+    }
+
+    #[test]
+    fn records_operations_and_final_values() {
+        let mut environment = ResolvedEnvironment::new(vec![
+            (OsString::from("PATH"), join(&["/usr/bin"])),
+            (OsString::from("REMOVE_ME"), OsString::from("value")),
+        ]);
+
+        environment.apply("PATH", EnvironmentVariableOperation::PrependPath, Some(OsString::from("/java/bin")));
+        environment.apply("PATH", EnvironmentVariableOperation::AppendPath, Some(OsString::from("/usr/bin")));
+        environment.apply("JAVA_HOME", EnvironmentVariableOperation::Set, Some(OsString::from("/jdk")));
+        environment.apply("JAVA_HOME", EnvironmentVariableOperation::SetIfAbsent, Some(OsString::from("/other")));
+        environment.apply("REMOVE_ME", EnvironmentVariableOperation::Unset, None);
+
+        assert_eq!(
+            &vec![
+                (OsString::from("PATH"), join(&["/java/bin", "/usr/bin"])),
+                (OsString::from("JAVA_HOME"), OsString::from("/jdk")),
+            ],
+            environment.get_variables()
+        );
+        assert_eq!(5, environment.get_operations().len());
+        assert_eq!(EnvironmentVariableOperation::Unset, environment.get_operations()[4].operation);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn command_only_sees_resolved_variables() {
+        let environment = ResolvedEnvironment::new(vec![(OsString::from("SOLIPATH_TEST_ONLY"), OsString::from("yes"))]);
+        let mut command = Command::new("/usr/bin/env");
+        environment.apply_to(&mut command);
+
+        let output = command.output().expect("failed to run env");
+
+        assert_eq!("SOLIPATH_TEST_ONLY=yes\n", String::from_utf8_lossy(&output.stdout));
+    }
+}
@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+
+use crate::solipath_directory::solipath_directory_finder::SolipathDirectoryFinderTrait;
+use crate::solipath_environment_variable::resolved_environment::ResolvedEnvironment;
+use crate::solipath_instructions::data::dependency::Dependency;
+use crate::solipath_instructions::data::environment_variable::EnvironmentVariable;
+
+// replaces ${env:NAME} from the environment solipath started the command with, an unset NAME is an error unless a
+// default is given with ${env:NAME:-default}. Also ${SOLIPATH_HOME}, ${downloads}, ${dep:NAME:downloads} and ${dep:NAME:env:VARIABLE}.
+// ${dep:NAME:env:VARIABLE} is the value that dependency NAME gave VARIABLE, so it has to be resolved first.
+// project variables from solipath.json have no dependency, so ${downloads} isn't available to them.
+// Any other ${...} is left as it is written.
+pub struct VariableInterpolator<'a> {
+    directory_finder: Arc<dyn SolipathDirectoryFinderTrait + Sync + Send>,
+    dependencies: &'a [Dependency],
+    environment: &'a ResolvedEnvironment,
+    resolved_values: HashMap<(String, String), String>,
+}
+
+impl<'a> VariableInterpolator<'a> {
+    pub fn new(
+        directory_finder: Arc<dyn SolipathDirectoryFinderTrait + Sync + Send>,
+        dependencies: &'a [Dependency],
+        environment: &'a ResolvedEnvironment,
+    ) -> Self {
+        Self {
+            directory_finder,
+            dependencies,
+            environment,
+            resolved_values: HashMap::new(),
+        }
+    }
+
+    pub fn add_resolved_value(&mut self, dependency: &Dependency, name: &str, value: &str) {
+        self.resolved_values
+            .insert((dependency.name.clone(), name.to_string()), value.to_string());
+    }
+
+    pub fn interpolate(&self, dependency: Option<&Dependency>, text: &str) -> Result<String> {
+        let mut interpolated = String::new();
+        let mut remaining = text;
+        while let Some(start) = remaining.find("${") {
+            let Some(length) = remaining[start..].find('}') else {
+                break;
+            };
+            let end = start + length;
+            interpolated.push_str(&remaining[..start]);
+            match self.resolve_expression(dependency, &remaining[start + 2..end])? {
+                Some(value) => interpolated.push_str(&value),
+                None => interpolated.push_str(&remaining[start..=end]),
+            }
+            remaining = &remaining[end + 1..];
+        }
+        interpolated.push_str(remaining);
+        Ok(interpolated)
+    }
+
+    fn resolve_expression(&self, dependency: Option<&Dependency>, expression: &str) -> Result<Option<String>> {
+        if let Some(variable) = expression.strip_prefix("env:") {
+            return self.resolve_environment_variable(dependency, expression, variable).map(Some);
+        }
+        let parts: Vec<&str> = expression.splitn(4, ':').collect();
+        let value = match parts.as_slice() {
+            ["SOLIPATH_HOME"] => path_to_string(self.directory_finder.get_base_solipath_directory()),
+            ["downloads"] => {
+                let dependency = dependency
+                    .context("${downloads} can only be used by a dependency, use ${dep:NAME:downloads} in solipath.json")?;
+                path_to_string(self.directory_finder.get_dependency_downloads_directory(dependency))
+            }
+            ["dep", name, "downloads"] => path_to_string(
+                self.directory_finder
+                    .get_dependency_downloads_directory(self.find_dependency(dependency, expression, name)?),
+            ),
+            ["dep", name, "env", variable] => self
+                .resolved_values
+                .get(&(name.to_string(), variable.to_string()))
+                .cloned()
+                .with_context(|| {
+                    format!(
+                        "{} uses ${{{}}}, but dependency {} does not set {}",
+                        get_scope_name(dependency), expression, name, variable
+                    )
+                })?,
+            _ => return Ok(None),
+        };
+        Ok(Some(value))
+    }
+
+    fn resolve_environment_variable(
+        &self,
+        dependency: Option<&Dependency>,
+        expression: &str,
+        variable: &str,
+    ) -> Result<String> {
+        let (name, default) = match variable.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (variable, None),
+        };
+        match (self.environment.get(name), default) {
+            (Some(value), _) => Ok(value.to_string_lossy().to_string()),
+            (None, Some(default)) => Ok(default.to_string()),
+            (None, None) => bail!(
+                "{} uses ${{{}}}, but {} is not set, a default can be given with ${{env:{}:-default}}",
+                get_scope_name(dependency), expression, name, name
+            ),
+        }
+    }
+
+    fn find_dependency(&self, dependency: Option<&Dependency>, expression: &str, name: &str) -> Result<&Dependency> {
+        self.dependencies
+            .iter()
+            .find(|project_dependency| project_dependency.name == name)
+            .with_context(|| {
+                format!(
+                    "{} uses ${{{}}}, but {} is not a dependency of this project",
+                    get_scope_name(dependency), expression, name
+                )
+            })
+    }
+}
+
+fn get_scope_name(dependency: Option<&Dependency>) -> &str {
+    dependency.map(|dependency| dependency.name.as_str()).unwrap_or("solipath.json")
+}
+
+fn path_to_string(path: std::path::PathBuf) -> String {
+    path.to_str().expect("path should be valid utf-8").to_string()
+}
+
+// a dependency's variables are set after those of every dependency it references, otherwise the original order is kept
+pub fn order_by_references<'a>(
+    environment_variables: &[(&'a Dependency, &'a EnvironmentVariable)],
+) -> Result<Vec<(&'a Dependency, &'a EnvironmentVariable)>> {
+    let mut dependency_names: Vec<&str> = Vec::new();
+    environment_variables.iter().for_each(|(dependency, _)| {
+        if !dependency_names.contains(&dependency.name.as_str()) {
+            dependency_names.push(&dependency.name);
+        }
+    });
+    let mut ordered_names: Vec<&str> = Vec::new();
+    let mut names_in_progress: Vec<&str> = Vec::new();
+    for name in &dependency_names {
+        visit(name, environment_variables, &mut ordered_names, &mut names_in_progress)?;
+    }
+    Ok(ordered_names
+        .iter()
+        .flat_map(|name| {
+            environment_variables
+                .iter()
+                .filter(move |(dependency, _)| dependency.name == *name)
+                .copied()
+        })
+        .collect())
+}
+
+fn visit<'a>(
+    name: &'a str,
+    environment_variables: &[(&'a Dependency, &'a EnvironmentVariable)],
+    ordered_names: &mut Vec<&'a str>,
+    names_in_progress: &mut Vec<&'a str>,
+) -> Result<()> {
+    if ordered_names.contains(&name) {
+        return Ok(());
+    }
+    if names_in_progress.contains(&name) {
+        bail!("environment variables of {} reference each other: {}", name, names_in_progress.join(" -> "));
+    }
+    names_in_progress.push(name);
+    let referenced_names: Vec<&str> = environment_variables
+        .iter()
+        .filter(|(dependency, _)| dependency.name == name)
+        .flat_map(|(_, environment_variable)| get_referenced_dependencies(environment_variable))
+        .filter(|referenced_name| *referenced_name != name)
+        .collect();
+    for referenced_name in referenced_names {
+        if let Some((dependency, _)) = environment_variables
+            .iter()
+            .find(|(dependency, _)| dependency.name == referenced_name)
+        {
+            visit(&dependency.name, environment_variables, ordered_names, names_in_progress)?;
+        }
+    }
+    names_in_progress.pop();
+    ordered_names.push(name);
+    Ok(())
+}
+
+fn get_referenced_dependencies(environment_variable: &EnvironmentVariable) -> Vec<&str> {
+    [environment_variable.get_value(), environment_variable.get_relative_path()]
+        .into_iter()
+        .flatten()
+        .flat_map(|text| text.split("${dep:").skip(1))
+        .filter_map(|reference| reference.split(':').next())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::solipath_directory::solipath_directory_finder::MockSolipathDirectoryFinderTrait;
+
+    fn environment_variable(json: &str) -> EnvironmentVariable {
+        serde_json::from_str::<EnvironmentVariable>(json).unwrap()
+    }
+
+    fn directory_finder() -> Arc<MockSolipathDirectoryFinderTrait> {
+        let mut directory_finder = MockSolipathDirectoryFinderTrait::new();
+        directory_finder
+            .expect_get_base_solipath_directory()
+            .return_const(PathBuf::from("/home/me/solipath"));
+        directory_finder
+            .expect_get_dependency_downloads_directory()
+            .returning(|dependency| PathBuf::from(format!("/home/me/solipath/{}/downloads", dependency.name)));
+        Arc::new(directory_finder)
+    }
+
+    #[test]
+    fn can_interpolate_solipath_home_downloads_and_environment() {
+        let environment = ResolvedEnvironment::new(vec![("SOLIPATH_TEST_INTERPOLATE".into(), "from-env".into())]);
+        let dependencies = vec![Dependency::new("gradle", "8.10"), Dependency::new("java", "21")];
+        let interpolator = VariableInterpolator::new(directory_finder(), &dependencies, &environment);
+
+        assert_eq!(
+            "/home/me/solipath/gradle/downloads/bin:/home/me/solipath/java/downloads:from-env:/home/me/solipath",
+            interpolator.interpolate(
+                Some(&dependencies[0]),
+                "${downloads}/bin:${dep:java:downloads}:${env:SOLIPATH_TEST_INTERPOLATE}:${SOLIPATH_HOME}"
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn can_reference_value_set_by_other_dependency() {
+        let dependencies = vec![Dependency::new("gradle", "8.10"), Dependency::new("java", "21")];
+        let environment = ResolvedEnvironment::new(Vec::new());
+        let mut interpolator = VariableInterpolator::new(directory_finder(), &dependencies, &environment);
+        interpolator.add_resolved_value(&dependencies[1], "JAVA_HOME", "/jdk-21");
+
+        assert_eq!(
+            "/jdk-21",
+            interpolator.interpolate(Some(&dependencies[0]), "${dep:java:env:JAVA_HOME}").unwrap()
+        );
+    }
+
+    #[test]
+    fn unknown_variables_are_left_as_written() {
+        let dependencies = vec![Dependency::new("gradle", "8.10")];
+        let environment = ResolvedEnvironment::new(Vec::new());
+        let interpolator = VariableInterpolator::new(directory_finder(), &dependencies, &environment);
+
+        assert_eq!(
+            "${FOO}/bin:${unterminated",
+            interpolator.interpolate(Some(&dependencies[0]), "${FOO}/bin:${unterminated").unwrap()
+        );
+    }
+
+    #[test]
+    fn missing_references_are_errors_naming_the_dependency_and_variable() {
+        let dependencies = vec![Dependency::new("gradle", "8.10")];
+        let environment = ResolvedEnvironment::new(Vec::new());
+        let interpolator = VariableInterpolator::new(directory_finder(), &dependencies, &environment);
+
+        assert_eq!(
+            "gradle uses ${dep:java:downloads}, but java is not a dependency of this project",
+            interpolator.interpolate(Some(&dependencies[0]), "${dep:java:downloads}").unwrap_err().to_string()
+        );
+        assert_eq!(
+            "solipath.json uses ${dep:gradle:env:GRADLE_HOME}, but dependency gradle does not set GRADLE_HOME",
+            interpolator.interpolate(None, "${dep:gradle:env:GRADLE_HOME}").unwrap_err().to_string()
+        );
+        assert!(interpolator.interpolate(None, "${downloads}").is_err());
+    }
+
+    #[test]
+    fn unset_environment_variables_are_errors_unless_they_have_a_default() {
+        let dependencies = vec![Dependency::new("gradle", "8.10")];
+        let environment = ResolvedEnvironment::new(vec![("SET".into(), "value".into())]);
+        let interpolator = VariableInterpolator::new(directory_finder(), &dependencies, &environment);
+
+        assert_eq!(
+            "gradle uses ${env:NOT_SET}, but NOT_SET is not set, a default can be given with ${env:NOT_SET:-default}",
+            interpolator.interpolate(Some(&dependencies[0]), "${env:NOT_SET}").unwrap_err().to_string()
+        );
+        assert_eq!(
+            "http://localhost:8080/value",
+            interpolator.interpolate(None, "${env:NOT_SET:-http://localhost:8080}/${env:SET:-unused}").unwrap()
+        );
+        assert_eq!("", interpolator.interpolate(None, "${env:NOT_SET:-}").unwrap());
+    }
+
+    #[test]
+    fn referenced_dependencies_are_ordered_first() {
+        let gradle = Dependency::new("gradle", "8.10");
+        let java = Dependency::new("java", "21");
+        let gradle_java_home = environment_variable(r#"{"name": "GRADLE_JAVA_HOME", "value": "${dep:java:env:JAVA_HOME}"}"#);
+        let gradle_path = environment_variable(r#"{"name": "PATH", "relative_path": "bin"}"#);
+        let java_home = environment_variable(r#"{"name": "JAVA_HOME", "relative_path": "jdk"}"#);
+        let environment_variables = vec![(&gradle, &gradle_java_home), (&gradle, &gradle_path), (&java, &java_home)];
+
+        let ordered = order_by_references(&environment_variables).unwrap();
+
+        assert_eq!(vec![(&java, &java_home), (&gradle, &gradle_java_home), (&gradle, &gradle_path)], ordered);
+    }
+
+    #[test]
+    fn circular_references_are_an_error() {
+        let first = Dependency::new("first", "1");
+        let second = Dependency::new("second", "1");
+        let first_variable = environment_variable(r#"{"name": "FIRST", "value": "${dep:second:env:SECOND}"}"#);
+        let second_variable = environment_variable(r#"{"name": "SECOND", "value": "${dep:first:env:FIRST}"}"#);
+        let error = order_by_references(&[(&first, &first_variable), (&second, &second_variable)]).unwrap_err();
+        assert!(error.to_string().contains("reference each other"));
+    }
+}
@@ -0,0 +1,139 @@
+use std::env::split_paths;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+// every executable named command on the path, in the order they would be found
+pub fn find_command_in_path(command: &str, path: &OsStr) -> Vec<PathBuf> {
+    let mut matches: Vec<PathBuf> = Vec::new();
+    split_paths(path)
+        .filter(|directory| !directory.as_os_str().is_empty())
+        .flat_map(|directory| {
+            get_candidate_file_names(command)
+                .into_iter()
+                .map(move |file_name| directory.join(file_name))
+        })
+        .filter(|candidate| is_executable(candidate))
+        .for_each(|candidate| {
+            if !matches.contains(&candidate) {
+                matches.push(candidate);
+            }
+        });
+    matches
+}
+
+// a command is managed when it lives under the solipath directory, anything else on the path is a system binary
+pub fn get_which_report(command: &str, path: &OsStr, solipath_directory: &Path) -> String {
+    let matches = find_command_in_path(command, path);
+    let Some(resolved) = matches.first() else {
+        return format!("{} was not found on PATH", command);
+    };
+    let describe = |candidate: &PathBuf| {
+        let origin = if candidate.starts_with(solipath_directory) { "managed by solipath" } else { "system" };
+        format!("{} ({})", candidate.display(), origin)
+    };
+    let mut report = vec![format!("{} resolves to {}", command, describe(resolved))];
+    matches
+        .iter()
+        .skip(1)
+        .for_each(|candidate| report.push(format!("  also found {}", describe(candidate))));
+    let resolved_is_managed = resolved.starts_with(solipath_directory);
+    if let Some(shadowed) = matches
+        .iter()
+        .skip(1)
+        .find(|candidate| candidate.starts_with(solipath_directory) != resolved_is_managed)
+    {
+        report.push(format!("warning: {} shadows {}", describe(resolved), describe(shadowed)));
+    }
+    report.join("\n")
+}
+
+fn get_candidate_file_names(command: &str) -> Vec<String> {
+    if std::env::consts::OS != "windows" || Path::new(command).extension().is_some() {
+        return vec![command.to_string()];
+    }
+    std::env::var("PATHEXT")
+        .unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string())
+        .split(';')
+        .filter(|extension| !extension.is_empty())
+        .map(|extension| format!("{}{}", command, extension.to_lowercase()))
+        .collect()
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+#[cfg(target_os = "windows")]
+pub fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(all(test, not(target_os = "windows")))]
+mod tests {
+    use std::env::join_paths;
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::solipath_download::file_permissions::set_file_as_executable;
+
+    fn create_executable(directory: &Path, name: &str) -> PathBuf {
+        fs::create_dir_all(directory).unwrap();
+        let executable = directory.join(name);
+        fs::write(&executable, "#!/bin/sh").unwrap();
+        set_file_as_executable(&executable);
+        executable
+    }
+
+    #[test]
+    fn reports_command_that_is_not_found() {
+        let temp_dir = tempdir().unwrap();
+        let path = join_paths([temp_dir.path()]).unwrap();
+        assert_eq!(
+            "java was not found on PATH",
+            get_which_report("java", &path, &temp_dir.path().join("solipath"))
+        );
+    }
+
+    #[test]
+    fn warns_when_system_binary_shadows_managed_one() {
+        let temp_dir = tempdir().unwrap();
+        let solipath_directory = temp_dir.path().join("solipath");
+        let system_java = create_executable(&temp_dir.path().join("usr/bin"), "java");
+        let managed_java = create_executable(&solipath_directory.join("java/downloads/bin"), "java");
+        fs::write(temp_dir.path().join("usr/bin/not-executable"), "").unwrap();
+        let path = join_paths([temp_dir.path().join("usr/bin"), solipath_directory.join("java/downloads/bin")]).unwrap();
+
+        let report = get_which_report("java", &path, &solipath_directory);
+
+        assert_eq!(
+            format!(
+                "java resolves to {} (system)\n  also found {} (managed by solipath)\nwarning: {} (system) shadows {} (managed by solipath)",
+                system_java.display(),
+                managed_java.display(),
+                system_java.display(),
+                managed_java.display()
+            ),
+            report
+        );
+        assert!(find_command_in_path("not-executable", &path).is_empty());
+    }
+
+    #[test]
+    fn managed_binary_resolves_first() {
+        let temp_dir = tempdir().unwrap();
+        let solipath_directory = temp_dir.path().join("solipath");
+        let managed_java = create_executable(&solipath_directory.join("java/downloads/bin"), "java");
+        create_executable(&temp_dir.path().join("usr/bin"), "java");
+        let path = join_paths([solipath_directory.join("java/downloads/bin"), temp_dir.path().join("usr/bin")]).unwrap();
+
+        let report = get_which_report("java", &path, &solipath_directory);
+
+        assert!(report.starts_with(&format!("java resolves to {} (managed by solipath)", managed_java.display())));
+        assert!(report.ends_with("(system)") && report.contains("warning:"));
+    }
+}
@@ -1,19 +1,48 @@
-use std::{process::ExitStatus, sync::Arc};
+use anyhow::{bail, Context, Result};
+use std::{
+    env::{current_dir, var_os},
+    path::PathBuf,
+    process::ExitStatus,
+    sync::Arc,
+};
+use tokio::sync::Semaphore;
 
 use crate::solipath_instructions::data::dependency::Dependency;
+use crate::solipath_instructions::dependency_resolver::{is_same_dependency, order_dependencies, select_versions};
+use crate::solipath_instructions::data::script::order_scripts;
+use crate::solipath_instructions::data::solipath_file::SolipathFile;
+use crate::solipath_instructions::data::environment_variable::EnvironmentVariableOperation;
+use crate::solipath_instructions::solipath_file_finder::{find_solipath_file, load_solipath_file};
 use crate::{
     async_loop::run_async,
-    solipath_directory::solipath_directory_finder::{SolipathDirectoryFinder, SolipathDirectoryFinderTrait},
+    solipath_config::{
+        user_config::UserConfig,
+        user_config_retriever::{UserConfigRetriever, UserConfigRetrieverTrait},
+    },
+    solipath_directory::{
+        directory_layout_migration::migrate_directory_layout,
+        solipath_directory_finder::{SolipathDirectoryFinder, SolipathDirectoryFinderTrait},
+    },
+    solipath_execute::command_locator::get_which_report,
+    solipath_execute::conflict_detector::{
+        find_download_conflicts, find_executable_conflicts, find_variable_conflicts, report_conflicts,
+    },
     solipath_download::{
+        artifact_cache::{ArtifactCache, ArtifactCacheTrait},
         conditional_file_downloader::ConditionalFileDownloader,
         dependency_downloader::{DependencyDownloader, DependencyDownloaderTrait},
         file_decompressor::FileDecompressor,
         file_downloader::FileDownloader,
         file_to_string_downloader::FileToStringDownloader,
     },
-    solipath_environment_variable::environment_setter::{EnvironmentSetter, EnvironmentSetterTrait},
+    solipath_environment_variable::{
+        env_file::read_env_file,
+        environment_resolver::{EnvironmentResolver, EnvironmentResolverTrait},
+        resolved_environment::ResolvedEnvironment,
+    },
     solipath_instructions::{
         data::dependency_instructions::{DependencyInstructions, VecDependencyInstructions},
+        data::environment_variable::EnvironmentVariable,
         dependency_instructions_retriever::{DependencyInstructionsRetriever, DependencyInstructionsRetrieverTrait},
     },
     solipath_platform::{
@@ -21,7 +50,7 @@ use crate::{
         platform_filter::{filter_list, PlatformFilter, PlatformFilterTrait},
     },
     solipath_shell::{
-        command_executor::{CommandExecutor, CommandExecutorTrait},
+        command_executor::{change_directory_command, quote_argument, CommandExecutor, CommandExecutorTrait},
         install_command_executor::{InstallCommandExecutor, InstallCommandExecutorTrait},
         install_command_filter::InstallCommandFilter,
     },
@@ -36,32 +65,138 @@ pub struct CommandWithPathExecutor {
     dependency_instructions_retriever: Arc<dyn DependencyInstructionsRetrieverTrait>,
     template_retriever: Arc<dyn TemplateRetrieverTrait>,
     dependency_downloader: Arc<dyn DependencyDownloaderTrait>,
-    environment_setter: Arc<dyn EnvironmentSetterTrait>,
+    artifact_cache: Arc<dyn ArtifactCacheTrait>,
+    environment_resolver: Arc<dyn EnvironmentResolverTrait>,
     install_command_executor: Arc<dyn InstallCommandExecutorTrait>,
     command_executor: Arc<dyn CommandExecutorTrait>,
+    directory_finder: Arc<dyn SolipathDirectoryFinderTrait + Send + Sync>,
+    config_path: Option<PathBuf>,
+}
+
+const MAX_RESOLUTION_ROUNDS: usize = 20;
+
+fn validate_install_commands(dependency_instructions: &Vec<DependencyInstructions>) -> Result<()> {
+    dependency_instructions
+        .get_install_commands()
+        .iter()
+        .try_for_each(|(dependency, install_command)| {
+            install_command.get_when_to_run_rule().map(|_| ()).with_context(|| {
+                format!(
+                    "invalid when_to_run_rules for install command '{}' of {} {}",
+                    install_command.get_command(),
+                    dependency.name,
+                    dependency.version
+                )
+            })
+        })
+}
+
+// dependencies are installed after everything they need, their variables are still resolved in solipath.json order
+fn get_install_order(dependency_instructions: &Vec<DependencyInstructions>) -> Result<Vec<Dependency>> {
+    let dependencies: Vec<Dependency> = dependency_instructions
+        .iter()
+        .map(|instructions| instructions.get_dependency().clone())
+        .collect();
+    order_dependencies(&dependencies, &dependency_instructions.get_dependencies())
 }
 
 impl CommandWithPathExecutor {
     pub fn new() -> Self {
-        let base_solipath_url =
-            "https://raw.githubusercontent.com/Solipath/Solipath-Install-Instructions/main".to_string();
-        let directory_finder = Arc::new(SolipathDirectoryFinder::new());
+        Self::new_with_user_config(&UserConfigRetriever::new().get_user_config())
+    }
+
+    pub fn new_with_user_config(user_config: &UserConfig) -> Self {
         let platform_retriever = Arc::new(CurrentPlatformRetriever::new());
+        let directory_finder =
+            Arc::new(SolipathDirectoryFinder::new_with_user_config(user_config, platform_retriever.clone()));
         let command_executor = Arc::new(CommandExecutor::new());
-        Self::new_with_injected_values(
-            base_solipath_url,
+        Self::new_with_registries(
+            user_config.get_registries().clone(),
+            user_config,
             directory_finder,
             platform_retriever,
             command_executor,
         )
     }
 
-    pub async fn set_path_from_solipath_file_and_execute_command(&self, commands: &[String]) -> ExitStatus {
-        let file_contents =
-            std::fs::read_to_string("solipath.json").expect("could not find a solipath.json file in current directory");
-        let dependency_list: Vec<Dependency> =
-            serde_json::from_str(&file_contents).expect("failed to parse dependency file");
-        self.set_path_and_execute_command(dependency_list, commands).await
+    pub async fn set_path_from_solipath_file_and_execute_command(&self, commands: &[String]) -> Result<ExitStatus> {
+        self.set_path_for_solipath_file_and_execute_command(self.read_solipath_file()?, commands).await
+    }
+
+    // --config wins over SOLIPATH_CONFIG, which wins over searching the current and parent directories
+    pub fn set_config_path(&mut self, config_path: PathBuf) {
+        self.config_path = Some(config_path);
+    }
+
+    fn read_solipath_file(&self) -> Result<SolipathFile> {
+        let path = match self.config_path.clone().or_else(|| var_os("SOLIPATH_CONFIG").map(PathBuf::from)) {
+            Some(path) => path,
+            None => find_solipath_file(&current_dir().context("failed to get current directory")?)
+                .context("could not find a solipath.json file in the current directory or any parent directory")?,
+        };
+        load_solipath_file(&path)
+    }
+
+    pub async fn set_path_from_solipath_file_and_execute_hermetic_command(&self, commands: &[String]) -> Result<ExitStatus> {
+        let mut solipath_file = self.read_solipath_file()?;
+        solipath_file.set_hermetic(true);
+        self.set_path_for_solipath_file_and_execute_command(solipath_file, commands).await
+    }
+
+    pub async fn run_script_from_solipath_file(&self, name: &str, extra_arguments: &[String]) -> Result<ExitStatus> {
+        self.run_script(self.read_solipath_file()?, name, extra_arguments).await
+    }
+
+    // scripts that name depends on are run first, extra arguments are passed to the last command of name itself,
+    // and the first command that fails stops the rest from running
+    pub async fn run_script(
+        &self,
+        solipath_file: SolipathFile,
+        name: &str,
+        extra_arguments: &[String],
+    ) -> Result<ExitStatus> {
+        let script_names = order_scripts(solipath_file.get_scripts(), name)?;
+        let environment = self.set_path_for_solipath_file(&solipath_file).await?;
+        for script_name in script_names {
+            let commands = solipath_file.get_scripts()[script_name].get_commands();
+            for (index, command) in commands.iter().enumerate() {
+                let mut command = command.clone();
+                if script_name == name && index == commands.len() - 1 {
+                    extra_arguments
+                        .iter()
+                        .for_each(|argument| command = format!("{} {}", command, quote_argument(argument)));
+                }
+                // scripts run from the project root, wherever solipath was started
+                if let Some(project_root) = solipath_file.get_project_root() {
+                    command = format!("{} && {}", change_directory_command(project_root), command);
+                }
+                let exit_status = self.command_executor.execute_single_string_command(command, &environment);
+                if !exit_status.success() {
+                    return Ok(exit_status);
+                }
+            }
+        }
+        Ok(ExitStatus::default())
+    }
+
+    // the dependencies that apply to the current directory, after merging any files that are extended
+    pub fn get_dependency_list_from_solipath_file(&self) -> Result<String> {
+        Ok(self
+            .read_solipath_file()?
+            .get_dependencies()
+            .iter()
+            .map(|dependency| format!("{} {}", dependency.name, dependency.version))
+            .collect::<Vec<String>>()
+            .join("\n"))
+    }
+
+    pub async fn get_which_report_from_solipath_file(&self, command: &str) -> Result<String> {
+        let environment = self.set_path_for_solipath_file(&self.read_solipath_file()?).await?;
+        Ok(get_which_report(
+            command,
+            environment.get("PATH").unwrap_or_default(),
+            &self.directory_finder.get_base_solipath_directory(),
+        ))
     }
 
     pub fn new_with_injected_values(
@@ -70,20 +205,52 @@ impl CommandWithPathExecutor {
         platform_retriever: Arc<dyn CurrentPlatformRetrieverTrait + Send + Sync>,
         command_executor: Arc<dyn CommandExecutorTrait + Send + Sync>,
     ) -> Self {
-        let file_downloader = Arc::new(FileDownloader::new());
+        Self::new_with_registries(
+            vec![base_solipath_url],
+            &UserConfig::default(),
+            directory_finder,
+            platform_retriever,
+            command_executor,
+        )
+    }
+
+    pub fn new_with_registries(
+        registries: Vec<String>,
+        user_config: &UserConfig,
+        directory_finder: Arc<dyn SolipathDirectoryFinderTrait + Send + Sync>,
+        platform_retriever: Arc<dyn CurrentPlatformRetrieverTrait + Send + Sync>,
+        command_executor: Arc<dyn CommandExecutorTrait + Send + Sync>,
+    ) -> Self {
+        let file_downloader = Arc::new(FileDownloader::new_with_user_config(user_config));
         let file_decompressor = Arc::new(FileDecompressor::new());
-        let conditional_file_downloader = Arc::new(ConditionalFileDownloader::new(file_downloader, file_decompressor));
+        // the one limit on parallel downloads, extracting an archive doesn't hold a permit
+        let download_permits = Arc::new(Semaphore::new(
+            user_config.get_download_settings().max_parallel_downloads.max(1),
+        ));
+        let artifact_cache = Arc::new(ArtifactCache::new_with_download_permits(
+            file_downloader.clone(),
+            directory_finder.get_artifact_cache_directory(),
+            user_config.get_cache_settings().retention_days,
+            download_permits.clone(),
+        ));
+        let conditional_file_downloader = Arc::new(ConditionalFileDownloader::new_with_download_permits(
+            file_downloader,
+            file_decompressor,
+            artifact_cache.clone(),
+            download_permits,
+        ));
         let file_to_string_downloader = Arc::new(FileToStringDownloader::new(conditional_file_downloader.clone()));
-        let dependency_instructions_retriever = Arc::new(DependencyInstructionsRetriever::new_with_alternate_url(
-            base_solipath_url.clone(),
+        let dependency_instructions_retriever = Arc::new(DependencyInstructionsRetriever::new_with_registries(
+            registries.clone(),
             file_to_string_downloader.clone(),
             directory_finder.clone(),
         ));
-        let template_retriever = Arc::new(TemplateRetriever::new_with_alternate_url(
-            base_solipath_url,
+        let template_retriever = Arc::new(TemplateRetriever::new_with_registries(
+            registries,
             file_to_string_downloader,
             directory_finder.clone(),
             Arc::new(TemplateVariableReplacer::new()),
+            platform_retriever.clone(),
         ));
         let platform_filter = Arc::new(PlatformFilter::new(platform_retriever));
         let dependency_downloader = Arc::new(DependencyDownloader::new(
@@ -91,13 +258,13 @@ impl CommandWithPathExecutor {
             conditional_file_downloader,
         ));
 
-        let environment_setter = Arc::new(EnvironmentSetter::new(directory_finder.clone()));
+        let environment_resolver = Arc::new(EnvironmentResolver::new(directory_finder.clone()));
 
         let install_command_filter = Arc::new(InstallCommandFilter::new(directory_finder.clone()));
         let install_command_executor = Arc::new(InstallCommandExecutor::new(
             command_executor.clone(),
             install_command_filter,
-            directory_finder,
+            directory_finder.clone(),
         ));
 
         CommandWithPathExecutor {
@@ -105,13 +272,16 @@ impl CommandWithPathExecutor {
             dependency_instructions_retriever,
             template_retriever,
             dependency_downloader,
-            environment_setter,
+            artifact_cache,
+            environment_resolver,
             install_command_executor,
             command_executor,
+            directory_finder,
+            config_path: None,
         }
     }
 
-    async fn get_dependency_instructions(&self, dependency_list: &Vec<Dependency>) -> Vec<DependencyInstructions> {
+    async fn get_dependency_instructions(&self, dependency_list: &Vec<Dependency>) -> Result<Vec<DependencyInstructions>> {
         let dependency_list = filter_list(&self.platform_filter, &dependency_list);
         let mut dependency_instructions = run_async(&dependency_list, |dependency| {
             self.dependency_instructions_retriever
@@ -125,48 +295,162 @@ impl CommandWithPathExecutor {
                     .retrieve_instructions_from_template(dependency, template)
             })
             .await
+            .into_iter()
+            .collect::<Result<Vec<Vec<DependencyInstructions>>>>()?
+            .concat()
             .filter_platform(&self.platform_filter);
         dependency_instructions.append(&mut template_instructions);
-        dependency_instructions
+        Ok(dependency_instructions)
+    }
+
+    // install_instructions can need other dependencies, the versions are picked again every time newly fetched
+    // instructions add requirements, until nothing changes
+    async fn resolve_dependency_instructions(&self, project_dependencies: &Vec<Dependency>) -> Result<Vec<DependencyInstructions>> {
+        let project_dependencies = filter_list(&self.platform_filter, project_dependencies);
+        let mut fetched_dependencies: Vec<Dependency> = Vec::new();
+        let mut fetched_instructions: Vec<DependencyInstructions> = Vec::new();
+        let mut selected_dependencies = project_dependencies.clone();
+        for _ in 0..MAX_RESOLUTION_ROUNDS {
+            let missing_dependencies: Vec<Dependency> = selected_dependencies
+                .iter()
+                .filter(|dependency| !fetched_dependencies.iter().any(|fetched| is_same_dependency(fetched, dependency)))
+                .cloned()
+                .collect();
+            fetched_instructions.append(&mut self.get_dependency_instructions(&missing_dependencies).await?);
+            fetched_dependencies.extend(missing_dependencies);
+
+            let selected_instructions: Vec<DependencyInstructions> = fetched_instructions
+                .iter()
+                .filter(|instructions| selected_dependencies.iter().any(|selected| is_same_dependency(selected, instructions.get_dependency())))
+                .cloned()
+                .collect();
+            let requirements = selected_instructions.get_dependencies();
+            let selection = select_versions(&project_dependencies, &requirements)?;
+            let unchanged = selection.dependencies.len() == selected_dependencies.len()
+                && selection.dependencies.iter().zip(&selected_dependencies).all(|(left, right)| is_same_dependency(left, right));
+            if unchanged {
+                selection.warnings.iter().for_each(|warning| eprintln!("{}", warning));
+                return Ok(selected_dependencies
+                    .iter()
+                    .flat_map(|dependency| {
+                        selected_instructions
+                            .iter()
+                            .filter(move |instructions| is_same_dependency(instructions.get_dependency(), dependency))
+                            .cloned()
+                    })
+                    .collect());
+            }
+            selected_dependencies = selection.dependencies;
+        }
+        bail!("could not settle on dependency versions after {} rounds", MAX_RESOLUTION_ROUNDS)
     }
 
     pub async fn set_path_and_execute_command(
         &self,
         dependency_list: Vec<Dependency>,
         commands: &[String],
-    ) -> ExitStatus {
-        let dependency_instructions = self.get_dependency_instructions(&dependency_list).await;
-
-        run_async(
-            &dependency_instructions.get_downloads(),
-            |(dependency, download_instruction)| {
-                self.dependency_downloader
-                    .download_dependency(dependency, download_instruction)
-            },
-        )
-        .await;
+    ) -> Result<ExitStatus> {
+        self.set_path_for_solipath_file_and_execute_command(SolipathFile::new(dependency_list), commands)
+            .await
+    }
+
+    // env files are relative to the project root, or the current directory when there is no solipath file
+    fn get_project_variables(
+        &self,
+        solipath_file: &SolipathFile,
+        base_environment: &ResolvedEnvironment,
+    ) -> Result<Vec<EnvironmentVariable>> {
+        let mut project_variables = filter_list(&self.platform_filter, solipath_file.get_environment_variables());
+        let project_root = solipath_file.get_project_root().cloned().unwrap_or_default();
+        for env_file in solipath_file.get_env_files() {
+            project_variables.append(&mut read_env_file(&project_root.join(env_file), base_environment)?);
+        }
+        Ok(project_variables)
+    }
+
+    fn get_base_environment(&self, solipath_file: &SolipathFile) -> ResolvedEnvironment {
+        let mut base_environment = if solipath_file.is_hermetic() {
+            ResolvedEnvironment::hermetic(solipath_file.get_pass_env())
+        } else {
+            ResolvedEnvironment::from_current_process()
+        };
+        if let Some(project_root) = solipath_file.get_project_root() {
+            base_environment.apply(
+                "SOLIPATH_PROJECT_ROOT",
+                EnvironmentVariableOperation::Set,
+                Some(project_root.clone().into_os_string()),
+            );
+        }
+        base_environment
+    }
+
+    pub async fn set_path_for_solipath_file_and_execute_command(
+        &self,
+        solipath_file: SolipathFile,
+        commands: &[String],
+    ) -> Result<ExitStatus> {
+        let environment = self.set_path_for_solipath_file(&solipath_file).await?;
+        Ok(self.command_executor.execute_command(commands, &environment))
+    }
 
-        dependency_instructions
-            .get_environment_variables()
+    // install commands and conflicts between dependencies are checked before anything is downloaded, so a bad
+    // instruction file fails early
+    pub async fn set_path_for_solipath_file(&self, solipath_file: &SolipathFile) -> Result<ResolvedEnvironment> {
+        migrate_directory_layout(&self.directory_finder.get_base_solipath_directory())
+            .unwrap_or_else(|error| println!("failed to move to the new solipath directory layout: {:#}", error));
+        let dependency_instructions = self.resolve_dependency_instructions(solipath_file.get_dependencies()).await?;
+        validate_install_commands(&dependency_instructions)?;
+        let install_order = get_install_order(&dependency_instructions)?;
+
+        let dependencies: Vec<Dependency> = dependency_instructions
             .iter()
-            .for_each(|(dependency, environment_variable)| {
-                self.environment_setter.set_variable(dependency, environment_variable)
-            });
-        dependency_instructions
-            .get_install_commands()
+            .map(|instructions| instructions.get_dependency().clone())
+            .collect();
+        let base_environment = self.get_base_environment(solipath_file);
+        let dependency_values = self.environment_resolver.get_dependency_values(
+            &base_environment,
+            &dependencies,
+            &dependency_instructions.get_environment_variables(),
+        )?;
+        let mut conflicts = find_download_conflicts(&dependency_instructions.get_downloads(), self.directory_finder.as_ref());
+        conflicts.append(&mut find_variable_conflicts(&dependency_values));
+        report_conflicts(conflicts, solipath_file.is_strict())?;
+
+        run_async(&dependency_instructions.get_downloads(), |(dependency, download_instruction)| {
+            self.dependency_downloader
+                .download_dependency(dependency, download_instruction)
+        })
+        .await;
+        self.artifact_cache.remove_expired_artifacts();
+
+        let project_variables = self.get_project_variables(solipath_file, &base_environment)?;
+        let environment = self.environment_resolver.resolve_variables(
+            base_environment,
+            &dependencies,
+            &dependency_instructions.get_environment_variables(),
+            &project_variables,
+        )?;
+        let mut install_commands = dependency_instructions.get_install_commands();
+        install_commands.sort_by_key(|(dependency, _)| {
+            install_order
+                .iter()
+                .position(|ordered_dependency| is_same_dependency(ordered_dependency, dependency))
+        });
+        install_commands
             .iter()
-            .for_each(|(dependency, install_command)| {
+            .try_for_each(|(dependency, install_command)| {
                 self.install_command_executor
-                    .execute_command(dependency, install_command);
-            });
-        self.command_executor.execute_command(commands)
+                    .execute_command(dependency, install_command, &environment)
+            })?;
+        // executables can come from install commands as well as downloads
+        report_conflicts(find_executable_conflicts(&dependency_values), solipath_file.is_strict())?;
+        Ok(environment)
     }
 }
 
 #[cfg(test)]
 mod test {
     use std::{
-        env::{self, VarError},
         fs::{read_dir, read_to_string},
         path::PathBuf,
         process::ExitStatus,
@@ -226,24 +510,32 @@ mod test {
         let file_server = start_file_server(&solipath_source, &downloads_directory);
         let exit_status = command_with_path_executor
             .set_path_and_execute_command(dependencies, &["command to run".to_string()])
-            .await;
+            .await
+            .unwrap();
         file_server.abort();
 
-        let expected_download_folder = output_path.clone_push("PerfectMatchDependency/downloads/result");
+        let downloads_directory = format!(
+            "PerfectMatchDependency/1.0.1/{}-{}",
+            std::env::consts::OS,
+            std::env::consts::ARCH
+        );
+        let expected_download_folder = output_path.clone_push(&format!("{}/result", downloads_directory));
         assert_eq!(1, read_dir(expected_download_folder).unwrap().count());
-        let expected_download = output_path.clone_push("PerfectMatchDependency/downloads/result/tar_bz2_file.txt");
+        let expected_download = output_path.clone_push(&format!("{}/result/tar_bz2_file.txt", downloads_directory));
         assert_eq!("tar bz2 file".to_string(), read_to_string(expected_download).unwrap());
-        let expected_path_value = output_path.clone_push("PerfectMatchDependency/downloads/perfect_match_path");
-        assert_environment_contains("PATH", &expected_path_value);
+        let expected_path_value = output_path.clone_push(&format!("{}/perfect_match_path", downloads_directory));
+        let environment = mock_command_executor.get_last_environment();
+        assert_environment_contains(&environment, "PATH", &expected_path_value);
 
         let expected_perfect_match_path_value =
-            output_path.clone_push("PerfectMatchDependency/downloads/perfect_match");
-        assert_environment_contains("PERFECT_MATCH", &expected_perfect_match_path_value);
-        assert_eq!(Err(VarError::NotPresent), env::var("SHOULD_NOT_BE_SET"));
+            output_path.clone_push(&format!("{}/perfect_match", downloads_directory));
+        assert_environment_contains(&environment, "PERFECT_MATCH", &expected_perfect_match_path_value);
+        assert_eq!(None, environment.get("SHOULD_NOT_BE_SET"));
+        assert_eq!(None, std::env::var_os("PERFECT_MATCH"));
         assert_eq!(
             vec![
                 prefix_change_directory_command(
-                    &output_path.clone_push("PerfectMatchDependency/downloads"),
+                    &output_path.clone_push(&downloads_directory),
                     "echo 'perfect path set!!!'"
                 ),
                 "command to run".to_string()
@@ -253,18 +545,216 @@ mod test {
         assert_eq!(ExitStatus::default(), exit_status);
     }
 
+    fn solipath_file_with_scripts(scripts: &str) -> SolipathFile {
+        serde_json::from_str(&format!(r#"{{"dependencies": [], "scripts": {}}}"#, scripts)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn run_script_runs_dependencies_first_and_passes_extra_arguments() {
+        let mock_command_executor = Arc::new(MockCommandExecutor::new());
+        let output_tempdir = tempdir().unwrap();
+        let command_with_path_executor = CommandWithPathExecutor::new_test(
+            &output_tempdir.path().to_path_buf(),
+            "http://127.0.0.1:1/solipath".to_string(),
+            mock_command_executor.clone(),
+        );
+        let solipath_file = solipath_file_with_scripts(
+            r#"{"compile": "gradle compileJava", "test": {"commands": ["gradle test", "gradle check"], "depends_on": ["compile"]}}"#,
+        );
+
+        let exit_status = command_with_path_executor
+            .run_script(solipath_file, "test", &["--info".to_string()])
+            .await
+            .unwrap();
+
+        let expected_last_command = if std::env::consts::OS == "windows" {
+            "gradle check --info"
+        } else {
+            "gradle check '--info'"
+        };
+        assert_eq!(
+            vec!["gradle compileJava", "gradle test", expected_last_command],
+            mock_command_executor.get_commands()
+        );
+        assert!(exit_status.success());
+    }
+
+    #[tokio::test]
+    async fn project_root_is_passed_to_commands() {
+        let mock_command_executor = Arc::new(MockCommandExecutor::new());
+        let output_tempdir = tempdir().unwrap();
+        let command_with_path_executor = CommandWithPathExecutor::new_test(
+            &output_tempdir.path().to_path_buf(),
+            "http://127.0.0.1:1/solipath".to_string(),
+            mock_command_executor.clone(),
+        );
+        let mut solipath_file = solipath_file_with_scripts(r#"{"build": "gradle build"}"#);
+        solipath_file.set_project_root(PathBuf::from("/projects/monorepo"));
+
+        command_with_path_executor.run_script(solipath_file, "build", &[]).await.unwrap();
+
+        assert_eq!(
+            Some(PathBuf::from("/projects/monorepo").as_os_str()),
+            mock_command_executor.get_last_environment().get("SOLIPATH_PROJECT_ROOT")
+        );
+        assert_eq!(
+            vec![prefix_change_directory_command(&PathBuf::from("/projects/monorepo"), "gradle build")],
+            mock_command_executor.get_commands()
+        );
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[tokio::test]
+    async fn run_script_stops_at_first_failing_command() {
+        use std::os::unix::process::ExitStatusExt;
+
+        use crate::solipath_shell::command_executor::MockCommandExecutorTrait;
+
+        let mut command_executor = MockCommandExecutorTrait::new();
+        command_executor
+            .expect_execute_single_string_command()
+            .withf(|command, _| command == "npm ci")
+            .once()
+            .return_const(ExitStatus::from_raw(256));
+        let output_tempdir = tempdir().unwrap();
+        let command_with_path_executor = CommandWithPathExecutor::new_test(
+            &output_tempdir.path().to_path_buf(),
+            "http://127.0.0.1:1/solipath".to_string(),
+            Arc::new(command_executor),
+        );
+
+        let exit_status = command_with_path_executor
+            .run_script(solipath_file_with_scripts(r#"{"test": ["npm ci", "npm test"]}"#), "test", &[])
+            .await
+            .unwrap();
+
+        assert_eq!(Some(1), exit_status.code());
+    }
+
+    #[tokio::test]
+    async fn dependencies_needed_by_install_instructions_are_installed_first() {
+        let mock_command_executor = Arc::new(MockCommandExecutor::new());
+        let output_path = tempdir().unwrap().into_path();
+        let instructions = [
+            ("gradle/8.5", r#"{"dependencies": [{"name": "java", "version": "^17"}], "install_commands": [{"command": "echo gradle"}]}"#),
+            ("kotlin/2.0", r#"{"dependencies": [{"name": "java", "version": "^17.0.2"}], "install_commands": [{"command": "echo kotlin"}]}"#),
+            ("java/17.0.2", r#"{"install_commands": [{"command": "echo java"}]}"#),
+        ];
+        for (directory, install_instructions) in instructions {
+            let dependency_directory = output_path.join(directory);
+            std::fs::create_dir_all(&dependency_directory).unwrap();
+            std::fs::write(dependency_directory.join("install_instructions.json"), install_instructions).unwrap();
+        }
+        let command_with_path_executor = CommandWithPathExecutor::new_test(
+            &output_path,
+            "http://127.0.0.1:1/solipath".to_string(),
+            mock_command_executor.clone(),
+        );
+
+        command_with_path_executor
+            .set_path_and_execute_command(
+                vec![Dependency::new("gradle", "8.5"), Dependency::new("kotlin", "2.0")],
+                &["command to run".to_string()],
+            )
+            .await
+            .unwrap();
+
+        let platform_directory = format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH);
+        let install_command = |directory: &str, command: &str| {
+            prefix_change_directory_command(&output_path.join(directory).join(&platform_directory), command)
+        };
+        assert_eq!(
+            vec![
+                install_command("java/17.0.2", "echo java"),
+                install_command("gradle/8.5", "echo gradle"),
+                install_command("kotlin/2.0", "echo kotlin"),
+                "command to run".to_string()
+            ],
+            mock_command_executor.get_commands()
+        );
+    }
+
+    #[tokio::test]
+    async fn variables_follow_solipath_file_order_when_installs_are_reordered() {
+        let mock_command_executor = Arc::new(MockCommandExecutor::new());
+        let output_path = tempdir().unwrap().into_path();
+        let instructions = [
+            ("gradle/8.5", r#"{"dependencies": [{"name": "java", "version": "^17"}], "environment_variables": [{"name": "PATH", "relative_path": "bin"}]}"#),
+            ("java/17", r#"{"environment_variables": [{"name": "PATH", "relative_path": "bin"}]}"#),
+        ];
+        for (directory, install_instructions) in instructions {
+            let dependency_directory = output_path.join(directory);
+            std::fs::create_dir_all(&dependency_directory).unwrap();
+            std::fs::write(dependency_directory.join("install_instructions.json"), install_instructions).unwrap();
+        }
+        let command_with_path_executor = CommandWithPathExecutor::new_test(
+            &output_path,
+            "http://127.0.0.1:1/solipath".to_string(),
+            mock_command_executor.clone(),
+        );
+
+        command_with_path_executor
+            .set_path_and_execute_command(
+                vec![Dependency::new("gradle", "8.5"), Dependency::new("java", "17")],
+                &["command to run".to_string()],
+            )
+            .await
+            .unwrap();
+
+        let platform_directory = format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH);
+        let path = mock_command_executor.get_last_environment().get_path_list("PATH");
+        assert_eq!(
+            vec![
+                output_path.join("gradle/8.5").join(&platform_directory).join("bin"),
+                output_path.join("java/17").join(&platform_directory).join("bin"),
+            ],
+            path[..2].to_vec()
+        );
+    }
+
+    #[tokio::test]
+    async fn conflicting_variables_are_an_error_in_strict_mode() {
+        let output_path = tempdir().unwrap().into_path();
+        let instructions = [
+            ("java/17", r#"{"environment_variables": [{"name": "JAVA_HOME", "relative_path": "jdk"}]}"#),
+            ("graalvm/21", r#"{"environment_variables": [{"name": "JAVA_HOME", "relative_path": "graalvm"}]}"#),
+        ];
+        for (directory, install_instructions) in instructions {
+            let dependency_directory = output_path.join(directory);
+            std::fs::create_dir_all(&dependency_directory).unwrap();
+            std::fs::write(dependency_directory.join("install_instructions.json"), install_instructions).unwrap();
+        }
+        let command_with_path_executor = CommandWithPathExecutor::new_test(
+            &output_path,
+            "http://127.0.0.1:1/solipath".to_string(),
+            Arc::new(MockCommandExecutor::new()),
+        );
+        let solipath_file: SolipathFile = serde_json::from_str(
+            r#"{"dependencies": [{"name": "java", "version": "17"}, {"name": "graalvm", "version": "21"}], "strict": true}"#,
+        )
+        .unwrap();
+
+        let error = command_with_path_executor
+            .set_path_for_solipath_file(&solipath_file)
+            .await
+            .unwrap_err();
+
+        assert!(error.to_string().starts_with(
+            "dependencies conflict and the solipath file is strict:\nJAVA_HOME is set by java 17"
+        ));
+    }
+
     fn prefix_change_directory_command(directory: &PathBuf, command: &str) -> String {
         let change_directory_command = if std::env::consts::OS == "windows" {
-            let expected_path_string = directory.to_str().unwrap().replace("/", "\\");
-            format!("cd /d {}", expected_path_string)
+            format!("cd /d {}", crate::solipath_shell::command_executor::quote_windows_argument(&directory.to_str().unwrap().replace("/", "\\")))
         } else {
-            format!("cd \"{}\"", directory.display())
+            format!("cd '{}'", directory.display())
         };
         format!("{} && {}", change_directory_command, command)
     }
 
-    fn assert_environment_contains(environment_name: &str, path_value: &PathBuf) {
-        let environment_variable = env::var(environment_name).unwrap();
+    fn assert_environment_contains(environment: &ResolvedEnvironment, environment_name: &str, path_value: &PathBuf) {
+        let environment_variable = environment.get(environment_name).unwrap().to_str().unwrap();
         let mut expected_path_string = path_value.to_str().unwrap().to_string();
         if std::env::consts::OS == "windows" {
             expected_path_string = expected_path_string.replace("/", "\\");
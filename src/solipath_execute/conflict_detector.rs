@@ -0,0 +1,240 @@
+use std::fs::read_dir;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+
+use crate::solipath_directory::solipath_directory_finder::SolipathDirectoryFinderTrait;
+use crate::solipath_environment_variable::environment_resolver::DependencyValue;
+use crate::solipath_execute::command_locator::is_executable;
+use crate::solipath_instructions::data::dependency::Dependency;
+use crate::solipath_instructions::data::download_instruction::DownloadInstruction;
+use crate::solipath_instructions::data::environment_variable::EnvironmentVariableOperation;
+
+// conflicts are warnings, unless the solipath file is in strict mode
+pub fn report_conflicts(conflicts: Vec<String>, strict: bool) -> Result<()> {
+    if conflicts.is_empty() {
+        return Ok(());
+    }
+    if strict {
+        bail!("dependencies conflict and the solipath file is strict:\n{}", conflicts.join("\n"));
+    }
+    conflicts.iter().for_each(|conflict| eprintln!("warning: {}", conflict));
+    Ok(())
+}
+
+// path lists are meant to be shared, any other variable set by two dependencies only keeps one of the values
+pub fn find_variable_conflicts(values: &[DependencyValue]) -> Vec<String> {
+    let set_values: Vec<&DependencyValue> = values
+        .iter()
+        .filter(|value| {
+            value.operation == EnvironmentVariableOperation::Set
+                || value.operation == EnvironmentVariableOperation::SetIfAbsent
+        })
+        .collect();
+    group_by_name(set_values.iter().map(|value| (value.name.as_str(), *value)))
+        .into_iter()
+        .filter(|(_, values)| {
+            values.iter().any(|value| {
+                value.dependency.name != values[0].dependency.name && value.value != values[0].value
+            })
+        })
+        .map(|(name, values)| {
+            let set_by: Vec<String> = values
+                .iter()
+                .map(|value| format!("{} ({})", describe(&value.dependency), value.value.display()))
+                .collect();
+            format!("{} is set by {}", name, set_by.join(" and "))
+        })
+        .collect()
+}
+
+// only the first match on the PATH runs, so the same executable from two dependencies hides one of them
+pub fn find_executable_conflicts(values: &[DependencyValue]) -> Vec<String> {
+    let mut executables: Vec<(String, &DependencyValue, PathBuf)> = Vec::new();
+    values
+        .iter()
+        .filter(|value| is_path_variable(&value.name))
+        .filter(|value| {
+            value.operation == EnvironmentVariableOperation::PrependPath
+                || value.operation == EnvironmentVariableOperation::AppendPath
+        })
+        .for_each(|value| {
+            get_executables(&value.value)
+                .into_iter()
+                .for_each(|(name, path)| executables.push((name, value, path)))
+        });
+    group_by_name(executables.iter().map(|(name, value, path)| (name.as_str(), (*value, path))))
+        .into_iter()
+        .filter(|(_, found)| found.iter().any(|(value, _)| value.dependency.name != found[0].0.dependency.name))
+        .map(|(name, found)| {
+            let locations: Vec<String> = found
+                .iter()
+                .map(|(value, path)| format!("{} ({})", describe(&value.dependency), path.display()))
+                .collect();
+            format!("{} is on the PATH from {}", name, locations.join(" and "))
+        })
+        .collect()
+}
+
+// a destination directory is only downloaded to when it doesn't exist yet, so the second download never happens
+pub fn find_download_conflicts(
+    downloads: &[(&Dependency, &DownloadInstruction)],
+    directory_finder: &dyn SolipathDirectoryFinderTrait,
+) -> Vec<String> {
+    let destinations: Vec<(String, (&Dependency, &DownloadInstruction))> = downloads
+        .iter()
+        .map(|(dependency, download_instruction)| {
+            let destination = directory_finder
+                .get_dependency_downloads_directory(dependency)
+                .join(download_instruction.get_destination_directory());
+            (destination.display().to_string(), (*dependency, *download_instruction))
+        })
+        .collect();
+    group_by_name(destinations.iter().map(|(destination, download)| (destination.as_str(), *download)))
+        .into_iter()
+        .filter(|(_, downloads)| downloads.len() > 1)
+        .map(|(destination, downloads)| {
+            let urls: Vec<String> = downloads
+                .iter()
+                .map(|(dependency, download_instruction)| {
+                    format!("{} from {}", download_instruction.get_url(), describe(dependency))
+                })
+                .collect();
+            format!("{} go to the same directory {}, only one of them is installed", urls.join(" and "), destination)
+        })
+        .collect()
+}
+
+fn describe(dependency: &Dependency) -> String {
+    format!("{} {}", dependency.name, dependency.version)
+}
+
+fn is_path_variable(name: &str) -> bool {
+    if std::env::consts::OS == "windows" {
+        name.eq_ignore_ascii_case("PATH")
+    } else {
+        name == "PATH"
+    }
+}
+
+// keeps the order names are first seen in
+fn group_by_name<'a, T>(entries: impl Iterator<Item = (&'a str, T)>) -> Vec<(&'a str, Vec<T>)> {
+    let mut groups: Vec<(&'a str, Vec<T>)> = Vec::new();
+    entries.for_each(|(name, entry)| match groups.iter_mut().find(|(group_name, _)| *group_name == name) {
+        Some((_, group)) => group.push(entry),
+        None => groups.push((name, vec![entry])),
+    });
+    groups
+}
+
+fn get_executables(directory: &Path) -> Vec<(String, PathBuf)> {
+    let Ok(entries) = read_dir(directory) else {
+        return Vec::new();
+    };
+    let mut executables: Vec<(String, PathBuf)> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter_map(|path| get_executable_name(&path).map(|name| (name, path)))
+        .collect();
+    executables.sort();
+    executables
+}
+
+fn get_executable_name(path: &Path) -> Option<String> {
+    if !is_executable(path) {
+        return None;
+    }
+    if std::env::consts::OS == "windows" {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        ["exe", "bat", "cmd", "com"]
+            .contains(&extension.as_str())
+            .then(|| path.file_stem().unwrap_or_default().to_string_lossy().to_lowercase())
+    } else {
+        Some(path.file_name()?.to_string_lossy().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::solipath_directory::moveable_home_directory_finder::MoveableHomeDirectoryFinder;
+
+    use super::*;
+
+    fn dependency_value(dependency: &str, name: &str, operation: EnvironmentVariableOperation, value: &Path) -> DependencyValue {
+        DependencyValue {
+            dependency: Dependency::new(dependency, "1"),
+            name: name.to_string(),
+            operation,
+            value: value.to_path_buf(),
+        }
+    }
+
+    #[test]
+    fn variables_set_by_two_dependencies_conflict() {
+        let values = vec![
+            dependency_value("java", "JAVA_HOME", EnvironmentVariableOperation::Set, Path::new("/java")),
+            dependency_value("java", "PATH", EnvironmentVariableOperation::PrependPath, Path::new("/java/bin")),
+            dependency_value("graalvm", "JAVA_HOME", EnvironmentVariableOperation::Set, Path::new("/graalvm")),
+            dependency_value("graalvm", "PATH", EnvironmentVariableOperation::PrependPath, Path::new("/graalvm/bin")),
+            dependency_value("maven", "JAVA_HOME", EnvironmentVariableOperation::SetIfAbsent, Path::new("/java")),
+        ];
+
+        assert_eq!(
+            vec!["JAVA_HOME is set by java 1 (/java) and graalvm 1 (/graalvm) and maven 1 (/java)".to_string()],
+            find_variable_conflicts(&values)
+        );
+        assert_eq!(Vec::<String>::new(), find_variable_conflicts(&values[0..2]));
+    }
+
+    #[test]
+    fn downloads_to_the_same_directory_conflict() {
+        let directory_finder = MoveableHomeDirectoryFinder::new(PathBuf::from("solipath"));
+        let java = Dependency::new("java", "17");
+        let download = |url: &str, destination_directory: &str| -> DownloadInstruction {
+            serde_json::from_str(&format!(
+                r#"{{"url": "{}", "destination_directory": "{}"}}"#,
+                url, destination_directory
+            ))
+            .unwrap()
+        };
+        let jdk = download("https://example.com/jdk.tar.gz", "jdk");
+        let jre = download("https://example.com/jre.tar.gz", "jdk");
+        let docs = download("https://example.com/docs.zip", "docs");
+
+        let conflicts = find_download_conflicts(&[(&java, &jdk), (&java, &jre), (&java, &docs)], &directory_finder);
+
+        assert_eq!(1, conflicts.len());
+        assert!(conflicts[0].starts_with(
+            "https://example.com/jdk.tar.gz from java 17 and https://example.com/jre.tar.gz from java 17 go to the same directory"
+        ));
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn executables_from_two_dependencies_conflict() {
+        use crate::solipath_download::file_permissions::set_file_as_executable;
+
+        let temp_dir = tempfile::tempdir().unwrap().into_path();
+        for executable in ["java/bin/java", "java/bin/javac", "graalvm/bin/java", "graalvm/bin/README"] {
+            let path = temp_dir.join(executable);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, "#!/bin/sh").unwrap();
+            if !executable.ends_with("README") {
+                set_file_as_executable(&path);
+            }
+        }
+        let values = vec![
+            dependency_value("java", "PATH", EnvironmentVariableOperation::PrependPath, &temp_dir.join("java/bin")),
+            dependency_value("graalvm", "PATH", EnvironmentVariableOperation::AppendPath, &temp_dir.join("graalvm/bin")),
+        ];
+
+        assert_eq!(
+            vec![format!(
+                "java is on the PATH from java 1 ({}) and graalvm 1 ({})",
+                temp_dir.join("java/bin/java").display(),
+                temp_dir.join("graalvm/bin/java").display()
+            )],
+            find_executable_conflicts(&values)
+        );
+    }
+}
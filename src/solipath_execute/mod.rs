@@ -1 +1,3 @@
-pub mod command_with_path_executor;
\ No newline at end of file
+pub mod command_locator;
+pub mod command_with_path_executor;
+pub mod conflict_detector;
\ No newline at end of file
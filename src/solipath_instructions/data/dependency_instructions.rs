@@ -41,6 +41,9 @@ impl DependencyInstructions {
     pub fn get_install_commands(&self) -> &Vec<InstallCommand> {
         self.install_instructions.get_install_commands()
     }
+    pub fn get_dependencies(&self) -> &Vec<Dependency> {
+        self.install_instructions.get_dependencies()
+    }
 
     pub fn filter_platform(&self, platform_filter: &Arc<dyn PlatformFilterTrait>) -> Self {
         Self {
@@ -55,6 +58,7 @@ pub trait VecDependencyInstructions {
     fn get_downloads(&self) -> Vec<(&Dependency, &DownloadInstruction)>;
     fn get_install_commands(&self) -> Vec<(&Dependency, &InstallCommand)>;
     fn get_templates(&self) -> Vec<(&Dependency, &Template)>;
+    fn get_dependencies(&self) -> Vec<(&Dependency, &Dependency)>;
     fn filter_platform(&self, platform_filter: &Arc<dyn PlatformFilterTrait>) -> Self;
 }
 
@@ -71,6 +75,9 @@ impl VecDependencyInstructions for Vec<DependencyInstructions> {
     fn get_templates(&self) -> Vec<(&Dependency, &Template)> {
         group_dependency_with_field(self, |instructions| instructions.get_templates())
     }
+    fn get_dependencies(&self) -> Vec<(&Dependency, &Dependency)> {
+        group_dependency_with_field(self, |instructions| instructions.get_dependencies())
+    }
 
     fn filter_platform(&self, platform_filter: &Arc<dyn PlatformFilterTrait>)-> Self{
         self.iter()
@@ -5,10 +5,22 @@ use crate::solipath_platform::{platform::Platform, platform_filter::HasPlatformF
 pub struct DownloadInstruction {
     url: String,
     destination_directory: String,
+    #[serde(default = "default_file_name", alias = "rename_to")]
+    file_name: Option<String>,
+    #[serde(default = "default_executable")]
+    executable: Executable,
     #[serde(default = "default_platform_filters")]
     platform_filters: Vec<Platform>,
 }
 
+// `true` marks the downloaded file itself, a list marks files matching those globs inside the destination directory
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(untagged)]
+pub enum Executable {
+    Flag(bool),
+    Patterns(Vec<String>),
+}
+
 impl DownloadInstruction {
     pub fn get_url(&self) -> String {
         self.url.clone()
@@ -18,13 +30,76 @@ impl DownloadInstruction {
         self.destination_directory.clone()
     }
 
+    pub fn get_file_name(&self) -> Option<String> {
+        self.file_name.clone()
+    }
+
+    // downloaded_file_name is the name the file was saved as, which a content-disposition header can change
+    pub fn get_executable_patterns(&self, downloaded_file_name: &str) -> Vec<String> {
+        match &self.executable {
+            Executable::Flag(true) => vec![self.get_file_name().unwrap_or_else(|| downloaded_file_name.to_string())],
+            Executable::Flag(false) => Vec::new(),
+            Executable::Patterns(patterns) => patterns.clone(),
+        }
+    }
 }
 fn default_platform_filters() -> Vec<Platform> {
     Vec::new()
 }
 
+fn default_file_name() -> Option<String> {
+    None
+}
+
+fn default_executable() -> Executable {
+    Executable::Flag(false)
+}
+
 impl HasPlatformFilter for DownloadInstruction {
     fn get_platform_filters(&self) -> &[Platform] {
         &self.platform_filters
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::DownloadInstruction;
+
+    #[test]
+    fn no_executable_patterns_by_default() {
+        let download_instruction = serde_json::from_str::<DownloadInstruction>(
+            r#"{"url": "https://dl.k8s.io/v1.31.0/bin/linux/amd64/kubectl", "destination_directory": "bin"}"#,
+        )
+        .unwrap();
+        assert_eq!(None, download_instruction.get_file_name());
+        assert_eq!(Vec::<String>::new(), download_instruction.get_executable_patterns("kubectl"));
+    }
+
+    #[test]
+    fn executable_flag_uses_downloaded_file_name() {
+        let download_instruction = serde_json::from_str::<DownloadInstruction>(
+            r#"{"url": "https://dl.k8s.io/v1.31.0/bin/linux/amd64/download?raw=true", "destination_directory": "bin", "executable": true}"#,
+        )
+        .unwrap();
+        assert_eq!(vec!["kubectl".to_string()], download_instruction.get_executable_patterns("kubectl"));
+    }
+
+    #[test]
+    fn executable_flag_uses_renamed_file_name() {
+        let download_instruction = serde_json::from_str::<DownloadInstruction>(
+            r#"{"url": "https://github.com/jqlang/jq/releases/download/jq-1.7.1/jq-linux-amd64", "destination_directory": "bin", "rename_to": "jq", "executable": true}"#,
+        )
+        .unwrap();
+        assert_eq!(Some("jq".to_string()), download_instruction.get_file_name());
+        assert_eq!(vec!["jq".to_string()], download_instruction.get_executable_patterns("jq-linux-amd64"));
+    }
+
+    #[test]
+    fn executable_can_be_a_list_of_patterns() {
+        let download_instruction = serde_json::from_str::<DownloadInstruction>(
+            r#"{"url": "https://www.7-zip.org/a/tool.7z", "destination_directory": "tool", "executable": ["tool/bin/*"]}"#,
+        )
+        .unwrap();
+        assert_eq!(vec!["tool/bin/*".to_string()], download_instruction.get_executable_patterns("tool.7z"));
+    }
+}
@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use serde::Deserialize;
 
 use crate::solipath_platform::{platform::Platform, platform_filter::HasPlatformFilter};
@@ -7,11 +9,56 @@ pub struct EnvironmentVariable {
     name: String,
     relative_path: Option<String>,
     value: Option<String>,
+    #[serde(default = "default_operation")]
+    operation: Option<EnvironmentVariableOperation>,
+    #[serde(default = "default_priority")]
+    priority: i32,
     #[serde(default = "default_platform_filters")]
     platform_filters: Vec<Platform>,
+    // single quoted env file values are used as written, ${...} in them is not interpolated
+    #[serde(skip)]
+    literal: bool,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum EnvironmentVariableOperation {
+    Set,
+    PrependPath,
+    AppendPath,
+    Unset,
+    SetIfAbsent,
 }
 
 impl EnvironmentVariable {
+    pub fn new(
+        name: &str,
+        value: Option<String>,
+        relative_path: Option<String>,
+        operation: Option<EnvironmentVariableOperation>,
+        priority: i32,
+        platform_filters: Vec<Platform>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            relative_path,
+            value,
+            operation,
+            priority,
+            platform_filters,
+            literal: false,
+        }
+    }
+
+    pub fn as_literal(mut self) -> Self {
+        self.literal = true;
+        self
+    }
+
+    pub fn is_literal(&self) -> bool {
+        self.literal
+    }
+
     pub fn get_name(&self) -> String {
         self.name.clone()
     }
@@ -23,7 +70,34 @@ impl EnvironmentVariable {
     pub fn get_value(&self) -> &Option<String> {
         &self.value
     }
-    
+
+    // project variables from a file that is extended are relative to that file's directory, not the child's.
+    // paths starting with a ${...} variable are left alone, they are already absolute once interpolated
+    pub fn with_base_directory(&self, directory: &Path) -> Self {
+        let mut environment_variable = self.clone();
+        environment_variable.relative_path = self.relative_path.as_ref().map(|relative_path| {
+            if relative_path.starts_with("${") {
+                relative_path.clone()
+            } else {
+                directory.join(relative_path).to_string_lossy().to_string()
+            }
+        });
+        environment_variable
+    }
+
+    // higher priority path entries come first, equal priorities keep the order of solipath.json
+    pub fn get_priority(&self) -> i32 {
+        self.priority
+    }
+
+    // PATH is prepended unless told otherwise, every other variable is overwritten
+    pub fn get_operation(&self) -> EnvironmentVariableOperation {
+        self.operation.unwrap_or(if self.name == "PATH" {
+            EnvironmentVariableOperation::PrependPath
+        } else {
+            EnvironmentVariableOperation::Set
+        })
+    }
 }
 
 impl HasPlatformFilter for EnvironmentVariable{
@@ -35,9 +109,17 @@ fn default_platform_filters() -> Vec<Platform> {
     Vec::new()
 }
 
+fn default_operation() -> Option<EnvironmentVariableOperation> {
+    None
+}
+
+fn default_priority() -> i32 {
+    0
+}
+
 #[cfg(test)]
 mod tests {
-    use super::EnvironmentVariable;
+    use super::{EnvironmentVariable, EnvironmentVariableOperation};
 
     #[test]
     fn can_set_only_relative_path() {
@@ -60,4 +142,24 @@ mod tests {
         assert_eq!(None, environment_variable.get_relative_path().clone());
         assert_eq!(Some("the value".to_string()), environment_variable.get_value().clone());
     }
+
+    #[test]
+    fn path_is_prepended_and_other_variables_are_set_by_default() {
+        let path = serde_json::from_str::<EnvironmentVariable>(r#"{"name": "PATH", "relative_path": "bin"}"#).unwrap();
+        let java_home =
+            serde_json::from_str::<EnvironmentVariable>(r#"{"name": "JAVA_HOME", "relative_path": "jdk"}"#).unwrap();
+
+        assert_eq!(EnvironmentVariableOperation::PrependPath, path.get_operation());
+        assert_eq!(EnvironmentVariableOperation::Set, java_home.get_operation());
+    }
+
+    #[test]
+    fn can_set_operation() {
+        let environment_variable = serde_json::from_str::<EnvironmentVariable>(
+            r#"{"name": "LD_LIBRARY_PATH", "relative_path": "lib", "operation": "append_path"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(EnvironmentVariableOperation::AppendPath, environment_variable.get_operation());
+    }
 }
@@ -1,7 +1,10 @@
 use std::collections::HashMap;
+use std::time::Duration;
 
 use serde::Deserialize;
+use sha2::{Digest, Sha256};
 
+use crate::solipath_instructions::data::when_to_run_rule::WhenToRunRule;
 use crate::solipath_platform::{platform::Platform, platform_filter::HasPlatformFilter};
 
 
@@ -12,7 +15,14 @@ pub struct InstallCommand {
     platform_filters: Vec<Platform>,
     
     #[serde(default = "default_when_to_run_rules")]
-    when_to_run_rules: HashMap<String, serde_json::Value>
+    when_to_run_rules: HashMap<String, serde_json::Value>,
+
+    #[serde(default)]
+    timeout_seconds: Option<u64>,
+    #[serde(default)]
+    working_directory: Option<String>,
+    #[serde(default = "default_env")]
+    env: HashMap<String, String>
 }
 
 impl InstallCommand {
@@ -23,6 +33,28 @@ impl InstallCommand {
     pub fn get_when_to_run_rules(&self) -> &HashMap<String, serde_json::Value> {
         &self.when_to_run_rules
     }
+
+    pub fn get_when_to_run_rule(&self) -> anyhow::Result<WhenToRunRule> {
+        WhenToRunRule::from_rules(&self.when_to_run_rules)
+    }
+
+    pub fn get_timeout(&self) -> Option<Duration> {
+        self.timeout_seconds.map(Duration::from_secs)
+    }
+
+    // relative to the dependency's downloads directory
+    pub fn get_working_directory(&self) -> Option<&String> {
+        self.working_directory.as_ref()
+    }
+
+    pub fn get_env(&self) -> &HashMap<String, String> {
+        &self.env
+    }
+
+    // the downloads directory is already unique to the dependency version, this tells its commands apart
+    pub fn get_id(&self) -> String {
+        format!("{:x}", Sha256::digest(self.command.as_bytes()))[..16].to_string()
+    }
 }
 
 impl HasPlatformFilter for InstallCommand {
@@ -38,4 +70,8 @@ fn default_platform_filters() -> Vec<Platform> {
 
 fn default_when_to_run_rules() -> HashMap<String, serde_json::Value> {
     HashMap::new()
-}
\ No newline at end of file
+}
+
+fn default_env() -> HashMap<String, String> {
+    HashMap::new()
+}
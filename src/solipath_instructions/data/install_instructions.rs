@@ -1,5 +1,6 @@
 use std::sync::Arc;
 
+use crate::solipath_instructions::data::dependency::Dependency;
 use crate::solipath_instructions::data::download_instruction::DownloadInstruction;
 use crate::solipath_instructions::data::environment_variable::EnvironmentVariable;
 use crate::solipath_instructions::data::install_command::InstallCommand;
@@ -20,6 +21,10 @@ pub struct InstallInstructions {
 
     #[serde(default = "default_commands")]
     install_commands: Vec<InstallCommand>,
+
+    // other dependencies this one needs, the version can be a constraint like ^17
+    #[serde(default = "default_dependencies")]
+    dependencies: Vec<Dependency>,
 }
 
 fn default_downloads() -> Vec<DownloadInstruction> {
@@ -38,18 +43,24 @@ fn default_commands() -> Vec<InstallCommand> {
     Vec::new()
 }
 
+fn default_dependencies() -> Vec<Dependency> {
+    Vec::new()
+}
+
 impl InstallInstructions {
     pub fn new(
         templates: Vec<Template>,
         downloads: Vec<DownloadInstruction>,
         environment_variables: Vec<EnvironmentVariable>,
         install_commands: Vec<InstallCommand>,
+        dependencies: Vec<Dependency>,
     ) -> Self {
         Self {
             templates,
             downloads,
             environment_variables,
             install_commands,
+            dependencies,
         }
     }
     pub fn get_downloads(&self) -> &Vec<DownloadInstruction> {
@@ -67,16 +78,22 @@ impl InstallInstructions {
     pub fn get_install_commands(&self) -> &Vec<InstallCommand> {
         &self.install_commands
     }
+
+    pub fn get_dependencies(&self) -> &Vec<Dependency> {
+        &self.dependencies
+    }
     pub fn filter_platform(&self, platform_filter: &Arc<dyn PlatformFilterTrait>) -> Self {
         let templates = filter_list(platform_filter, self.get_templates());
         let downloads = filter_list(platform_filter, self.get_downloads());
         let environment_variables = filter_list(platform_filter, self.get_environment_variables());
         let install_commands = filter_list(platform_filter, self.get_install_commands());
+        let dependencies = filter_list(platform_filter, self.get_dependencies());
         Self {
                 templates,
                 downloads,
                 environment_variables,
-                install_commands
+                install_commands,
+                dependencies
         }
     }
 }
@@ -148,4 +165,17 @@ mod tests{
         let filtered_instructions = install_instructions.filter_platform(&platform_filter);
         assert_eq!(vec![install_instructions.templates[0].clone()], filtered_instructions.templates)
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn can_filter_dependencies(){
+        let platform_filter = create_platform_filter();
+        let install_instructions = serde_json::from_str::<InstallInstructions>(
+            r#"{"dependencies": [
+                {"name": "java", "version": "^17", "platform_filters": [{"os": "Matching OS", "arch": "Matching Arch"}]},
+                {"name": "rosetta", "version": "2", "platform_filters": [{"os": "a bad match", "arch": "x86"}]}
+            ]}"#).unwrap();
+
+        let filtered_instructions = install_instructions.filter_platform(&platform_filter);
+        assert_eq!(vec![install_instructions.dependencies[0].clone()], filtered_instructions.dependencies)
+    }
+}
@@ -3,5 +3,9 @@ pub mod download_instruction;
 pub mod environment_variable;
 pub mod install_instructions;
 pub mod install_command;
+pub mod script;
+pub mod solipath_file;
 pub mod template;
+pub mod version_constraint;
+pub mod when_to_run_rule;
 pub mod dependency;
\ No newline at end of file
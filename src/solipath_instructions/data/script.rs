@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+// "build": "gradle build", "test": ["npm ci", "npm test"] or "release": {"commands": [...], "depends_on": ["test"]}
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(from = "ScriptFormat")]
+pub struct Script {
+    commands: Vec<String>,
+    depends_on: Vec<String>,
+}
+
+impl Script {
+    pub fn new(commands: Vec<String>, depends_on: Vec<String>) -> Self {
+        Self { commands, depends_on }
+    }
+
+    pub fn get_commands(&self) -> &Vec<String> {
+        &self.commands
+    }
+
+    pub fn get_depends_on(&self) -> &Vec<String> {
+        &self.depends_on
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScriptFormat {
+    Commands(ScriptCommands),
+    Settings(ScriptSettings),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScriptCommands {
+    Single(String),
+    List(Vec<String>),
+}
+
+#[derive(Deserialize)]
+struct ScriptSettings {
+    commands: ScriptCommands,
+    #[serde(default = "default_depends_on")]
+    depends_on: Vec<String>,
+}
+
+impl From<ScriptCommands> for Vec<String> {
+    fn from(commands: ScriptCommands) -> Self {
+        match commands {
+            ScriptCommands::Single(command) => vec![command],
+            ScriptCommands::List(commands) => commands,
+        }
+    }
+}
+
+impl From<ScriptFormat> for Script {
+    fn from(format: ScriptFormat) -> Self {
+        match format {
+            ScriptFormat::Commands(commands) => Script::new(commands.into(), Vec::new()),
+            ScriptFormat::Settings(settings) => Script::new(settings.commands.into(), settings.depends_on),
+        }
+    }
+}
+
+fn default_depends_on() -> Vec<String> {
+    Vec::new()
+}
+
+// every script that name depends on comes before it, and each script is only run once
+pub fn order_scripts<'a>(scripts: &'a HashMap<String, Script>, name: &'a str) -> Result<Vec<&'a str>> {
+    let mut ordered_names: Vec<&str> = Vec::new();
+    visit(scripts, name, &mut ordered_names, &mut Vec::new())?;
+    Ok(ordered_names)
+}
+
+fn visit<'a>(
+    scripts: &'a HashMap<String, Script>,
+    name: &'a str,
+    ordered_names: &mut Vec<&'a str>,
+    names_in_progress: &mut Vec<&'a str>,
+) -> Result<()> {
+    if ordered_names.contains(&name) {
+        return Ok(());
+    }
+    if names_in_progress.contains(&name) {
+        bail!("scripts depend on each other: {} -> {}", names_in_progress.join(" -> "), name);
+    }
+    let script = scripts.get(name).with_context(|| {
+        let mut script_names: Vec<&String> = scripts.keys().collect();
+        script_names.sort();
+        format!("no script named {} in solipath.json, available scripts: {:?}", name, script_names)
+    })?;
+    names_in_progress.push(name);
+    for dependency in script.get_depends_on() {
+        visit(scripts, dependency, ordered_names, names_in_progress)?;
+    }
+    names_in_progress.pop();
+    ordered_names.push(name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scripts(json: &str) -> HashMap<String, Script> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn can_parse_each_script_form() {
+        let scripts = scripts(
+            r#"{
+                "build": "gradle build",
+                "test": ["npm ci", "npm test"],
+                "release": {"commands": "gradle publish", "depends_on": ["build", "test"]}
+            }"#,
+        );
+
+        assert_eq!(Script::new(vec!["gradle build".to_string()], Vec::new()), scripts["build"]);
+        assert_eq!(
+            Script::new(vec!["npm ci".to_string(), "npm test".to_string()], Vec::new()),
+            scripts["test"]
+        );
+        assert_eq!(
+            Script::new(vec!["gradle publish".to_string()], vec!["build".to_string(), "test".to_string()]),
+            scripts["release"]
+        );
+    }
+
+    #[test]
+    fn dependencies_run_first_and_only_once() {
+        let scripts = scripts(
+            r#"{
+                "compile": "gradle compileJava",
+                "test": {"commands": "gradle test", "depends_on": ["compile"]},
+                "release": {"commands": "gradle publish", "depends_on": ["compile", "test"]}
+            }"#,
+        );
+
+        assert_eq!(vec!["compile", "test", "release"], order_scripts(&scripts, "release").unwrap());
+    }
+
+    #[test]
+    fn circular_dependencies_are_an_error() {
+        let scripts = scripts(
+            r#"{
+                "first": {"commands": "echo 1", "depends_on": ["second"]},
+                "second": {"commands": "echo 2", "depends_on": ["first"]}
+            }"#,
+        );
+        assert_eq!(
+            "scripts depend on each other: first -> second -> first",
+            order_scripts(&scripts, "first").unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn unknown_script_is_an_error() {
+        let error = order_scripts(&scripts(r#"{"build": "gradle build"}"#), "deploy").unwrap_err();
+        assert!(error.to_string().starts_with("no script named deploy in solipath.json"));
+    }
+}
@@ -0,0 +1,458 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::de::{MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+
+use crate::solipath_instructions::data::dependency::Dependency;
+use crate::solipath_instructions::data::environment_variable::{EnvironmentVariable, EnvironmentVariableOperation};
+use crate::solipath_instructions::data::script::Script;
+use crate::solipath_platform::platform::Platform;
+
+// solipath.json is either a list of dependencies, or an object that can also hold project environment variables
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(from = "SolipathFileFormat")]
+pub struct SolipathFile {
+    dependencies: Vec<Dependency>,
+    env: Vec<EnvironmentVariable>,
+    env_files: Vec<String>,
+    hermetic: bool,
+    strict: bool,
+    pass_env: Vec<String>,
+    scripts: HashMap<String, Script>,
+    project_root: Option<PathBuf>,
+    extends: Option<Extends>,
+}
+
+// "extends": true uses the nearest solipath file above this one, "extends": "../solipath.json" names it
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Extends {
+    NearestParent,
+    Path(PathBuf),
+}
+
+// a dependency the child file gives a different version to than the file it extends
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DependencyOverride {
+    pub name: String,
+    pub parent_version: String,
+    pub version: String,
+}
+
+impl SolipathFile {
+    pub fn new(dependencies: Vec<Dependency>) -> Self {
+        Self {
+            dependencies,
+            env: Vec::new(),
+            env_files: Vec::new(),
+            hermetic: false,
+            strict: false,
+            pass_env: Vec::new(),
+            scripts: HashMap::new(),
+            project_root: None,
+            extends: None,
+        }
+    }
+
+    pub fn get_dependencies(&self) -> &Vec<Dependency> {
+        &self.dependencies
+    }
+
+    pub fn get_environment_variables(&self) -> &Vec<EnvironmentVariable> {
+        &self.env
+    }
+
+    pub fn get_env_files(&self) -> &Vec<String> {
+        &self.env_files
+    }
+
+    pub fn is_hermetic(&self) -> bool {
+        self.hermetic
+    }
+
+    pub fn set_hermetic(&mut self, hermetic: bool) {
+        self.hermetic = hermetic;
+    }
+
+    // conflicts between dependencies are errors instead of warnings
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    pub fn get_pass_env(&self) -> &Vec<String> {
+        &self.pass_env
+    }
+
+    pub fn get_scripts(&self) -> &HashMap<String, Script> {
+        &self.scripts
+    }
+
+    // only known once the file has been found on disk
+    pub fn get_project_root(&self) -> Option<&PathBuf> {
+        self.project_root.as_ref()
+    }
+
+    pub fn set_project_root(&mut self, project_root: PathBuf) {
+        self.project_root = Some(project_root);
+    }
+
+    pub fn get_extends(&self) -> Option<&Extends> {
+        self.extends.as_ref()
+    }
+
+    // the parent's settings are applied first so this file's win: dependencies and scripts with the same name are
+    // replaced, and env variables and env_files come after the parent's
+    pub fn extend(self, parent: SolipathFile) -> (SolipathFile, Vec<DependencyOverride>) {
+        let parent_root = parent.project_root.clone();
+        let mut overrides = Vec::new();
+        // an overridden dependency keeps the parent's position, so the order dependencies are set up in doesn't change
+        let mut dependencies: Vec<Dependency> = Vec::new();
+        for parent_dependency in parent.dependencies {
+            match self.dependencies.iter().find(|dependency| dependency.name == parent_dependency.name) {
+                Some(dependency) => {
+                    if dependency.version != parent_dependency.version {
+                        overrides.push(DependencyOverride {
+                            name: dependency.name.clone(),
+                            parent_version: parent_dependency.version,
+                            version: dependency.version.clone(),
+                        });
+                    }
+                    dependencies.push(dependency.clone());
+                }
+                None => dependencies.push(parent_dependency),
+            }
+        }
+        let new_dependencies: Vec<Dependency> = self
+            .dependencies
+            .into_iter()
+            .filter(|dependency| !dependencies.iter().any(|inherited| inherited.name == dependency.name))
+            .collect();
+        dependencies.extend(new_dependencies);
+
+        let mut env: Vec<EnvironmentVariable> = parent
+            .env
+            .iter()
+            .map(|environment_variable| match &parent_root {
+                Some(parent_root) => environment_variable.with_base_directory(parent_root),
+                None => environment_variable.clone(),
+            })
+            .collect();
+        env.extend(self.env);
+        let mut env_files: Vec<String> = parent
+            .env_files
+            .into_iter()
+            .map(|env_file| resolve_against(&parent_root, env_file))
+            .collect();
+        env_files.extend(self.env_files);
+        let mut pass_env = parent.pass_env;
+        self.pass_env
+            .into_iter()
+            .for_each(|name| if !pass_env.contains(&name) { pass_env.push(name) });
+        let mut scripts = parent.scripts;
+        scripts.extend(self.scripts);
+
+        let merged = SolipathFile {
+            dependencies,
+            env,
+            env_files,
+            hermetic: parent.hermetic || self.hermetic,
+            strict: parent.strict || self.strict,
+            pass_env,
+            scripts,
+            project_root: self.project_root,
+            extends: None,
+        };
+        (merged, overrides)
+    }
+}
+
+fn resolve_against(directory: &Option<PathBuf>, path: String) -> String {
+    match directory {
+        Some(directory) => directory.join(Path::new(&path)).to_string_lossy().to_string(),
+        None => path,
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ExtendsFormat {
+    Enabled(bool),
+    Path(PathBuf),
+}
+
+impl ExtendsFormat {
+    fn into_extends(self) -> Option<Extends> {
+        match self {
+            ExtendsFormat::Enabled(true) => Some(Extends::NearestParent),
+            ExtendsFormat::Enabled(false) => None,
+            ExtendsFormat::Path(path) => Some(Extends::Path(path)),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SolipathFileFormat {
+    DependencyList(Vec<Dependency>),
+    Project(ProjectFormat),
+}
+
+#[derive(Deserialize)]
+struct ProjectFormat {
+    #[serde(default = "default_dependencies")]
+    dependencies: Vec<Dependency>,
+    #[serde(default = "default_env", deserialize_with = "deserialize_env")]
+    env: Vec<EnvironmentVariable>,
+    #[serde(default = "default_env_files")]
+    env_files: Vec<String>,
+    #[serde(default = "default_hermetic")]
+    hermetic: bool,
+    #[serde(default = "default_strict")]
+    strict: bool,
+    #[serde(default = "default_pass_env")]
+    pass_env: Vec<String>,
+    #[serde(default = "default_scripts")]
+    scripts: HashMap<String, Script>,
+    extends: Option<ExtendsFormat>,
+}
+
+impl From<SolipathFileFormat> for SolipathFile {
+    fn from(format: SolipathFileFormat) -> Self {
+        match format {
+            SolipathFileFormat::DependencyList(dependencies) => SolipathFile::new(dependencies),
+            SolipathFileFormat::Project(project) => SolipathFile {
+                dependencies: project.dependencies,
+                env: project.env,
+                env_files: project.env_files,
+                hermetic: project.hermetic,
+                strict: project.strict,
+                pass_env: project.pass_env,
+                scripts: project.scripts,
+                project_root: None,
+                extends: project.extends.and_then(ExtendsFormat::into_extends),
+            },
+        }
+    }
+}
+
+// "NAME": "value" or "NAME": {"value": ..., "operation": ..., "platform_filters": [...]}
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ProjectVariable {
+    Value(String),
+    Settings(ProjectVariableSettings),
+}
+
+#[derive(Deserialize)]
+struct ProjectVariableSettings {
+    value: Option<String>,
+    relative_path: Option<String>,
+    operation: Option<EnvironmentVariableOperation>,
+    #[serde(default = "default_priority")]
+    priority: i32,
+    #[serde(default = "default_platform_filters")]
+    platform_filters: Vec<Platform>,
+}
+
+impl ProjectVariable {
+    fn into_environment_variable(self, name: &str) -> EnvironmentVariable {
+        match self {
+            ProjectVariable::Value(value) => EnvironmentVariable::new(name, Some(value), None, None, 0, Vec::new()),
+            ProjectVariable::Settings(settings) => EnvironmentVariable::new(
+                name,
+                settings.value,
+                settings.relative_path,
+                settings.operation,
+                settings.priority,
+                settings.platform_filters,
+            ),
+        }
+    }
+}
+
+// variables are applied in the order they are written, so the map is read into a list rather than a HashMap
+fn deserialize_env<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<EnvironmentVariable>, D::Error> {
+    struct EnvVisitor;
+
+    impl<'de> Visitor<'de> for EnvVisitor {
+        type Value = Vec<EnvironmentVariable>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a map of environment variable names to values")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut environment_variables = Vec::new();
+            while let Some((name, project_variable)) = map.next_entry::<String, ProjectVariable>()? {
+                environment_variables.push(project_variable.into_environment_variable(&name));
+            }
+            Ok(environment_variables)
+        }
+    }
+
+    deserializer.deserialize_map(EnvVisitor)
+}
+
+fn default_dependencies() -> Vec<Dependency> {
+    Vec::new()
+}
+
+fn default_env() -> Vec<EnvironmentVariable> {
+    Vec::new()
+}
+
+fn default_priority() -> i32 {
+    0
+}
+
+fn default_env_files() -> Vec<String> {
+    Vec::new()
+}
+
+fn default_hermetic() -> bool {
+    false
+}
+
+fn default_strict() -> bool {
+    false
+}
+
+fn default_pass_env() -> Vec<String> {
+    Vec::new()
+}
+
+fn default_scripts() -> HashMap<String, Script> {
+    HashMap::new()
+}
+
+fn default_platform_filters() -> Vec<Platform> {
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_list_of_dependencies() {
+        let solipath_file =
+            serde_json::from_str::<SolipathFile>(r#"[{"name": "java", "version": "21"}]"#).unwrap();
+
+        assert_eq!(&vec![Dependency::new("java", "21")], solipath_file.get_dependencies());
+        assert!(solipath_file.get_environment_variables().is_empty());
+        assert!(solipath_file.get_env_files().is_empty());
+        assert!(!solipath_file.is_hermetic());
+        assert!(!solipath_file.is_strict());
+        assert!(solipath_file.get_scripts().is_empty());
+    }
+
+    #[test]
+    fn can_parse_scripts() {
+        let solipath_file = serde_json::from_str::<SolipathFile>(
+            r#"{"dependencies": [], "scripts": {"build": "gradle build", "test": ["npm ci", "npm test"]}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(&vec!["gradle build".to_string()], solipath_file.get_scripts()["build"].get_commands());
+        assert_eq!(
+            &vec!["npm ci".to_string(), "npm test".to_string()],
+            solipath_file.get_scripts()["test"].get_commands()
+        );
+    }
+
+    #[test]
+    fn can_parse_object_with_env_and_env_files() {
+        let solipath_file = serde_json::from_str::<SolipathFile>(
+            r#"{
+                "dependencies": [{"name": "gradle", "version": "8.10"}],
+                "env": {
+                    "NODE_ENV": "development",
+                    "GRADLE_OPTS": {"value": "-Xmx2g", "platform_filters": [{"os": "linux"}]},
+                    "PATH": {"relative_path": "scripts", "priority": 10}
+                },
+                "env_files": [".env", ".env.local"]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(&vec![Dependency::new("gradle", "8.10")], solipath_file.get_dependencies());
+        let names: Vec<String> = solipath_file
+            .get_environment_variables()
+            .iter()
+            .map(|environment_variable| environment_variable.get_name())
+            .collect();
+        assert_eq!(vec!["NODE_ENV", "GRADLE_OPTS", "PATH"], names);
+        let gradle_opts = &solipath_file.get_environment_variables()[1];
+        assert_eq!(&Some("-Xmx2g".to_string()), gradle_opts.get_value());
+        assert_eq!(
+            EnvironmentVariableOperation::PrependPath,
+            solipath_file.get_environment_variables()[2].get_operation()
+        );
+        assert_eq!(0, gradle_opts.get_priority());
+        assert_eq!(10, solipath_file.get_environment_variables()[2].get_priority());
+        assert_eq!(&vec![".env".to_string(), ".env.local".to_string()], solipath_file.get_env_files());
+    }
+
+    #[test]
+    fn can_parse_strict_mode() {
+        let solipath_file = serde_json::from_str::<SolipathFile>(r#"{"dependencies": [], "strict": true}"#).unwrap();
+
+        assert!(solipath_file.is_strict());
+    }
+
+    #[test]
+    fn can_parse_hermetic_settings() {
+        let solipath_file = serde_json::from_str::<SolipathFile>(
+            r#"{"dependencies": [], "hermetic": true, "pass_env": ["CI", "GITHUB_TOKEN"]}"#,
+        )
+        .unwrap();
+
+        assert!(solipath_file.is_hermetic());
+        assert_eq!(&vec!["CI".to_string(), "GITHUB_TOKEN".to_string()], solipath_file.get_pass_env());
+    }
+
+    #[test]
+    fn extending_reports_child_versions_that_override_the_parent() {
+        let child = serde_json::from_str::<SolipathFile>(
+            r#"{"extends": "../solipath.json", "dependencies": [{"name": "java", "version": "21"}, {"name": "node", "version": "20"}]}"#,
+        )
+        .unwrap();
+        let parent = SolipathFile::new(vec![Dependency::new("java", "17"), Dependency::new("node", "20")]);
+
+        assert_eq!(Some(&Extends::Path(PathBuf::from("../solipath.json"))), child.get_extends());
+        let (merged, overrides) = child.extend(parent);
+
+        assert_eq!(&vec![Dependency::new("java", "21"), Dependency::new("node", "20")], merged.get_dependencies());
+        assert_eq!(
+            vec![DependencyOverride {
+                name: "java".to_string(),
+                parent_version: "17".to_string(),
+                version: "21".to_string()
+            }],
+            overrides
+        );
+    }
+
+    #[test]
+    fn overridden_dependencies_keep_the_parent_position() {
+        let child = SolipathFile::new(vec![Dependency::new("python", "3.12"), Dependency::new("java", "21")]);
+        let parent = SolipathFile::new(vec![
+            Dependency::new("java", "17"),
+            Dependency::new("gradle", "8.5"),
+            Dependency::new("node", "20"),
+        ]);
+
+        let (merged, _) = child.extend(parent);
+
+        assert_eq!(
+            &vec![
+                Dependency::new("java", "21"),
+                Dependency::new("gradle", "8.5"),
+                Dependency::new("node", "20"),
+                Dependency::new("python", "3.12"),
+            ],
+            merged.get_dependencies()
+        );
+    }
+}
@@ -10,6 +10,8 @@ pub struct Template {
     variables: HashMap<String, String>,
     #[serde(default = "default_platform_filters")]
     platform_filters: Vec<Platform>,
+    #[serde(default = "default_aliases")]
+    aliases: HashMap<String, String>,
 }
 
 impl Template {
@@ -19,6 +21,23 @@ impl Template {
     pub fn get_name(&self) -> String {
         self.name.clone()
     }
+    // "common:github-release" refers to the shared _common/templates folder instead of the dependency's own
+    pub fn get_common_name(&self) -> Option<&str> {
+        self.name.strip_prefix("common:")
+    }
+    // renames built in values such as an arch of x86_64, for downloads that call it x64
+    pub fn get_alias<'a>(&'a self, value: &'a str) -> &'a str {
+        self.aliases.get(value).map_or(value, String::as_str)
+    }
+    // variables set in install_instructions win over the built in ones
+    pub fn with_builtin_variables(&self, builtin_variables: HashMap<String, String>) -> Self {
+        let mut variables = builtin_variables;
+        variables.extend(self.variables.clone());
+        Self {
+            variables,
+            ..self.clone()
+        }
+    }
 }
 impl HasPlatformFilter for Template {
     fn get_platform_filters(&self) -> &[Platform] {
@@ -33,3 +52,7 @@ fn default_platform_filters() -> Vec<Platform> {
 fn default_variables() -> HashMap<String, String> {
     HashMap::new()
 }
+
+fn default_aliases() -> HashMap<String, String> {
+    HashMap::new()
+}
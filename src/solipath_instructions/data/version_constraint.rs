@@ -0,0 +1,124 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum ConstraintOperator {
+    Exact,
+    Caret,
+    Tilde,
+    AtLeast,
+}
+
+// the versions install_instructions ask for from other dependencies: "17" or "=17" for exactly that version,
+// "^17.0.2" for the same major version, "~3.12" for the same minor version and ">=8" for anything newer.
+// The version written in a constraint is the one installed unless something else needs a newer one
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct VersionConstraint {
+    operator: ConstraintOperator,
+    version: String,
+}
+
+impl VersionConstraint {
+    pub fn parse(constraint: &str) -> Self {
+        let constraint = constraint.trim();
+        let (operator, version) = if let Some(version) = constraint.strip_prefix(">=") {
+            (ConstraintOperator::AtLeast, version)
+        } else if let Some(version) = constraint.strip_prefix('^') {
+            (ConstraintOperator::Caret, version)
+        } else if let Some(version) = constraint.strip_prefix('~') {
+            (ConstraintOperator::Tilde, version)
+        } else if let Some(version) = constraint.strip_prefix('=') {
+            (ConstraintOperator::Exact, version)
+        } else {
+            (ConstraintOperator::Exact, constraint)
+        };
+        Self {
+            operator,
+            version: version.trim().to_string(),
+        }
+    }
+
+    pub fn get_version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn matches(&self, version: &str) -> bool {
+        let ordering = compare_versions(version, &self.version);
+        match self.operator {
+            ConstraintOperator::Exact => ordering == Ordering::Equal,
+            ConstraintOperator::AtLeast => ordering != Ordering::Less,
+            ConstraintOperator::Caret => {
+                let parts = split_version(&self.version);
+                let fixed_parts = parts.iter().position(|part| part != "0").map_or(parts.len(), |index| index + 1);
+                ordering != Ordering::Less && same_prefix(version, &self.version, fixed_parts)
+            }
+            ConstraintOperator::Tilde => {
+                let fixed_parts = split_version(&self.version).len().clamp(1, 2);
+                ordering != Ordering::Less && same_prefix(version, &self.version, fixed_parts)
+            }
+        }
+    }
+}
+
+impl fmt::Display for VersionConstraint {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let operator = match self.operator {
+            ConstraintOperator::Exact => "",
+            ConstraintOperator::Caret => "^",
+            ConstraintOperator::Tilde => "~",
+            ConstraintOperator::AtLeast => ">=",
+        };
+        write!(formatter, "{}{}", operator, self.version)
+    }
+}
+
+// numeric parts are compared as numbers and anything else as text, missing parts count as 0 so 17 equals 17.0
+pub fn compare_versions(left: &str, right: &str) -> Ordering {
+    let left_parts = split_version(left);
+    let right_parts = split_version(right);
+    (0..left_parts.len().max(right_parts.len()))
+        .map(|index| {
+            let left_part = left_parts.get(index).map_or("0", String::as_str);
+            let right_part = right_parts.get(index).map_or("0", String::as_str);
+            match (left_part.parse::<u64>(), right_part.parse::<u64>()) {
+                (Ok(left_number), Ok(right_number)) => left_number.cmp(&right_number),
+                _ => left_part.cmp(right_part),
+            }
+        })
+        .find(|ordering| *ordering != Ordering::Equal)
+        .unwrap_or(Ordering::Equal)
+}
+
+fn split_version(version: &str) -> Vec<String> {
+    version.split('.').map(|part| part.to_string()).collect()
+}
+
+fn same_prefix(left: &str, right: &str, number_of_parts: usize) -> bool {
+    let left_parts = split_version(left);
+    let right_parts = split_version(right);
+    (0..number_of_parts).all(|index| {
+        let left_part = left_parts.get(index).map_or("0", String::as_str);
+        let right_part = right_parts.get(index).map_or("0", String::as_str);
+        compare_versions(left_part, right_part) == Ordering::Equal
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constraints_match_versions() {
+        assert!(VersionConstraint::parse("17").matches("17.0"));
+        assert!(!VersionConstraint::parse("=17").matches("17.0.2"));
+        assert!(VersionConstraint::parse("^17").matches("17.0.2"));
+        assert!(!VersionConstraint::parse("^17").matches("21"));
+        assert!(!VersionConstraint::parse("^17.0.2").matches("17.0.1"));
+        assert!(!VersionConstraint::parse("^0.3").matches("0.4"));
+        assert!(VersionConstraint::parse("~3.12").matches("3.12.4"));
+        assert!(!VersionConstraint::parse("~3.12").matches("3.13"));
+        assert!(VersionConstraint::parse(">=8").matches("10.2"));
+        assert_eq!(Ordering::Less, compare_versions("1.9", "1.10"));
+        assert_eq!("^17", VersionConstraint::parse("^17").to_string());
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+
+const RULE_NAMES: [&str; 10] = [
+    "file_exists",
+    "file_does_not_exist",
+    "directory_empty",
+    "env_var_set",
+    "env_var_equals",
+    "command_not_on_path",
+    "once",
+    "any_of",
+    "all_of",
+    "not",
+];
+
+// paths are relative to the dependency's downloads directory. Every entry of a when_to_run_rules object has to pass,
+// any_of and all_of take a list of rule objects and not takes a single rule object
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum WhenToRunRule {
+    FileExists(String),
+    FileDoesNotExist(String),
+    DirectoryEmpty(String),
+    EnvVarSet(String),
+    EnvVarEquals { name: String, value: String },
+    CommandNotOnPath(String),
+    Once,
+    AnyOf(Vec<WhenToRunRule>),
+    AllOf(Vec<WhenToRunRule>),
+    Not(Box<WhenToRunRule>),
+}
+
+impl WhenToRunRule {
+    pub fn from_rules(rules: &HashMap<String, Value>) -> Result<Self> {
+        let mut rule_names: Vec<&String> = rules.keys().collect();
+        rule_names.sort();
+        rule_names
+            .into_iter()
+            .map(|rule_name| Self::from_entry(rule_name, &rules[rule_name]))
+            .collect::<Result<Vec<WhenToRunRule>>>()
+            .map(WhenToRunRule::AllOf)
+    }
+
+    // whether a successful run has to be recorded for a once rule
+    pub fn uses_once(&self) -> bool {
+        match self {
+            WhenToRunRule::Once => true,
+            WhenToRunRule::AnyOf(rules) | WhenToRunRule::AllOf(rules) => rules.iter().any(WhenToRunRule::uses_once),
+            WhenToRunRule::Not(rule) => rule.uses_once(),
+            _ => false,
+        }
+    }
+
+    fn from_value(value: &Value) -> Result<Self> {
+        let rules: HashMap<String, Value> = serde_json::from_value(value.clone())
+            .with_context(|| format!("expected an object of when_to_run_rules, found {}", value))?;
+        Self::from_rules(&rules)
+    }
+
+    fn from_list(rule_name: &str, value: &Value) -> Result<Vec<Self>> {
+        value
+            .as_array()
+            .with_context(|| format!("{} should be a list of rule objects", rule_name))?
+            .iter()
+            .map(Self::from_value)
+            .collect()
+    }
+
+    fn from_entry(rule_name: &str, value: &Value) -> Result<Self> {
+        let string_value = || {
+            value
+                .as_str()
+                .map(|string| string.to_string())
+                .with_context(|| format!("{} should be a string, found {}", rule_name, value))
+        };
+        Ok(match rule_name {
+            "file_exists" => WhenToRunRule::FileExists(string_value()?),
+            "file_does_not_exist" => WhenToRunRule::FileDoesNotExist(string_value()?),
+            "directory_empty" => WhenToRunRule::DirectoryEmpty(string_value()?),
+            "env_var_set" => WhenToRunRule::EnvVarSet(string_value()?),
+            "env_var_equals" => {
+                let name = value.get("name").and_then(Value::as_str);
+                let expected_value = value.get("value").and_then(Value::as_str);
+                match (name, expected_value) {
+                    (Some(name), Some(expected_value)) => WhenToRunRule::EnvVarEquals {
+                        name: name.to_string(),
+                        value: expected_value.to_string(),
+                    },
+                    _ => bail!("env_var_equals should be {{\"name\": ..., \"value\": ...}}, found {}", value),
+                }
+            }
+            "command_not_on_path" => WhenToRunRule::CommandNotOnPath(string_value()?),
+            "once" => match value.as_bool() {
+                Some(true) => WhenToRunRule::Once,
+                Some(false) => WhenToRunRule::AllOf(Vec::new()),
+                None => bail!("once should be true or false, found {}", value),
+            },
+            "any_of" => WhenToRunRule::AnyOf(Self::from_list(rule_name, value)?),
+            "all_of" => WhenToRunRule::AllOf(Self::from_list(rule_name, value)?),
+            "not" => WhenToRunRule::Not(Box::new(Self::from_value(value)?)),
+            _ => bail!(
+                "unrecognized when_to_run_rules entry '{}', expected one of {}",
+                rule_name,
+                RULE_NAMES.join(", ")
+            ),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> Result<WhenToRunRule> {
+        WhenToRunRule::from_rules(&serde_json::from_str(json).unwrap())
+    }
+
+    #[test]
+    fn can_parse_nested_rules() {
+        let rule = parse(
+            r#"{
+                "once": true,
+                "any_of": [{"command_not_on_path": "cargo"}, {"env_var_equals": {"name": "FORCE", "value": "1"}}],
+                "not": {"directory_empty": "bin"}
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            WhenToRunRule::AllOf(vec![
+                WhenToRunRule::AnyOf(vec![
+                    WhenToRunRule::AllOf(vec![WhenToRunRule::CommandNotOnPath("cargo".to_string())]),
+                    WhenToRunRule::AllOf(vec![WhenToRunRule::EnvVarEquals {
+                        name: "FORCE".to_string(),
+                        value: "1".to_string()
+                    }]),
+                ]),
+                WhenToRunRule::Not(Box::new(WhenToRunRule::AllOf(vec![WhenToRunRule::DirectoryEmpty(
+                    "bin".to_string()
+                )]))),
+                WhenToRunRule::Once,
+            ]),
+            rule
+        );
+    }
+
+    #[test]
+    fn unknown_and_malformed_rules_are_errors() {
+        let unknown = parse(r#"{"all_of": [{"nonexistent_filter": "something"}]}"#).unwrap_err();
+        let malformed = parse(r#"{"file_exists": 3}"#).unwrap_err();
+
+        assert!(unknown.to_string().starts_with("unrecognized when_to_run_rules entry 'nonexistent_filter'"));
+        assert_eq!("file_exists should be a string, found 3", malformed.to_string());
+    }
+}
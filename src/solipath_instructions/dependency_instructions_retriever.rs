@@ -1,8 +1,10 @@
 use async_trait::async_trait;
 use std::path::PathBuf;
 use std::sync::Arc;
+use crate::solipath_config::user_config::DEFAULT_REGISTRY;
 use crate::solipath_instructions::data::dependency::Dependency;
 use crate::solipath_instructions::data::dependency_instructions::DependencyInstructions;
+use crate::solipath_instructions::file_format::{get_file_names, parse_file};
 use crate::{
     solipath_directory::solipath_directory_finder::SolipathDirectoryFinderTrait,
     solipath_download::file_to_string_downloader::FileToStringDownloaderTrait,
@@ -18,7 +20,7 @@ pub trait DependencyInstructionsRetrieverTrait {
 }
 
 pub struct DependencyInstructionsRetriever {
-    base_dependency_url: String,
+    base_dependency_urls: Vec<String>,
     file_downloader: Arc<dyn FileToStringDownloaderTrait + Sync + Send>,
     directory_finder: Arc<dyn SolipathDirectoryFinderTrait + Sync + Send>,
 }
@@ -29,7 +31,7 @@ impl DependencyInstructionsRetriever {
         directory_finder: Arc<dyn SolipathDirectoryFinderTrait + Sync + Send>,
     ) -> Self {
         Self {
-            base_dependency_url: "https://raw.githubusercontent.com/Solipath/Solipath-Install-Instructions/main".to_string(),
+            base_dependency_urls: vec![DEFAULT_REGISTRY.to_string()],
             file_downloader,
             directory_finder,
         }
@@ -39,42 +41,49 @@ impl DependencyInstructionsRetriever {
         base_dependency_url: String,
         file_downloader: Arc<dyn FileToStringDownloaderTrait + Sync + Send>,
         directory_finder: Arc<dyn SolipathDirectoryFinderTrait + Sync + Send>,
+    ) -> Self {
+        Self::new_with_registries(vec![base_dependency_url], file_downloader, directory_finder)
+    }
+
+    pub fn new_with_registries(
+        base_dependency_urls: Vec<String>,
+        file_downloader: Arc<dyn FileToStringDownloaderTrait + Sync + Send>,
+        directory_finder: Arc<dyn SolipathDirectoryFinderTrait + Sync + Send>,
     ) -> Self {
         Self {
-            base_dependency_url,
+            base_dependency_urls,
             file_downloader,
             directory_finder,
         }
     }
 
 
-    fn get_path_to_save_file(&self, dependency: &Dependency) -> PathBuf {
-        let mut path_to_save_file = self.directory_finder.get_dependency_version_directory(&dependency);
-        path_to_save_file.push("install_instructions.json");
-        path_to_save_file
-    }
-
-    fn get_url(&self, dependency: &Dependency) -> String {
-        format!(
-            "{}/{}/{}/install_instructions.json",
-            &self.base_dependency_url, dependency.name, dependency.version
-        )
+    // registries are tried in order, and a registry can use any supported format, json is looked for first
+    fn get_files(&self, dependency: &Dependency) -> Vec<(String, PathBuf)> {
+        let dependency_directory = &self.directory_finder.get_dependency_version_directory(dependency);
+        self.base_dependency_urls
+            .iter()
+            .flat_map(|base_dependency_url| {
+                get_file_names("install_instructions").into_iter().map(move |file_name| {
+                    let url = format!(
+                        "{}/{}/{}/{}",
+                        base_dependency_url, dependency.name, dependency.version, file_name
+                    );
+                    (url, dependency_directory.join(file_name))
+                })
+            })
+            .collect()
     }
 }
 
 #[async_trait]
 impl DependencyInstructionsRetrieverTrait for DependencyInstructionsRetriever {
     async fn retrieve_dependency_instructions(&self, dependency: &Dependency) -> DependencyInstructions {
-        let path_to_save_file = self.get_path_to_save_file(dependency);
-        let url = self.get_url(dependency);
-        let dependency_json_string = self
+        let (path, contents) = self
             .file_downloader
-            .download_file_then_parse_to_string(&url, &path_to_save_file)
+            .download_first_available_file_then_parse_to_string(&self.get_files(dependency))
             .await;
-        DependencyInstructions::new(
-            dependency.clone(),
-            serde_json::from_str(&dependency_json_string).expect(&format!("failed to serialize install instructions {}", &dependency_json_string)),
-        )
+        DependencyInstructions::new(dependency.clone(), parse_file(&path, &contents))
     }
 }
 
@@ -109,13 +118,15 @@ mod test {
 
         let mut mock_file_downloader = MockFileToStringDownloaderTrait::new();
         mock_file_downloader
-            .expect_download_file_then_parse_to_string()
-            .withf(move |url, path| {
-                url == "https://raw.githubusercontent.com/Solipath/Solipath-Install-Instructions/main/name/version/install_instructions.json"
-                    && path == path_to_downloaded_file.clone()
+            .expect_download_first_available_file_then_parse_to_string()
+            .withf(move |files| {
+                files[0] == (
+                    "https://raw.githubusercontent.com/Solipath/Solipath-Install-Instructions/main/name/version/install_instructions.json".to_string(),
+                    path_to_downloaded_file.clone(),
+                )
             })
             .times(1)
-            .return_const("{}");
+            .return_const((PathBuf::from("install_instructions.json"), "{}".to_string()));
 
         let file_retriever =
             DependencyInstructionsRetriever::new(Arc::new(mock_file_downloader), Arc::new(mock_directory_finder));
@@ -123,4 +134,64 @@ mod test {
         let actual = file_retriever.retrieve_dependency_instructions(&input_dependency).await;
         assert_eq!(actual, expected);
     }
+
+    #[tokio::test]
+    async fn can_retrieve_yaml_install_instructions() {
+        let dependency = Dependency::new("name", "version");
+        let expected_instructions: InstallInstructions =
+            serde_json::from_str(r#"{"environment_variables": [{"name": "PATH", "relative_path": "bin"}]}"#).unwrap();
+        let mut mock_directory_finder = MockSolipathDirectoryFinderTrait::new();
+        mock_directory_finder
+            .expect_get_dependency_version_directory()
+            .return_const(PathBuf::from("/solipath/name/version"));
+        let mut mock_file_downloader = MockFileToStringDownloaderTrait::new();
+        mock_file_downloader
+            .expect_download_first_available_file_then_parse_to_string()
+            .withf(|files| {
+                files.iter().map(|(_, path)| path.clone()).collect::<Vec<PathBuf>>()
+                    == vec![
+                        PathBuf::from("/solipath/name/version/install_instructions.json"),
+                        PathBuf::from("/solipath/name/version/install_instructions.jsonc"),
+                        PathBuf::from("/solipath/name/version/install_instructions.toml"),
+                        PathBuf::from("/solipath/name/version/install_instructions.yaml"),
+                        PathBuf::from("/solipath/name/version/install_instructions.yml"),
+                    ]
+            })
+            .return_const((
+                PathBuf::from("/solipath/name/version/install_instructions.yaml"),
+                "environment_variables:\n  - name: PATH\n    relative_path: bin\n".to_string(),
+            ));
+
+        let file_retriever =
+            DependencyInstructionsRetriever::new(Arc::new(mock_file_downloader), Arc::new(mock_directory_finder));
+
+        let actual = file_retriever.retrieve_dependency_instructions(&dependency).await;
+        assert_eq!(DependencyInstructions::new(dependency, expected_instructions), actual);
+    }
+
+    #[tokio::test]
+    async fn registries_are_tried_in_order() {
+        let dependency = Dependency::new("name", "version");
+        let mut mock_directory_finder = MockSolipathDirectoryFinderTrait::new();
+        mock_directory_finder
+            .expect_get_dependency_version_directory()
+            .return_const(PathBuf::from("/solipath/name/version"));
+        let mut mock_file_downloader = MockFileToStringDownloaderTrait::new();
+        mock_file_downloader
+            .expect_download_first_available_file_then_parse_to_string()
+            .withf(|files| {
+                files.len() == 10
+                    && files[0].0 == "https://mirror.example.com/name/version/install_instructions.json"
+                    && files[5].0 == "https://registry.example.com/name/version/install_instructions.json"
+            })
+            .return_const((PathBuf::from("install_instructions.json"), "{}".to_string()));
+
+        let file_retriever = DependencyInstructionsRetriever::new_with_registries(
+            vec!["https://mirror.example.com".to_string(), "https://registry.example.com".to_string()],
+            Arc::new(mock_file_downloader),
+            Arc::new(mock_directory_finder),
+        );
+
+        file_retriever.retrieve_dependency_instructions(&dependency).await;
+    }
 }
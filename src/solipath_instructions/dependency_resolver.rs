@@ -0,0 +1,173 @@
+use anyhow::{bail, Context, Result};
+
+use crate::solipath_instructions::data::dependency::Dependency;
+use crate::solipath_instructions::data::version_constraint::{compare_versions, VersionConstraint};
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct DependencySelection {
+    pub dependencies: Vec<Dependency>,
+    pub warnings: Vec<String>,
+}
+
+pub fn is_same_dependency(left: &Dependency, right: &Dependency) -> bool {
+    left.name == right.name && left.version == right.version
+}
+
+// requirements pair a dependency with one it needs. The solipath file's own versions always win, otherwise the
+// highest version written in a constraint that every constraint accepts is picked, so ^17 and ^17.0.2 install 17.0.2
+pub fn select_versions(
+    project_dependencies: &[Dependency],
+    requirements: &[(&Dependency, &Dependency)],
+) -> Result<DependencySelection> {
+    let mut dependencies = project_dependencies.to_vec();
+    let mut warnings = Vec::new();
+    let mut needed_names: Vec<&String> = Vec::new();
+    for (required_by, needed) in requirements {
+        match project_dependencies.iter().find(|project_dependency| project_dependency.name == needed.name) {
+            Some(project_dependency)
+                if !VersionConstraint::parse(&needed.version).matches(&project_dependency.version) =>
+            {
+                warnings.push(format!(
+                    "warning: {} {} needs {} {}, using {} from the solipath file",
+                    required_by.name, required_by.version, needed.name, needed.version, project_dependency.version
+                ))
+            }
+            Some(_) => {}
+            None if !needed_names.contains(&&needed.name) => needed_names.push(&needed.name),
+            None => {}
+        }
+    }
+    for name in needed_names {
+        let constraints: Vec<(&Dependency, VersionConstraint)> = requirements
+            .iter()
+            .filter(|(_, needed)| &needed.name == name)
+            .map(|(required_by, needed)| (*required_by, VersionConstraint::parse(&needed.version)))
+            .collect();
+        let mut candidates: Vec<&str> = constraints.iter().map(|(_, constraint)| constraint.get_version()).collect();
+        candidates.sort_by(|left, right| compare_versions(right, left));
+        let version = candidates
+            .into_iter()
+            .find(|candidate| constraints.iter().all(|(_, constraint)| constraint.matches(candidate)))
+            .with_context(|| {
+                let needed_by: Vec<String> = constraints
+                    .iter()
+                    .map(|(required_by, constraint)| format!("{} {} needs {}", required_by.name, required_by.version, constraint))
+                    .collect();
+                format!("no version of {} works for every dependency: {}", name, needed_by.join(", "))
+            })?;
+        dependencies.push(Dependency::new(name, version));
+    }
+    Ok(DependencySelection { dependencies, warnings })
+}
+
+// dependencies come after everything they need, otherwise the original order is kept
+pub fn order_dependencies(
+    dependencies: &[Dependency],
+    requirements: &[(&Dependency, &Dependency)],
+) -> Result<Vec<Dependency>> {
+    let mut ordered = Vec::new();
+    let mut path = Vec::new();
+    for dependency in dependencies {
+        visit(dependency, dependencies, requirements, &mut path, &mut ordered)?;
+    }
+    Ok(ordered)
+}
+
+fn visit(
+    dependency: &Dependency,
+    dependencies: &[Dependency],
+    requirements: &[(&Dependency, &Dependency)],
+    path: &mut Vec<String>,
+    ordered: &mut Vec<Dependency>,
+) -> Result<()> {
+    if ordered.iter().any(|ordered_dependency| ordered_dependency.name == dependency.name) {
+        return Ok(());
+    }
+    if let Some(cycle_start) = path.iter().position(|name| name == &dependency.name) {
+        let mut cycle = path[cycle_start..].to_vec();
+        cycle.push(dependency.name.clone());
+        bail!("dependencies need each other: {}", cycle.join(" -> "));
+    }
+    path.push(dependency.name.clone());
+    for (_, needed) in requirements.iter().filter(|(required_by, _)| is_same_dependency(required_by, dependency)) {
+        if let Some(selected) = dependencies.iter().find(|selected| selected.name == needed.name) {
+            visit(selected, dependencies, requirements, path, ordered)?;
+        }
+    }
+    path.pop();
+    ordered.push(dependency.clone());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_are_deduplicated_and_the_solipath_file_wins() {
+        let gradle = Dependency::new("gradle", "8.5");
+        let kotlin = Dependency::new("kotlin", "2.0");
+        let node_tool = Dependency::new("node-tool", "1");
+        let project_dependencies = vec![gradle.clone(), kotlin.clone(), node_tool.clone()];
+        let java_17 = Dependency::new("java", "^17");
+        let java_17_0_2 = Dependency::new("java", "^17.0.2");
+        let node = Dependency::new("node", "20");
+        let old_kotlin = Dependency::new("kotlin", "^1.9");
+        let requirements = vec![
+            (&gradle, &java_17),
+            (&kotlin, &java_17_0_2),
+            (&gradle, &old_kotlin),
+            (&node_tool, &node),
+        ];
+
+        let selection = select_versions(&project_dependencies, &requirements).unwrap();
+
+        assert_eq!(
+            DependencySelection {
+                dependencies: vec![
+                    gradle.clone(),
+                    kotlin.clone(),
+                    node_tool.clone(),
+                    Dependency::new("java", "17.0.2"),
+                    Dependency::new("node", "20"),
+                ],
+                warnings: vec!["warning: gradle 8.5 needs kotlin ^1.9, using 2.0 from the solipath file".to_string()],
+            },
+            selection
+        );
+    }
+
+    #[test]
+    fn incompatible_constraints_are_an_error() {
+        let gradle = Dependency::new("gradle", "8.5");
+        let legacy_tool = Dependency::new("legacy-tool", "1");
+        let java_17 = Dependency::new("java", "^17");
+        let java_8 = Dependency::new("java", "8");
+
+        let error = select_versions(&[gradle.clone(), legacy_tool.clone()], &[(&gradle, &java_17), (&legacy_tool, &java_8)])
+            .unwrap_err();
+
+        assert_eq!(
+            "no version of java works for every dependency: gradle 8.5 needs ^17, legacy-tool 1 needs 8",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn dependencies_are_ordered_after_what_they_need_and_cycles_are_errors() {
+        let gradle = Dependency::new("gradle", "8.5");
+        let java = Dependency::new("java", "17");
+        let java_constraint = Dependency::new("java", "^17");
+        let gradle_constraint = Dependency::new("gradle", "8.5");
+
+        let ordered = order_dependencies(&[gradle.clone(), java.clone()], &[(&gradle, &java_constraint)]).unwrap();
+        let cycle = order_dependencies(
+            &[gradle.clone(), java.clone()],
+            &[(&gradle, &java_constraint), (&java, &gradle_constraint)],
+        )
+        .unwrap_err();
+
+        assert_eq!(vec![java, gradle], ordered);
+        assert_eq!("dependencies need each other: gradle -> java -> gradle", cycle.to_string());
+    }
+}
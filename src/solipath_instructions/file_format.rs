@@ -0,0 +1,215 @@
+use std::fmt::Display;
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Json,
+    Jsonc,
+    Toml,
+    Yaml,
+}
+
+// the order files are looked for in, when more than one format could exist
+pub const FILE_FORMATS: [FileFormat; 4] = [FileFormat::Json, FileFormat::Jsonc, FileFormat::Toml, FileFormat::Yaml];
+
+impl FileFormat {
+    // anything without a known extension is read as json
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("jsonc") => FileFormat::Jsonc,
+            Some("toml") => FileFormat::Toml,
+            Some("yaml") | Some("yml") => FileFormat::Yaml,
+            _ => FileFormat::Json,
+        }
+    }
+
+    pub fn get_extensions(&self) -> &'static [&'static str] {
+        match self {
+            FileFormat::Json => &["json"],
+            FileFormat::Jsonc => &["jsonc"],
+            FileFormat::Toml => &["toml"],
+            FileFormat::Yaml => &["yaml", "yml"],
+        }
+    }
+
+    pub fn parse<T: DeserializeOwned>(&self, contents: &str) -> Result<T, ParseError> {
+        match self {
+            FileFormat::Json => serde_json::from_str(contents).map_err(ParseError::from_json),
+            FileFormat::Jsonc => serde_json::from_str(&strip_comments(contents)).map_err(ParseError::from_json),
+            FileFormat::Toml => toml::from_str(contents).map_err(|error| ParseError::from_toml(contents, error)),
+            FileFormat::Yaml => serde_yaml::from_str(contents).map_err(ParseError::from_yaml),
+        }
+    }
+}
+
+// every name a file can be published as, in the order they are looked for
+pub fn get_file_names(base_name: &str) -> Vec<String> {
+    FILE_FORMATS
+        .iter()
+        .flat_map(|format| format.get_extensions())
+        .map(|extension| format!("{}.{}", base_name, extension))
+        .collect()
+}
+
+pub fn has_supported_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| FILE_FORMATS.iter().any(|format| format.get_extensions().contains(&extension)))
+}
+
+// picks the format from the file extension and panics with the line and column of any error
+pub fn parse_file<T: DeserializeOwned>(path: &Path, contents: &str) -> T {
+    FileFormat::from_path(path)
+        .parse(contents)
+        .unwrap_or_else(|error| panic!("failed to parse {}: {}", path.display(), error))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    fn from_json(error: serde_json::Error) -> Self {
+        Self {
+            line: error.line(),
+            column: error.column(),
+            message: error.to_string(),
+        }
+    }
+
+    fn from_toml(contents: &str, error: toml::de::Error) -> Self {
+        let offset = error.span().map(|span| span.start).unwrap_or_default();
+        let before_error = &contents[..offset.min(contents.len())];
+        let line = before_error.matches('\n').count() + 1;
+        let column = before_error.len() - before_error.rfind('\n').map(|index| index + 1).unwrap_or(0) + 1;
+        Self {
+            line,
+            column,
+            message: error.message().to_string(),
+        }
+    }
+
+    fn from_yaml(error: serde_yaml::Error) -> Self {
+        let location = error.location();
+        Self {
+            line: location.as_ref().map(|location| location.line()).unwrap_or_default(),
+            column: location.as_ref().map(|location| location.column()).unwrap_or_default(),
+            message: error.to_string(),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "line {} column {}: {}", self.line, self.column, self.message)
+    }
+}
+
+// comments are replaced with spaces rather than removed, so errors still point at the right line and column
+fn strip_comments(contents: &str) -> String {
+    let mut stripped = String::with_capacity(contents.len());
+    let mut characters = contents.chars().peekable();
+    let mut in_string = false;
+    while let Some(character) = characters.next() {
+        if in_string {
+            stripped.push(character);
+            if character == '\\' {
+                if let Some(escaped) = characters.next() {
+                    stripped.push(escaped);
+                }
+            } else if character == '"' {
+                in_string = false;
+            }
+        } else if character == '/' && characters.peek() == Some(&'/') {
+            stripped.push(' ');
+            while let Some(comment_character) = characters.next_if(|next| *next != '\n') {
+                stripped.push(if comment_character == '\t' { '\t' } else { ' ' });
+            }
+        } else if character == '/' && characters.peek() == Some(&'*') {
+            stripped.push(' ');
+            let mut previous = ' ';
+            for comment_character in characters.by_ref() {
+                stripped.push(if comment_character == '\n' { '\n' } else { ' ' });
+                if previous == '*' && comment_character == '/' {
+                    break;
+                }
+                previous = comment_character;
+            }
+        } else {
+            in_string = character == '"';
+            stripped.push(character);
+        }
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::solipath_instructions::data::dependency::Dependency;
+
+    #[test]
+    fn format_is_detected_by_extension() {
+        assert_eq!(FileFormat::Json, FileFormat::from_path(&PathBuf::from("solipath.json")));
+        assert_eq!(FileFormat::Jsonc, FileFormat::from_path(&PathBuf::from("solipath.jsonc")));
+        assert_eq!(FileFormat::Toml, FileFormat::from_path(&PathBuf::from("install_instructions.toml")));
+        assert_eq!(FileFormat::Yaml, FileFormat::from_path(&PathBuf::from("solipath.yml")));
+    }
+
+    #[test]
+    fn file_names_cover_every_extension() {
+        assert_eq!(
+            vec!["template.json", "template.jsonc", "template.toml", "template.yaml", "template.yml"],
+            get_file_names("template")
+        );
+        assert!(has_supported_extension(&PathBuf::from("java/21/install_instructions.yml")));
+        assert!(!has_supported_extension(&PathBuf::from("java/21/install_instructions.json.bak")));
+    }
+
+    #[test]
+    fn every_format_parses_into_the_same_types() {
+        let expected = vec![Dependency::new("java", "21")];
+
+        let json: Vec<Dependency> = FileFormat::Json.parse(r#"[{"name": "java", "version": "21"}]"#).unwrap();
+        let jsonc: Vec<Dependency> = FileFormat::Jsonc
+            .parse("[\n  // the jdk\n  {\"name\": \"java\", /* lts */ \"version\": \"21\"}\n]")
+            .unwrap();
+        let yaml: Vec<Dependency> = FileFormat::Yaml.parse("- name: java\n  version: \"21\"\n").unwrap();
+        let toml: std::collections::HashMap<String, Vec<Dependency>> = FileFormat::Toml
+            .parse("[[dependencies]]\nname = \"java\"\nversion = \"21\"\n")
+            .unwrap();
+
+        assert_eq!(expected, json);
+        assert_eq!(expected, jsonc);
+        assert_eq!(expected, yaml);
+        assert_eq!(expected, toml["dependencies"]);
+    }
+
+    #[test]
+    fn jsonc_keeps_comment_markers_inside_strings() {
+        let value: serde_json::Value = FileFormat::Jsonc.parse(r#"{"url": "https://example.com/a//b"} // done"#).unwrap();
+        assert_eq!("https://example.com/a//b", value["url"]);
+    }
+
+    #[test]
+    fn errors_have_line_and_column() {
+        let json_error = FileFormat::Jsonc
+            .parse::<Vec<Dependency>>("[\n  // comment\n  {\"name\": }\n]")
+            .unwrap_err();
+        let toml_error = FileFormat::Toml
+            .parse::<std::collections::HashMap<String, Vec<Dependency>>>("[[dependencies]]\nname = \"java\"\nversion = \n")
+            .unwrap_err();
+        let yaml_error = FileFormat::Yaml.parse::<Vec<Dependency>>("- name: java\n  version: [\n").unwrap_err();
+
+        assert_eq!((3, 12), (json_error.line, json_error.column));
+        assert_eq!(3, toml_error.line);
+        assert!(yaml_error.line > 0 && yaml_error.column > 0);
+    }
+}
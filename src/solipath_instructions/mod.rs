@@ -1,2 +1,5 @@
 pub mod data;
+pub mod dependency_resolver;
 pub mod dependency_instructions_retriever;
+pub mod file_format;
+pub mod solipath_file_finder;
@@ -0,0 +1,187 @@
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::solipath_instructions::data::solipath_file::{Extends, SolipathFile};
+use crate::solipath_instructions::file_format::FileFormat;
+
+pub const SOLIPATH_FILE_NAMES: [&str; 5] =
+    ["solipath.json", "solipath.jsonc", "solipath.toml", "solipath.yaml", "solipath.yml"];
+
+const VCS_DIRECTORIES: [&str; 3] = [".git", ".hg", ".svn"];
+
+// looks in start_directory and then each parent, stopping at the root of a repository or of the filesystem
+pub fn find_solipath_file(start_directory: &Path) -> Option<PathBuf> {
+    for directory in start_directory.ancestors() {
+        if let Some(solipath_file) = find_solipath_file_in_directory(directory) {
+            return Some(solipath_file);
+        }
+        if VCS_DIRECTORIES.iter().any(|vcs_directory| directory.join(vcs_directory).exists()) {
+            return None;
+        }
+    }
+    None
+}
+
+pub fn find_solipath_file_in_directory(directory: &Path) -> Option<PathBuf> {
+    SOLIPATH_FILE_NAMES
+        .iter()
+        .map(|file_name| directory.join(file_name))
+        .find(|path| path.is_file())
+}
+
+// the directory holding the file is the project root, and any file it extends is merged underneath it
+pub fn load_solipath_file(path: &Path) -> Result<SolipathFile> {
+    load_solipath_file_extending(path, &mut Vec::new())
+}
+
+fn load_solipath_file_extending(path: &Path, files_in_progress: &mut Vec<PathBuf>) -> Result<SolipathFile> {
+    let file_contents =
+        read_to_string(path).with_context(|| format!("failed to read solipath file {}", path.display()))?;
+    // canonical paths spot the same file reached through different relative paths
+    let canonical_path = path
+        .canonicalize()
+        .with_context(|| format!("failed to get canonical path of solipath file {}", path.display()))?;
+    if files_in_progress.contains(&canonical_path) {
+        let chain: Vec<String> = files_in_progress.iter().map(|file| file.display().to_string()).collect();
+        bail!("solipath files extend each other: {} -> {}", chain.join(" -> "), canonical_path.display());
+    }
+    let mut solipath_file: SolipathFile = FileFormat::from_path(path)
+        .parse(&file_contents)
+        .map_err(|error| anyhow!("failed to parse {}: {}", path.display(), error))?;
+    let absolute_path = std::path::absolute(path)
+        .with_context(|| format!("failed to get absolute path of solipath file {}", path.display()))?;
+    let project_root = absolute_path
+        .parent()
+        .with_context(|| format!("solipath file {} has no parent directory", path.display()))?
+        .to_path_buf();
+    solipath_file.set_project_root(project_root.clone());
+    let parent_path = match solipath_file.get_extends() {
+        None => return Ok(solipath_file),
+        Some(Extends::NearestParent) => project_root
+            .parent()
+            .and_then(find_solipath_file)
+            .with_context(|| format!("{} extends its parent, but there is no solipath file above it", path.display()))?,
+        Some(Extends::Path(parent_path)) => project_root.join(parent_path),
+    };
+    files_in_progress.push(canonical_path);
+    let parent = load_solipath_file_extending(&parent_path, files_in_progress)?;
+    files_in_progress.pop();
+    let (solipath_file, dependency_overrides) = solipath_file.extend(parent);
+    dependency_overrides.iter().for_each(|dependency_override| {
+        eprintln!(
+            "warning: {} overrides {} {} from {} with version {}",
+            path.display(),
+            dependency_override.name,
+            dependency_override.parent_version,
+            parent_path.display(),
+            dependency_override.version
+        )
+    });
+    Ok(solipath_file)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, write};
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::solipath_instructions::data::dependency::Dependency;
+
+    #[test]
+    fn finds_file_in_parent_directory() {
+        let temp_dir = tempdir().unwrap();
+        let subproject = temp_dir.path().join("services/api");
+        create_dir_all(&subproject).unwrap();
+        write(temp_dir.path().join("solipath.yaml"), "[]").unwrap();
+
+        assert_eq!(Some(temp_dir.path().join("solipath.yaml")), find_solipath_file(&subproject));
+    }
+
+    #[test]
+    fn stops_at_repository_root() {
+        let temp_dir = tempdir().unwrap();
+        let repository = temp_dir.path().join("repository");
+        create_dir_all(repository.join(".git")).unwrap();
+        create_dir_all(repository.join("subproject")).unwrap();
+        write(temp_dir.path().join("solipath.json"), "[]").unwrap();
+
+        assert_eq!(None, find_solipath_file(&repository.join("subproject")));
+    }
+
+    #[test]
+    fn loaded_file_knows_its_project_root() {
+        let temp_dir = tempdir().unwrap();
+        let solipath_file_path = temp_dir.path().join("solipath.json");
+        write(&solipath_file_path, r#"[{"name": "java", "version": "21"}]"#).unwrap();
+
+        let solipath_file = load_solipath_file(&solipath_file_path).unwrap();
+
+        assert_eq!(Some(&temp_dir.path().to_path_buf()), solipath_file.get_project_root());
+    }
+
+    #[test]
+    fn nested_file_extends_its_parent() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().to_path_buf();
+        let service = root.join("services/api");
+        create_dir_all(&service).unwrap();
+        write(
+            root.join("solipath.json"),
+            r#"{
+                "dependencies": [{"name": "java", "version": "17"}, {"name": "node", "version": "20"}],
+                "env": {"SCRIPTS": {"relative_path": "scripts"}},
+                "env_files": [".env"],
+                "scripts": {"build": "gradle build", "lint": "npm run lint"}
+            }"#,
+        )
+        .unwrap();
+        write(
+            service.join("solipath.yaml"),
+            "extends: true\ndependencies:\n  - name: java\n    version: \"21\"\n  - name: python\n    version: \"3.12\"\nscripts:\n  build: pip install .\n",
+        )
+        .unwrap();
+
+        let solipath_file = load_solipath_file(&service.join("solipath.yaml")).unwrap();
+
+        assert_eq!(
+            &vec![Dependency::new("java", "21"), Dependency::new("node", "20"), Dependency::new("python", "3.12")],
+            solipath_file.get_dependencies()
+        );
+        assert_eq!(&vec!["pip install .".to_string()], solipath_file.get_scripts()["build"].get_commands());
+        assert_eq!(&vec!["npm run lint".to_string()], solipath_file.get_scripts()["lint"].get_commands());
+        assert_eq!(
+            &Some(root.join("scripts").to_string_lossy().to_string()),
+            solipath_file.get_environment_variables()[0].get_relative_path()
+        );
+        assert_eq!(&vec![root.join(".env").to_string_lossy().to_string()], solipath_file.get_env_files());
+        assert_eq!(Some(&service), solipath_file.get_project_root());
+    }
+
+    #[test]
+    fn files_extending_each_other_are_an_error() {
+        let temp_dir = tempdir().unwrap();
+        write(temp_dir.path().join("first.json"), r#"{"extends": "second.json"}"#).unwrap();
+        write(temp_dir.path().join("second.json"), r#"{"extends": "first.json"}"#).unwrap();
+
+        let error = load_solipath_file(&temp_dir.path().join("first.json")).unwrap_err();
+
+        assert!(error.to_string().starts_with("solipath files extend each other"));
+    }
+
+    #[test]
+    fn missing_parent_is_an_error() {
+        let temp_dir = tempdir().unwrap();
+        let service = temp_dir.path().join("services/api");
+        create_dir_all(temp_dir.path().join("services/.git")).unwrap();
+        create_dir_all(&service).unwrap();
+        write(service.join("solipath.json"), r#"{"extends": true}"#).unwrap();
+
+        let error = load_solipath_file(&service.join("solipath.json")).unwrap_err();
+
+        assert!(error.to_string().ends_with("extends its parent, but there is no solipath file above it"));
+    }
+}
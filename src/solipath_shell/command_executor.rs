@@ -1,24 +1,110 @@
 #[cfg(test)]
 use mockall::automock;
-use std::process::Command;
+use std::fs::{create_dir_all, File};
+use std::future::Future;
+use std::path::Path;
+use std::process::{Child, Command};
 use std::process::ExitStatus;
 use std::process::Stdio;
+use std::sync::Mutex;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+
+use crate::solipath_environment_variable::resolved_environment::ResolvedEnvironment;
 
 #[cfg_attr(test, automock)]
 pub trait CommandExecutorTrait {
-    fn execute_command(&self, commands: &[String]) -> ExitStatus;
-    fn execute_single_string_command(&self, command: String) -> ExitStatus;
+    fn execute_command(&self, commands: &[String], environment: &ResolvedEnvironment) -> ExitStatus;
+    fn execute_single_string_command(&self, command: String, environment: &ResolvedEnvironment) -> ExitStatus;
+    fn execute_logged_single_string_command(
+        &self,
+        command: String,
+        environment: &ResolvedEnvironment,
+        log_file: &Path,
+        timeout: Option<Duration>,
+    ) -> Result<ExitStatus>;
+}
+
+// install commands run in their own process group, so a ctrl-c in the terminal doesn't reach them. their groups are
+// kept here so they can be stopped when solipath is interrupted
+pub struct RunningCommands {
+    process_ids: Mutex<Vec<u32>>,
+}
+
+impl RunningCommands {
+    pub const fn new() -> Self {
+        Self { process_ids: Mutex::new(Vec::new()) }
+    }
+
+    fn add(&self, process_id: u32) {
+        self.process_ids.lock().unwrap().push(process_id);
+    }
+
+    fn remove(&self, process_id: u32) {
+        self.process_ids.lock().unwrap().retain(|running_id| *running_id != process_id);
+    }
+
+    pub fn stop_all(&self) {
+        let process_ids: Vec<u32> = self.process_ids.lock().unwrap().drain(..).collect();
+        process_ids.into_iter().for_each(|process_id| {
+            kill_process_tree(process_id);
+        });
+    }
+}
+
+impl Default for RunningCommands {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub static RUNNING_COMMANDS: RunningCommands = RunningCommands::new();
+
+// waits for ctrl-c or SIGTERM, stops the install commands that are still running and returns the exit code solipath
+// would have had if it was killed by the signal. the signals are caught from the call, not from the first poll
+pub fn stop_running_commands_when_interrupted(running_commands: &'static RunningCommands) -> impl Future<Output = i32> {
+    #[cfg(unix)]
+    let interrupted = {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut interrupt = signal(SignalKind::interrupt()).expect("failed to listen for SIGINT");
+        let mut terminate = signal(SignalKind::terminate()).expect("failed to listen for SIGTERM");
+        async move {
+            tokio::select! {
+                _ = interrupt.recv() => 130,
+                _ = terminate.recv() => 143,
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let interrupted = async {
+        let _ = tokio::signal::ctrl_c().await;
+        130
+    };
+    async move {
+        let exit_code = interrupted.await;
+        running_commands.stop_all();
+        exit_code
+    }
 }
 
-pub struct CommandExecutor;
+pub struct CommandExecutor {
+    running_commands: &'static RunningCommands,
+}
 
 impl CommandExecutor {
     pub fn new() -> Self {
-        Self {}
+        Self::new_with_running_commands(&RUNNING_COMMANDS)
     }
 
-    pub fn setup_command(&self, commands: &[String]) -> Command {
-        if std::env::consts::OS == "windows" {
+    pub fn new_with_running_commands(running_commands: &'static RunningCommands) -> Self {
+        Self { running_commands }
+    }
+
+    // programs are looked up on the PATH of the resolved environment, not the one solipath was started with
+    pub fn setup_command(&self, commands: &[String], environment: &ResolvedEnvironment) -> Command {
+        let mut command = if std::env::consts::OS == "windows" {
             let mut command = Command::new("cmd");
             command.arg("/C").args(commands);
             command
@@ -26,11 +112,13 @@ impl CommandExecutor {
             let mut command = Command::new(commands.get(0).expect("expected at least one command!"));
             command.args(&commands[1..]);
             command
-        }
+        };
+        environment.apply_to(&mut command);
+        command
     }
 
-    pub fn setup_single_string_command(&self, commands: &String) -> Command {
-        if std::env::consts::OS == "windows" {
+    pub fn setup_single_string_command(&self, commands: &String, environment: &ResolvedEnvironment) -> Command {
+        let mut command = if std::env::consts::OS == "windows" {
             let mut command = Command::new("cmd");
             command.arg("/C").arg(commands);
             command
@@ -38,7 +126,9 @@ impl CommandExecutor {
             let mut command = Command::new("bash");
             command.arg("-c").arg(commands);
             command
-        }
+        };
+        environment.apply_to(&mut command);
+        command
     }
 
     pub fn run_command(&self, command: &mut Command) -> ExitStatus{
@@ -48,37 +138,151 @@ impl CommandExecutor {
             .status()
             .unwrap_or_else(|error| panic!("failed to execute the command: {:?}, error: {}", command, error))
     }
+
+    // stdout and stderr both go to the log file, a command still running when the timeout passes is killed
+    pub fn run_logged_command(&self, command: &mut Command, log_file: &Path, timeout: Option<Duration>) -> Result<ExitStatus> {
+        if let Some(log_directory) = log_file.parent() {
+            create_dir_all(log_directory)?;
+        }
+        let log = File::create(log_file).with_context(|| format!("failed to create log file {}", log_file.display()))?;
+        // the command runs in its own process group, so a timeout stops what bash or cmd started as well
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(command, 0);
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(log.try_clone()?)
+            .stderr(log)
+            .spawn()
+            .with_context(|| format!("failed to execute the command: {:?}", command))?;
+        self.running_commands.add(child.id());
+        let exit_status = self.wait_for_logged_command(&mut child, timeout);
+        self.running_commands.remove(child.id());
+        exit_status
+    }
+
+    fn wait_for_logged_command(&self, child: &mut Child, timeout: Option<Duration>) -> Result<ExitStatus> {
+        let Some(timeout) = timeout else {
+            return Ok(child.wait()?);
+        };
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(exit_status) = child.try_wait()? {
+                return Ok(exit_status);
+            }
+            if Instant::now() >= deadline {
+                if !kill_process_tree(child.id()) {
+                    let _ = child.kill();
+                }
+                child.wait()?;
+                bail!("timed out after {} seconds", timeout.as_secs());
+            }
+            sleep(Duration::from_millis(50));
+        }
+    }
+}
+
+// prefixed to a single string command with &&, so the command runs in the directory
+pub fn change_directory_command(directory: &Path) -> String {
+    if std::env::consts::OS == "windows" {
+        format!("cd /d {}", quote_windows_argument(directory.to_str().unwrap()))
+    } else {
+        format!("cd {}", quote_argument(directory.to_str().unwrap()))
+    }
+}
+
+pub fn quote_argument(argument: &str) -> String {
+    if std::env::consts::OS == "windows" {
+        quote_windows_argument(argument)
+    } else {
+        format!("'{}'", argument.replace('\'', r"'\''"))
+    }
+}
+
+// windows programs split their own arguments: quotes are escaped with a backslash, and backslashes are only doubled
+// when they come before a quote
+pub fn quote_windows_argument(argument: &str) -> String {
+    if !argument.is_empty() && !argument.contains([' ', '\t', '"']) {
+        return argument.to_string();
+    }
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for character in argument.chars() {
+        match character {
+            '\\' => backslashes += 1,
+            '"' => {
+                quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                quoted.push('"');
+                backslashes = 0;
+            }
+            _ => {
+                quoted.push_str(&"\\".repeat(backslashes));
+                quoted.push(character);
+                backslashes = 0;
+            }
+        }
+    }
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+fn kill_process_tree(process_id: u32) -> bool {
+    let killed = if std::env::consts::OS == "windows" {
+        Command::new("taskkill").args(["/T", "/F", "/PID", &process_id.to_string()]).output()
+    } else {
+        Command::new("kill").args(["-s", "KILL", "--", &format!("-{}", process_id)]).output()
+    };
+    killed.map(|output| output.status.success()).unwrap_or(false)
 }
 
 #[cfg_attr(test, automock)]
 impl CommandExecutorTrait for CommandExecutor {
-    fn execute_command(&self, commands: &[String]) -> ExitStatus{
-        self.run_command(&mut self.setup_command(commands))
+    fn execute_command(&self, commands: &[String], environment: &ResolvedEnvironment) -> ExitStatus{
+        self.run_command(&mut self.setup_command(commands, environment))
+    }
+
+    fn execute_single_string_command(&self, command: String, environment: &ResolvedEnvironment) -> ExitStatus{
+        self.run_command(&mut self.setup_single_string_command(&command, environment))
     }
 
-    fn execute_single_string_command(&self, command: String) -> ExitStatus{
-        self.run_command(&mut self.setup_single_string_command(&command))
+    fn execute_logged_single_string_command(
+        &self,
+        command: String,
+        environment: &ResolvedEnvironment,
+        log_file: &Path,
+        timeout: Option<Duration>,
+    ) -> Result<ExitStatus> {
+        self.run_logged_command(&mut self.setup_single_string_command(&command, environment), log_file, timeout)
     }
 }
 
 #[cfg(test)]
 pub mod pub_test{
+    use std::path::Path;
     use std::process::ExitStatus;
+    use std::time::Duration;
+
+    use std::sync::Mutex;
 
     use crossbeam::channel::{unbounded, Receiver, Sender};
 
     use super::CommandExecutorTrait;
+    use crate::solipath_environment_variable::resolved_environment::ResolvedEnvironment;
 
     
     pub struct MockCommandExecutor{
         commands_sender: Sender<String>,
-        commands_receiver: Receiver<String>
+        commands_receiver: Receiver<String>,
+        last_environment: Mutex<Option<ResolvedEnvironment>>,
     }
 
     impl MockCommandExecutor {
         pub fn new()-> Self {
             let (commands_sender, commands_receiver): (Sender<String>, Receiver<String>) = unbounded();
-            MockCommandExecutor{commands_sender, commands_receiver}
+            MockCommandExecutor{commands_sender, commands_receiver, last_environment: Mutex::new(None)}
+        }
+        pub fn get_last_environment(&self)-> ResolvedEnvironment {
+            self.last_environment.lock().unwrap().clone().expect("no command was executed")
         }
         pub fn get_commands(&self)-> Vec<String> {
             let length = self.commands_receiver.len();
@@ -91,15 +295,27 @@ pub mod pub_test{
     }
 
     impl CommandExecutorTrait for MockCommandExecutor {
-        fn execute_command(&self,commands: &[String]) -> ExitStatus {
+        fn execute_command(&self,commands: &[String], environment: &ResolvedEnvironment) -> ExitStatus {
+            *self.last_environment.lock().unwrap() = Some(environment.clone());
             self.commands_sender.send(commands.join(" ")).unwrap();
             ExitStatus::default()
         }
     
-        fn execute_single_string_command(&self,command:String) -> ExitStatus {
+        fn execute_single_string_command(&self,command:String, environment: &ResolvedEnvironment) -> ExitStatus {
+            *self.last_environment.lock().unwrap() = Some(environment.clone());
             self.commands_sender.send(command).unwrap();
             ExitStatus::default()
         }
+
+        fn execute_logged_single_string_command(
+            &self,
+            command: String,
+            environment: &ResolvedEnvironment,
+            _log_file: &Path,
+            _timeout: Option<Duration>,
+        ) -> anyhow::Result<ExitStatus> {
+            Ok(self.execute_single_string_command(command, environment))
+        }
     }
 }
 
@@ -107,10 +323,28 @@ pub mod pub_test{
 mod test {
     use super::*;
 
+    #[test]
+    fn windows_arguments_escape_quotes() {
+        assert_eq!("--info", quote_windows_argument("--info"));
+        assert_eq!(r#""two words""#, quote_windows_argument("two words"));
+        assert_eq!(r#""say \"hi\"""#, quote_windows_argument(r#"say "hi""#));
+        assert_eq!(r#""C:\dir\\\"quoted\" C:\trailing\\""#, quote_windows_argument(r#"C:\dir\"quoted" C:\trailing\"#));
+        assert_eq!(r#""""#, quote_windows_argument(""));
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn change_directory_command_escapes_quotes() {
+        assert_eq!(r#"cd '/projects/it'\''s "here"'"#, change_directory_command(Path::new(r#"/projects/it's "here""#)));
+    }
+
     #[test]
     fn run_command() {
         let command_executor = CommandExecutor::new();
-        let mut command = command_executor.setup_command(&vec!["echo".to_string(), "the test worked!!!".to_string()]);
+        let mut command = command_executor.setup_command(
+            &vec!["echo".to_string(), "the test worked!!!".to_string()],
+            &ResolvedEnvironment::from_current_process(),
+        );
         let output = command.stdout(Stdio::piped()).output().expect("failed to run command");
         if std::env::consts::OS == "windows" {
             assert_eq!(String::from_utf8_lossy(&output.stdout), "\"the test worked!!!\"\r\n");
@@ -124,13 +358,125 @@ mod test {
     fn run_single_string_command() {
         let command_executor = CommandExecutor::new();
         if std::env::consts::OS == "windows" {
-            let mut command = command_executor.setup_single_string_command(&"cd tests && dir /b".to_string());
+            let mut command = command_executor.setup_single_string_command(&"cd tests && dir /b".to_string(), &ResolvedEnvironment::from_current_process());
             let output = command.stdout(Stdio::piped()).output().expect("failed to run command");
             assert_eq!(String::from_utf8_lossy(&output.stdout), "mod.rs\r\nresources\r\n");
         } else {
-            let mut command = command_executor.setup_single_string_command(&"cd tests && ls".to_string());
+            let mut command = command_executor.setup_single_string_command(&"cd tests && ls".to_string(), &ResolvedEnvironment::from_current_process());
             let output = command.stdout(Stdio::piped()).output().expect("failed to run command");
             assert_eq!(String::from_utf8_lossy(&output.stdout), "mod.rs\nresources\n");
         }
     }
+
+    #[test]
+    fn logged_command_output_goes_to_the_log_file() {
+        let command_executor = CommandExecutor::new();
+        let log_file = tempfile::tempdir().unwrap().into_path().join("logs").join("install.log");
+        let exit_status = command_executor
+            .execute_logged_single_string_command(
+                "echo to stdout && echo to stderr 1>&2 && exit 3".to_string(),
+                &ResolvedEnvironment::from_current_process(),
+                &log_file,
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(Some(3), exit_status.code());
+        let log = std::fs::read_to_string(&log_file).unwrap();
+        assert!(log.contains("to stdout"));
+        assert!(log.contains("to stderr"));
+    }
+
+    #[test]
+    fn logged_command_is_stopped_after_the_timeout() {
+        let command_executor = CommandExecutor::new();
+        let log_file = tempfile::tempdir().unwrap().into_path().join("install.log");
+        let sleep_command = if std::env::consts::OS == "windows" { "ping -n 30 127.0.0.1" } else { "sleep 30" };
+        let started = Instant::now();
+        let error = command_executor
+            .execute_logged_single_string_command(
+                sleep_command.to_string(),
+                &ResolvedEnvironment::from_current_process(),
+                &log_file,
+                Some(Duration::from_secs(1)),
+            )
+            .unwrap_err();
+
+        assert_eq!("timed out after 1 seconds", error.to_string());
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn timeout_stops_the_processes_the_command_started() {
+        let command_executor = CommandExecutor::new();
+        let directory = tempfile::tempdir().unwrap().into_path();
+        let pid_file = directory.join("pid");
+        command_executor
+            .execute_logged_single_string_command(
+                format!("echo $$ > {} && cd . && sleep 30 && echo done", pid_file.display()),
+                &ResolvedEnvironment::from_current_process(),
+                &directory.join("install.log"),
+                Some(Duration::from_secs(1)),
+            )
+            .unwrap_err();
+
+        let process_group = format!("-{}", std::fs::read_to_string(&pid_file).unwrap().trim());
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let is_running = || {
+            Command::new("kill")
+                .args(["-0", "--", &process_group])
+                .stderr(Stdio::null())
+                .status()
+                .unwrap()
+                .success()
+        };
+        while is_running() && Instant::now() < deadline {
+            sleep(Duration::from_millis(50));
+        }
+        assert!(!is_running());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn interrupting_solipath_stops_the_running_install_commands() {
+        static RUNNING_COMMANDS: RunningCommands = RunningCommands::new();
+        let interrupted = tokio::spawn(stop_running_commands_when_interrupted(&RUNNING_COMMANDS));
+        let directory = tempfile::tempdir().unwrap().into_path();
+        let pid_file = directory.join("pid");
+        let command = format!("echo $$ > {} && cd . && sleep 30 && echo done", pid_file.display());
+        let log_file = directory.join("install.log");
+        let started = Instant::now();
+        let install = std::thread::spawn(move || {
+            CommandExecutor::new_with_running_commands(&RUNNING_COMMANDS).execute_logged_single_string_command(
+                command,
+                &ResolvedEnvironment::from_current_process(),
+                &log_file,
+                None,
+            )
+        });
+        while !pid_file.exists() || std::fs::read_to_string(&pid_file).unwrap().trim().is_empty() {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        Command::new("kill").args(["-s", "TERM", &std::process::id().to_string()]).status().unwrap();
+
+        assert_eq!(143, interrupted.await.unwrap());
+        assert!(!install.join().unwrap().unwrap().success());
+        assert!(started.elapsed() < Duration::from_secs(10));
+        let process_group = format!("-{}", std::fs::read_to_string(&pid_file).unwrap().trim());
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let is_running = || {
+            Command::new("kill")
+                .args(["-0", "--", &process_group])
+                .stderr(Stdio::null())
+                .status()
+                .unwrap()
+                .success()
+        };
+        while is_running() && Instant::now() < deadline {
+            sleep(Duration::from_millis(50));
+        }
+        assert!(!is_running());
+    }
 }
@@ -1,16 +1,20 @@
 #[cfg(test)]
 use mockall::automock;
-use std::fs::create_dir_all;
-use std::path::PathBuf;
+use std::fs::{create_dir_all, read_to_string};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use anyhow::{anyhow, Context, Result};
+
+use crate::solipath_environment_variable::resolved_environment::ResolvedEnvironment;
+use crate::solipath_instructions::data::environment_variable::EnvironmentVariableOperation;
 use crate::solipath_instructions::data::dependency::Dependency;
-use crate::{solipath_shell::command_executor::CommandExecutorTrait, solipath_directory::solipath_directory_finder::SolipathDirectoryFinderTrait, solipath_instructions::data::install_command::InstallCommand};
+use crate::{solipath_shell::command_executor::{change_directory_command, CommandExecutorTrait}, solipath_directory::solipath_directory_finder::SolipathDirectoryFinderTrait, solipath_instructions::data::install_command::InstallCommand};
 use crate::solipath_shell::install_command_filter::InstallCommandFilterTrait;
 
 #[cfg_attr(test, automock)]
 pub trait InstallCommandExecutorTrait{
-    fn execute_command(&self, dependency: &Dependency, install_command: &InstallCommand);
+    fn execute_command(&self, dependency: &Dependency, install_command: &InstallCommand, environment: &ResolvedEnvironment) -> Result<()>;
 }
 
 pub struct InstallCommandExecutor{
@@ -29,63 +33,112 @@ impl InstallCommandExecutor {
     }
 }
 
-impl InstallCommandExecutorTrait for InstallCommandExecutor {
-    fn execute_command(&self, dependency: &Dependency, install_command: &InstallCommand) {
-        if self.install_command_filter.command_should_be_run(dependency, install_command.get_when_to_run_rules()) {
-            let downloads_directory = self.directory_finder.get_dependency_downloads_directory(dependency);
-            create_dir_all(&downloads_directory).expect("failed to create downloads directory");
+impl InstallCommandExecutor {
+    fn get_log_file(&self, dependency: &Dependency, install_command: &InstallCommand)-> PathBuf {
+        let mut log_file = self.directory_finder.get_dependency_version_directory(dependency);
+        log_file.push("install_logs");
+        log_file.push(format!("{}.log", install_command.get_id()));
+        log_file
+    }
+
+    fn run_command(&self, dependency: &Dependency, install_command: &InstallCommand, environment: &ResolvedEnvironment) -> Result<()> {
+        let mut working_directory = self.directory_finder.get_dependency_downloads_directory(dependency);
+        create_dir_all(&working_directory).expect("failed to create downloads directory");
+        if let Some(relative_directory) = install_command.get_working_directory() {
+            working_directory.push(relative_directory);
+        }
+        let mut environment = environment.clone();
+        for (name, value) in install_command.get_env() {
+            environment.apply(name, EnvironmentVariableOperation::Set, Some(value.into()));
+        }
+
+        let log_file = self.get_log_file(dependency, install_command);
+        let command_string = format!("{} && {}", change_directory_command(&working_directory), install_command.get_command());
+        let result = self.command_executor
+            .execute_logged_single_string_command(command_string, &environment, &log_file, install_command.get_timeout())
+            .and_then(|exit_status| if exit_status.success() {
+                Ok(())
+            } else {
+                Err(anyhow!("exited with {}", exit_status))
+            });
+        if result.is_err() {
+            replay_log(&log_file);
+        }
+        result.with_context(|| format!(
+            "install command '{}' for {} {} failed, its output is in {}",
+            install_command.get_command(), dependency.name, dependency.version, log_file.display()
+        ))
+    }
+}
 
-            let command_string = format!("{} && {}", switch_to_download_directory_command(&downloads_directory), install_command.get_command());
-            self.command_executor.execute_single_string_command(command_string);
+impl InstallCommandExecutorTrait for InstallCommandExecutor {
+    fn execute_command(&self, dependency: &Dependency, install_command: &InstallCommand, environment: &ResolvedEnvironment) -> Result<()> {
+        let should_run = self.install_command_filter
+            .command_should_be_run(dependency, install_command, environment)
+            .with_context(|| format!("invalid install command '{}' for {} {}", install_command.get_command(), dependency.name, dependency.version))?;
+        if should_run {
+            self.run_command(dependency, install_command, environment)?;
+            self.install_command_filter.record_successful_run(dependency, install_command)?;
         }
+        Ok(())
     }
 }
 
-fn switch_to_download_directory_command(downloads_directory: &PathBuf)-> String {
-    if std::env::consts::OS == "windows" {
-        format!("cd /d {}", downloads_directory.to_str().unwrap())
-    } else {
-        format!("cd \"{}\"", downloads_directory.display())
+fn replay_log(log_file: &Path) {
+    if let Ok(output) = read_to_string(log_file) {
+        eprint!("{}", output);
     }
 }
 
 
+
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
     use std::process::ExitStatus;
 
     use mockall::predicate::eq;
+    use tempfile::tempdir;
 
     use super::*;
-    use crate::solipath_shell::command_executor::MockCommandExecutorTrait;
+    use crate::solipath_shell::command_executor::{CommandExecutor, MockCommandExecutorTrait};
     use crate::solipath_directory::solipath_directory_finder::MockSolipathDirectoryFinderTrait;
-    use crate::solipath_shell::install_command_filter::MockInstallCommandFilterTrait;
+    use crate::solipath_shell::install_command_filter::{InstallCommandFilter, MockInstallCommandFilterTrait};
 
 
     #[test]
     fn run_command_if_rules_pass(){
+        let environment = ResolvedEnvironment::new(vec![("JAVA_HOME".into(), "/jdk".into())]);
         let dependency = Dependency::new("depend", "version");
-        let mut command_rules = HashMap::new();
-        command_rules.insert("file_does_not_exist".to_string(), serde_json::Value::String("thefile".to_string()));
         let mut command_filter = MockInstallCommandFilterTrait::new();
         command_filter.expect_command_should_be_run()
-            .with(eq(dependency.clone()), eq(command_rules))
-            .return_const(true);
+            .withf(|dependency, install_command, _| dependency.name == "depend" && install_command.get_when_to_run_rules().contains_key("file_does_not_exist"))
+            .returning(|_, _, _| Ok(true));
+        command_filter.expect_record_successful_run()
+            .times(1)
+            .returning(|_, _| Ok(()));
         let mut directory_finder = MockSolipathDirectoryFinderTrait::new();
         directory_finder.expect_get_dependency_downloads_directory()
             .with(eq(dependency.clone()))
             .return_const("downloads_directory");
+        directory_finder.expect_get_dependency_version_directory()
+            .with(eq(dependency.clone()))
+            .return_const("version_directory");
         let mut command_executor = MockCommandExecutorTrait::new();
         
-        let command_expectation = command_executor.expect_execute_single_string_command();
-        if std::env::consts::OS == "windows" {
-            command_expectation.with(eq("cd /d downloads_directory && do something".to_string()))
-            .return_const(ExitStatus::default());
+        let expected_command = if std::env::consts::OS == "windows" {
+            "cd /d downloads_directory && do something"
         } else {
-            command_expectation.with(eq("cd \"downloads_directory\" && do something".to_string()))
-            .return_const(ExitStatus::default());
-        }
+            "cd 'downloads_directory' && do something"
+        };
+        let expected_environment = environment.clone();
+        command_executor.expect_execute_logged_single_string_command()
+            .withf(move |command, environment, log_file, timeout| {
+                command == expected_command
+                    && environment == &expected_environment
+                    && log_file.starts_with("version_directory/install_logs")
+                    && timeout.is_none()
+            })
+            .returning(|_, _, _, _| Ok(ExitStatus::default()));
         let install_command_executor = InstallCommandExecutor::new(
             Arc::new(command_executor),
             Arc::new(command_filter),
@@ -95,18 +148,16 @@ mod tests {
             "command": "do something", 
             "when_to_run_rules": {"file_does_not_exist": "thefile"}}
         "#).expect("failed to parse string");
-        install_command_executor.execute_command(&dependency, &install_command);
+        install_command_executor.execute_command(&dependency, &install_command, &environment).unwrap();
     }
 
     #[test]
     fn do_not_run_command_if_rules_fail(){
         let dependency = Dependency::new("depend", "version");
-        let mut command_rules = HashMap::new();
-        command_rules.insert("file_does_not_exist".to_string(), serde_json::Value::String("thefile".to_string()));
         let mut command_filter = MockInstallCommandFilterTrait::new();
         command_filter.expect_command_should_be_run()
-            .with(eq(dependency.clone()), eq(command_rules))
-            .return_const(false);
+            .withf(|dependency, install_command, _| dependency.name == "depend" && install_command.get_when_to_run_rules().contains_key("file_does_not_exist"))
+            .returning(|_, _, _| Ok(false));
         let directory_finder = MockSolipathDirectoryFinderTrait::new();
         let command_executor = MockCommandExecutorTrait::new();    
 
@@ -119,6 +170,84 @@ mod tests {
             "command": "do something", 
             "when_to_run_rules": {"file_does_not_exist": "thefile"}}
         "#).expect("failed to parse string");
-        install_command_executor.execute_command(&dependency, &install_command);
+        install_command_executor.execute_command(&dependency, &install_command, &ResolvedEnvironment::new(Vec::new())).unwrap();
+    }
+
+    #[test]
+    fn unknown_rules_are_an_error_naming_the_dependency(){
+        let dependency = Dependency::new("depend", "version");
+        let install_command_executor = InstallCommandExecutor::new(
+            Arc::new(MockCommandExecutorTrait::new()),
+            Arc::new(InstallCommandFilter::new(Arc::new(MockSolipathDirectoryFinderTrait::new()))),
+            Arc::new(MockSolipathDirectoryFinderTrait::new())
+        );
+        let install_command: InstallCommand = serde_json::from_str(r#"{
+            "command": "do something",
+            "when_to_run_rules": {"nonexistent_filter": "something"}}
+        "#).expect("failed to parse string");
+        let error = install_command_executor
+            .execute_command(&dependency, &install_command, &ResolvedEnvironment::new(Vec::new()))
+            .unwrap_err();
+        assert!(format!("{:#}", error).starts_with(
+            "invalid install command 'do something' for depend version: unrecognized when_to_run_rules entry 'nonexistent_filter'"
+        ));
     }
-}
\ No newline at end of file
+
+    fn install_command_executor_in(downloads_directory: &Path, dependency: &Dependency)-> InstallCommandExecutor {
+        let mut command_filter = MockInstallCommandFilterTrait::new();
+        command_filter.expect_command_should_be_run().returning(|_, _, _| Ok(true));
+        command_filter.expect_record_successful_run().returning(|_, _| Ok(()));
+        let mut directory_finder = MockSolipathDirectoryFinderTrait::new();
+        directory_finder.expect_get_dependency_downloads_directory()
+            .with(eq(dependency.clone()))
+            .return_const(downloads_directory.join("downloads"));
+        directory_finder.expect_get_dependency_version_directory()
+            .with(eq(dependency.clone()))
+            .return_const(downloads_directory.to_path_buf());
+        InstallCommandExecutor::new(
+            Arc::new(CommandExecutor::new()),
+            Arc::new(command_filter),
+            Arc::new(directory_finder)
+        )
+    }
+
+    #[test]
+    fn failed_commands_are_an_error_naming_the_dependency_and_keep_their_output(){
+        let dependency = Dependency::new("depend", "version");
+        let version_directory = tempdir().unwrap().into_path();
+        let install_command_executor = install_command_executor_in(&version_directory, &dependency);
+        let install_command: InstallCommand = serde_json::from_str(r#"{"command": "echo broken toolchain && exit 3"}"#).unwrap();
+
+        let error = install_command_executor
+            .execute_command(&dependency, &install_command, &ResolvedEnvironment::from_current_process())
+            .unwrap_err();
+
+        assert!(error.to_string().starts_with("install command 'echo broken toolchain && exit 3' for depend version failed"));
+        let log_file = version_directory.join("install_logs").join(format!("{}.log", install_command.get_id()));
+        assert!(read_to_string(log_file).unwrap().contains("broken toolchain"));
+    }
+
+    #[test]
+    fn commands_run_in_their_working_directory_with_their_env(){
+        let dependency = Dependency::new("depend", "version");
+        let version_directory = tempdir().unwrap().into_path();
+        create_dir_all(version_directory.join("downloads").join("bin")).unwrap();
+        let install_command_executor = install_command_executor_in(&version_directory, &dependency);
+        let command = if std::env::consts::OS == "windows" {
+            "echo %GREETING% > greeting.txt"
+        } else {
+            "echo $GREETING > greeting.txt"
+        };
+        let install_command: InstallCommand = serde_json::from_str(&format!(
+            r#"{{"command": "{}", "working_directory": "bin", "env": {{"GREETING": "hello"}}, "timeout_seconds": 30}}"#,
+            command
+        )).unwrap();
+
+        install_command_executor
+            .execute_command(&dependency, &install_command, &ResolvedEnvironment::from_current_process())
+            .unwrap();
+
+        let greeting = read_to_string(version_directory.join("downloads").join("bin").join("greeting.txt")).unwrap();
+        assert_eq!("hello", greeting.trim());
+    }
+}
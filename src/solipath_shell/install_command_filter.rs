@@ -1,13 +1,24 @@
 #[cfg(test)]
 use mockall::automock;
 
-use std::{collections::HashMap, sync::Arc};
+use std::ffi::OsStr;
+use std::fs::{create_dir_all, read_dir, write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use crate::solipath_environment_variable::resolved_environment::ResolvedEnvironment;
+use crate::solipath_execute::command_locator::find_command_in_path;
 use crate::solipath_instructions::data::dependency::Dependency;
+use crate::solipath_instructions::data::install_command::InstallCommand;
+use crate::solipath_instructions::data::when_to_run_rule::WhenToRunRule;
 use crate::solipath_directory::solipath_directory_finder::SolipathDirectoryFinderTrait;
 
 #[cfg_attr(test, automock)]
 pub trait InstallCommandFilterTrait{
-    fn command_should_be_run(&self, dependency: &Dependency, when_to_run_rules: &HashMap<String,  serde_json::Value>)-> bool;
+    fn command_should_be_run(&self, dependency: &Dependency, install_command: &InstallCommand, environment: &ResolvedEnvironment)-> Result<bool>;
+    fn record_successful_run(&self, dependency: &Dependency, install_command: &InstallCommand)-> Result<()>;
 }
 
 pub struct InstallCommandFilter{
@@ -17,31 +28,58 @@ impl InstallCommandFilter{
     pub fn new(directory_finder: Arc<dyn SolipathDirectoryFinderTrait + Sync + Send>)-> Self{
         Self{directory_finder}
     }
-    fn check_single_rule(&self, dependency: &Dependency, rule_name: &String, rule_value: &serde_json::Value)-> bool {
-        match rule_name.as_str() {
-            "file_does_not_exist" => self.check_file_does_not_exist(dependency, rule_value.as_str().expect("file_does_exist should be a string")),
-            _ => panic!("unrecognized command filter option '{}' in '{}' dependency!", rule_name, dependency.name)
+    fn check_rule(&self, dependency: &Dependency, install_command: &InstallCommand, environment: &ResolvedEnvironment, rule: &WhenToRunRule)-> bool {
+        match rule {
+            WhenToRunRule::FileExists(relative_path) => self.get_downloads_path(dependency, relative_path).exists(),
+            WhenToRunRule::FileDoesNotExist(relative_path) => !self.get_downloads_path(dependency, relative_path).exists(),
+            WhenToRunRule::DirectoryEmpty(relative_path) => read_dir(self.get_downloads_path(dependency, relative_path))
+                .map(|mut entries| entries.next().is_none())
+                .unwrap_or(true),
+            WhenToRunRule::EnvVarSet(name) => environment.get(name).is_some(),
+            WhenToRunRule::EnvVarEquals { name, value } => environment.get(name) == Some(OsStr::new(value)),
+            WhenToRunRule::CommandNotOnPath(command) => {
+                find_command_in_path(command, environment.get("PATH").unwrap_or_default()).is_empty()
+            }
+            WhenToRunRule::Once => !self.get_once_stamp_file(dependency, install_command).exists(),
+            WhenToRunRule::AnyOf(rules) => rules.iter().any(|rule| self.check_rule(dependency, install_command, environment, rule)),
+            WhenToRunRule::AllOf(rules) => rules.iter().all(|rule| self.check_rule(dependency, install_command, environment, rule)),
+            WhenToRunRule::Not(rule) => !self.check_rule(dependency, install_command, environment, rule),
         }
     }
-    fn check_file_does_not_exist(&self, dependency: &Dependency, relative_file_path: &str)-> bool {
+    fn get_downloads_path(&self, dependency: &Dependency, relative_path: &str)-> PathBuf {
         let mut downloads_path = self.directory_finder.get_dependency_downloads_directory(dependency);
-        downloads_path.push(relative_file_path);
-        !downloads_path.exists()
+        downloads_path.push(relative_path);
+        downloads_path
+    }
+    fn get_once_stamp_file(&self, dependency: &Dependency, install_command: &InstallCommand)-> PathBuf {
+        let mut stamp_file = self.directory_finder.get_dependency_downloads_directory(dependency);
+        stamp_file.push(".solipath_once");
+        stamp_file.push(install_command.get_id());
+        stamp_file
     }
 }
 
 impl InstallCommandFilterTrait for InstallCommandFilter{
-    fn command_should_be_run(&self, dependency: &Dependency, when_to_run_rules: &HashMap<String,  serde_json::Value>)-> bool {
-        when_to_run_rules.iter().fold(true, |should_run, (key, value)|{
-            should_run & self.check_single_rule(dependency, key, value)
-        })
+    fn command_should_be_run(&self, dependency: &Dependency, install_command: &InstallCommand, environment: &ResolvedEnvironment)-> Result<bool> {
+        let rule = install_command.get_when_to_run_rule()?;
+        Ok(self.check_rule(dependency, install_command, environment, &rule))
+    }
+
+    fn record_successful_run(&self, dependency: &Dependency, install_command: &InstallCommand)-> Result<()> {
+        if install_command.get_when_to_run_rule()?.uses_once() {
+            let stamp_file = self.get_once_stamp_file(dependency, install_command);
+            create_dir_all(stamp_file.parent().expect("stamp file has a parent directory"))?;
+            write(&stamp_file, install_command.get_command())?;
+        }
+        Ok(())
     }
 }
 
 
 #[cfg(test)]
 mod tests{
-    use std::fs::File;
+    use std::fs::{create_dir_all, File};
+    use std::path::Path;
 
     use mockall::predicate::eq;
     use tempfile::tempdir;
@@ -49,53 +87,98 @@ mod tests{
     use super::*;
     use crate::solipath_directory::solipath_directory_finder::MockSolipathDirectoryFinderTrait;
 
+    fn install_command(when_to_run_rules: &str)-> InstallCommand {
+        serde_json::from_str(&format!(r#"{{"command": "do something", "when_to_run_rules": {}}}"#, when_to_run_rules)).unwrap()
+    }
+
+    fn filter_with_downloads_directory(dependency: &Dependency, downloads_directory: &Path)-> InstallCommandFilter {
+        let mut directory_finder = MockSolipathDirectoryFinderTrait::new();
+        directory_finder.expect_get_dependency_downloads_directory()
+            .with(eq(dependency.clone()))
+            .return_const(downloads_directory.to_path_buf());
+        InstallCommandFilter::new(Arc::new(directory_finder))
+    }
+
+    fn empty_environment()-> ResolvedEnvironment {
+        ResolvedEnvironment::new(Vec::new())
+    }
+
     #[test]
     fn filter_commands_no_rules_returns_true() {
         let directory_finder = MockSolipathDirectoryFinderTrait::new();
         let dependency = Dependency::new("depend", "version");
         let install_command_filter = InstallCommandFilter::new(Arc::new(directory_finder));
-        assert_eq!(install_command_filter.command_should_be_run(&dependency, &HashMap::new()), true);
+        assert_eq!(install_command_filter.command_should_be_run(&dependency, &install_command("{}"), &empty_environment()).unwrap(), true);
     }
 
     #[test]
     fn filter_commands_file_exists_already_on_a_file_does_not_exist_rule_returns_false() {
-        let mut directory_finder = MockSolipathDirectoryFinderTrait::new();
         let dependency = Dependency::new("depend", "version");
         let temp_dir = tempdir().unwrap().into_path();
-        directory_finder.expect_get_dependency_downloads_directory()
-            .with(eq(dependency.clone()))
-            .return_const(temp_dir.clone());
         let expected_file_path = temp_dir.clone().join("somepath".to_string());
         File::create(expected_file_path).expect("failed to create file");
-        let mut map = HashMap::new();
-        map.insert("file_does_not_exist".to_string(), serde_json::Value::String("somepath".to_string()));
-        let install_command_filter = InstallCommandFilter::new(Arc::new(directory_finder));
-        assert_eq!(install_command_filter.command_should_be_run(&dependency, &map), false);
+        let install_command_filter = filter_with_downloads_directory(&dependency, &temp_dir);
+        let install_command = install_command(r#"{"file_does_not_exist": "somepath"}"#);
+        assert_eq!(install_command_filter.command_should_be_run(&dependency, &install_command, &empty_environment()).unwrap(), false);
     }
 
     #[test]
     fn filter_commands_file_does_not_exist_on_a_file_does_not_exist_rule_returns_true() {
-        let mut directory_finder = MockSolipathDirectoryFinderTrait::new();
         let dependency = Dependency::new("depend", "version");
         let temp_dir = tempdir().unwrap().into_path();
-        directory_finder.expect_get_dependency_downloads_directory()
-            .with(eq(dependency.clone()))
-            .return_const(temp_dir.clone());
-        let mut map = HashMap::new();
-        map.insert("file_does_not_exist".to_string(), serde_json::Value::String("somepath".to_string()));
-        let install_command_filter = InstallCommandFilter::new(Arc::new(directory_finder));
-        assert_eq!(install_command_filter.command_should_be_run(&dependency, &map), true);
+        let install_command_filter = filter_with_downloads_directory(&dependency, &temp_dir);
+        let install_command = install_command(r#"{"file_does_not_exist": "somepath"}"#);
+        assert_eq!(install_command_filter.command_should_be_run(&dependency, &install_command, &empty_environment()).unwrap(), true);
     }
 
     #[test]
-    #[should_panic(expected="unrecognized command filter option 'nonexistent_filter' in 'depend' dependency!")]
-    fn filter_commands_unrecognized_filter_panics() {
+    fn filter_commands_unrecognized_filter_is_an_error() {
         let directory_finder = MockSolipathDirectoryFinderTrait::new();
         let dependency = Dependency::new("depend", "version");
-        let mut map = HashMap::new();
-        map.insert("nonexistent_filter".to_string(), serde_json::Value::String("something".to_string()));
         let install_command_filter = InstallCommandFilter::new(Arc::new(directory_finder));
-        install_command_filter.command_should_be_run(&dependency, &map);
-    }    
+        let error = install_command_filter
+            .command_should_be_run(&dependency, &install_command(r#"{"nonexistent_filter": "something"}"#), &empty_environment())
+            .unwrap_err();
+        assert!(error.to_string().starts_with("unrecognized when_to_run_rules entry 'nonexistent_filter'"));
+    }
+
+    #[test]
+    fn filter_commands_checks_files_directories_and_environment() {
+        let dependency = Dependency::new("depend", "version");
+        let temp_dir = tempdir().unwrap().into_path();
+        create_dir_all(temp_dir.join("empty")).unwrap();
+        create_dir_all(temp_dir.join("bin")).unwrap();
+        File::create(temp_dir.join("bin/tool")).unwrap();
+        let install_command_filter = filter_with_downloads_directory(&dependency, &temp_dir);
+        let environment = ResolvedEnvironment::new(vec![("MODE".into(), "ci".into()), ("PATH".into(), "".into())]);
+        let should_run = |rules: &str| {
+            install_command_filter.command_should_be_run(&dependency, &install_command(rules), &environment).unwrap()
+        };
 
-}
\ No newline at end of file
+        assert!(should_run(r#"{"file_exists": "bin/tool", "directory_empty": "empty"}"#));
+        assert!(should_run(r#"{"directory_empty": "missing"}"#));
+        assert!(!should_run(r#"{"directory_empty": "bin"}"#));
+        assert!(should_run(r#"{"env_var_set": "MODE", "env_var_equals": {"name": "MODE", "value": "ci"}}"#));
+        assert!(!should_run(r#"{"env_var_equals": {"name": "MODE", "value": "local"}}"#));
+        assert!(should_run(r#"{"command_not_on_path": "solipath-test-missing-command"}"#));
+        assert!(should_run(r#"{"any_of": [{"env_var_set": "UNSET"}, {"file_exists": "bin/tool"}]}"#));
+        assert!(!should_run(r#"{"all_of": [{"env_var_set": "UNSET"}, {"file_exists": "bin/tool"}]}"#));
+        assert!(should_run(r#"{"not": {"env_var_set": "UNSET"}}"#));
+    }
+
+    #[test]
+    fn once_rule_only_passes_until_a_successful_run_is_recorded() {
+        let dependency = Dependency::new("depend", "version");
+        let temp_dir = tempdir().unwrap().into_path();
+        let install_command_filter = filter_with_downloads_directory(&dependency, &temp_dir);
+        let once_command = install_command(r#"{"once": true}"#);
+        let other_command: InstallCommand =
+            serde_json::from_str(r#"{"command": "do something else", "when_to_run_rules": {"once": true}}"#).unwrap();
+
+        assert!(install_command_filter.command_should_be_run(&dependency, &once_command, &empty_environment()).unwrap());
+        install_command_filter.record_successful_run(&dependency, &once_command).unwrap();
+
+        assert!(!install_command_filter.command_should_be_run(&dependency, &once_command, &empty_environment()).unwrap());
+        assert!(install_command_filter.command_should_be_run(&dependency, &other_command, &empty_environment()).unwrap());
+    }
+}
@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+
+use crate::solipath_instructions::data::dependency::Dependency;
+use crate::solipath_instructions::data::template::Template;
+use crate::solipath_platform::platform::Platform;
+use crate::solipath_template::template_variable_replacer::get_version_part;
+
+// variables every template can use without install_instructions passing them in
+pub fn get_builtin_variables(dependency: &Dependency, platform: &Platform, template: &Template) -> HashMap<String, String> {
+    let arch = platform.arch.clone().unwrap_or_default();
+    let archive_extension = if platform.os == "windows" { "zip" } else { "tar.gz" };
+    HashMap::from([
+        ("name".to_string(), dependency.name.clone()),
+        ("version".to_string(), dependency.version.clone()),
+        ("version.major".to_string(), get_version_part(&dependency.version, 0)),
+        ("version.minor".to_string(), get_version_part(&dependency.version, 1)),
+        ("version.patch".to_string(), get_version_part(&dependency.version, 2)),
+        ("os".to_string(), template.get_alias(&platform.os).to_string()),
+        ("arch".to_string(), template.get_alias(&arch).to_string()),
+        ("archive_ext".to_string(), archive_extension.to_string()),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_variables_come_from_the_dependency_and_platform() {
+        let template =
+            serde_json::from_str::<Template>(r#"{"name": "release", "aliases": {"x86_64": "x64", "macos": "mac"}}"#).unwrap();
+
+        let linux = get_builtin_variables(&Dependency::new("node", "20.11.1"), &Platform::new("linux", "x86_64"), &template);
+        let windows = get_builtin_variables(&Dependency::new("node", "20"), &Platform::new("windows", "aarch64"), &template);
+
+        assert_eq!(
+            HashMap::from([
+                ("name".to_string(), "node".to_string()),
+                ("version".to_string(), "20.11.1".to_string()),
+                ("version.major".to_string(), "20".to_string()),
+                ("version.minor".to_string(), "11".to_string()),
+                ("version.patch".to_string(), "1".to_string()),
+                ("os".to_string(), "linux".to_string()),
+                ("arch".to_string(), "x64".to_string()),
+                ("archive_ext".to_string(), "tar.gz".to_string()),
+            ]),
+            linux
+        );
+        assert_eq!(("0", "aarch64", "zip"), (windows["version.minor"].as_str(), windows["arch"].as_str(), windows["archive_ext"].as_str()));
+    }
+}
@@ -1,2 +1,3 @@
+pub mod builtin_variables;
 pub mod template_retriever;
 pub mod template_variable_replacer;
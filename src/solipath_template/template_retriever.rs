@@ -1,16 +1,22 @@
 #[cfg(test)]
 use mockall::automock;
 
+use anyhow::{anyhow, bail, Context, Result};
 use async_trait::async_trait;
 use std::path::PathBuf;
 use std::sync::Arc;
 
+use crate::solipath_config::user_config::DEFAULT_REGISTRY;
 use crate::solipath_instructions::data::dependency::Dependency;
 use crate::solipath_directory::solipath_directory_finder::SolipathDirectoryFinderTrait;
 use crate::solipath_download::file_to_string_downloader::FileToStringDownloaderTrait;
 use crate::solipath_instructions::data::dependency_instructions::DependencyInstructions;
 use crate::solipath_instructions::data::install_instructions::InstallInstructions;
 use crate::solipath_instructions::data::template::Template;
+use crate::solipath_instructions::file_format::{get_file_names, FileFormat};
+use crate::solipath_platform::current_platform_retriever::CurrentPlatformRetrieverTrait;
+use crate::solipath_platform::platform_filter::{PlatformFilter, PlatformFilterTrait};
+use crate::solipath_template::builtin_variables::get_builtin_variables;
 use crate::solipath_template::template_variable_replacer::TemplateVariableReplacerTrait;
 
 #[cfg_attr(test, automock)]
@@ -20,14 +26,15 @@ pub trait TemplateRetrieverTrait {
         &self,
         dependency: &Dependency,
         template: &Template,
-    ) -> DependencyInstructions;
+    ) -> Result<Vec<DependencyInstructions>>;
 }
 
 pub struct TemplateRetriever {
-    base_dependency_url: String,
+    base_dependency_urls: Vec<String>,
     file_downloader: Arc<dyn FileToStringDownloaderTrait + Sync + Send>,
     directory_finder: Arc<dyn SolipathDirectoryFinderTrait + Sync + Send>,
     template_variable_replacer: Arc<dyn TemplateVariableReplacerTrait + Sync + Send>,
+    current_platform_retriever: Arc<dyn CurrentPlatformRetrieverTrait + Sync + Send>,
 }
 
 impl TemplateRetriever {
@@ -35,12 +42,14 @@ impl TemplateRetriever {
         file_downloader: Arc<dyn FileToStringDownloaderTrait + Sync + Send>,
         directory_finder: Arc<dyn SolipathDirectoryFinderTrait + Sync + Send>,
         template_variable_replacer: Arc<dyn TemplateVariableReplacerTrait + Sync + Send>,
+        current_platform_retriever: Arc<dyn CurrentPlatformRetrieverTrait + Sync + Send>,
     ) -> Self {
         Self {
-            base_dependency_url: "https://raw.githubusercontent.com/Solipath/Solipath-Install-Instructions/main".to_string(),
+            base_dependency_urls: vec![DEFAULT_REGISTRY.to_string()],
             file_downloader,
             directory_finder,
             template_variable_replacer,
+            current_platform_retriever,
         }
     }
     pub fn new_with_alternate_url(
@@ -48,52 +57,113 @@ impl TemplateRetriever {
         file_downloader: Arc<dyn FileToStringDownloaderTrait + Sync + Send>,
         directory_finder: Arc<dyn SolipathDirectoryFinderTrait + Sync + Send>,
         template_variable_replacer: Arc<dyn TemplateVariableReplacerTrait + Sync + Send>,
+        current_platform_retriever: Arc<dyn CurrentPlatformRetrieverTrait + Sync + Send>,
+    ) -> Self {
+        Self::new_with_registries(
+            vec![base_dependency_url],
+            file_downloader,
+            directory_finder,
+            template_variable_replacer,
+            current_platform_retriever,
+        )
+    }
+
+    pub fn new_with_registries(
+        base_dependency_urls: Vec<String>,
+        file_downloader: Arc<dyn FileToStringDownloaderTrait + Sync + Send>,
+        directory_finder: Arc<dyn SolipathDirectoryFinderTrait + Sync + Send>,
+        template_variable_replacer: Arc<dyn TemplateVariableReplacerTrait + Sync + Send>,
+        current_platform_retriever: Arc<dyn CurrentPlatformRetrieverTrait + Sync + Send>,
     ) -> Self {
         Self {
-            base_dependency_url,
+            base_dependency_urls,
             file_downloader,
             directory_finder,
             template_variable_replacer,
+            current_platform_retriever,
         }
     }
 
-    fn get_path_to_save_file(&self, dependency: &Dependency, template: &Template) -> PathBuf {
-        let mut path_to_save_file = self.directory_finder.get_dependency_template_directory(&dependency);
-        path_to_save_file.push(format!("{}.json", template.get_name()));
-        path_to_save_file
+    fn get_files(&self, dependency: &Dependency, template: &Template) -> Vec<(String, PathBuf)> {
+        let (template_name, folder, template_directory) = match template.get_common_name() {
+            Some(common_name) => (
+                common_name.to_string(),
+                "_common".to_string(),
+                self.directory_finder.get_common_template_directory(),
+            ),
+            None => (
+                template.get_name(),
+                dependency.name.clone(),
+                self.directory_finder.get_dependency_template_directory(dependency),
+            ),
+        };
+        let (template_name, folder, template_directory) = (&template_name, &folder, &template_directory);
+        self.base_dependency_urls
+            .iter()
+            .flat_map(|base_dependency_url| {
+                get_file_names(template_name).into_iter().map(move |file_name| {
+                    let url = format!("{}/{}/templates/{}", base_dependency_url, folder, file_name);
+                    (url, template_directory.join(file_name))
+                })
+            })
+            .collect()
     }
 
-    fn get_url(&self, dependency: &Dependency, template: &Template) -> String {
-        format!(
-            "{}/{}/templates/{}.json",
-            &self.base_dependency_url,
-            dependency.name,
-            template.get_name()
-        )
+    async fn retrieve_single_template(&self, dependency: &Dependency, template: &Template) -> Result<DependencyInstructions> {
+        let (path, template_content) = self
+            .file_downloader
+            .download_first_available_file_then_parse_to_string(&self.get_files(dependency, template))
+            .await;
+        let platform = self.current_platform_retriever.get_current_platform();
+        let template = template.with_builtin_variables(get_builtin_variables(dependency, &platform, template));
+        let replaced_template_content = self
+            .template_variable_replacer
+            .replace_variables(&template_content, &template, FileFormat::from_path(&path))
+            .with_context(|| format!("failed to expand template {} for {} {}", template.get_name(), dependency.name, dependency.version))?;
+        let install_instructions = FileFormat::from_path(&path)
+            .parse::<InstallInstructions>(&replaced_template_content)
+            .map_err(|error| {
+                anyhow!(
+                    "failed to parse {} for template {} of {} {}: {}",
+                    path.display(),
+                    template.get_name(),
+                    dependency.name,
+                    dependency.version,
+                    error
+                )
+            })?;
+        Ok(DependencyInstructions::new(dependency.clone(), install_instructions))
     }
 }
 
 #[async_trait]
 impl TemplateRetrieverTrait for TemplateRetriever {
+    // templates can use other templates, each one is expanded until no template references are left
     async fn retrieve_instructions_from_template(
         &self,
         dependency: &Dependency,
         template: &Template,
-    ) -> DependencyInstructions {
-        let url = self.get_url(&dependency, &template);
-        let output_path = self.get_path_to_save_file(dependency, template);
-        let template_content = self
-            .file_downloader
-            .download_file_then_parse_to_string(&url, &output_path)
-            .await;
-        let replaced_template_content = self
-            .template_variable_replacer
-            .replace_variables(&template_content, &template);
-        DependencyInstructions::new(
-            dependency.clone(),
-            serde_json::from_str::<InstallInstructions>(&replaced_template_content)
-                .expect(&format!("failed to parse template {}", &replaced_template_content)),
-        )
+    ) -> Result<Vec<DependencyInstructions>> {
+        let platform_filter: Arc<dyn PlatformFilterTrait> =
+            Arc::new(PlatformFilter::new(self.current_platform_retriever.clone()));
+        let mut pending = vec![(template.clone(), vec![template.get_name()])];
+        let mut instructions = Vec::new();
+        while let Some((template, used_by)) = pending.pop() {
+            let template_instructions = self
+                .retrieve_single_template(dependency, &template)
+                .await?
+                .filter_platform(&platform_filter);
+            for included_template in template_instructions.get_templates().iter().rev() {
+                let mut chain = used_by.clone();
+                chain.push(included_template.get_name());
+                if used_by.contains(&included_template.get_name()) {
+                    bail!("templates include each other: {}", chain.join(" -> "));
+                }
+                pending.push((included_template.clone(), chain));
+            }
+            instructions.push(template_instructions);
+        }
+        Ok(instructions)
     }
 }
 
@@ -106,8 +176,17 @@ mod test {
 
     use crate::solipath_directory::solipath_directory_finder::MockSolipathDirectoryFinderTrait;
     use crate::solipath_download::file_to_string_downloader::MockFileToStringDownloaderTrait;
+    use crate::solipath_instructions::data::dependency_instructions::VecDependencyInstructions;
+    use crate::solipath_platform::current_platform_retriever::MockCurrentPlatformRetrieverTrait;
+    use crate::solipath_platform::platform::Platform;
     use crate::solipath_template::template_variable_replacer::TemplateVariableReplacer;
 
+    fn platform_retriever(os: &'static str, arch: &'static str) -> MockCurrentPlatformRetrieverTrait {
+        let mut platform_retriever = MockCurrentPlatformRetrieverTrait::new();
+        platform_retriever.expect_get_current_platform().returning(move || Platform::new(os, arch));
+        platform_retriever
+    }
+
     #[tokio::test]
     async fn retrieve_instructions_downloads_data_sends_data_to_template_retriever_and_builds_dependency_instructions()
     {
@@ -127,13 +206,19 @@ mod test {
 
         let mut mock_file_downloader = MockFileToStringDownloaderTrait::new();
         mock_file_downloader
-            .expect_download_file_then_parse_to_string()
-            .withf(move |url, path| {
-                url == "https://raw.githubusercontent.com/Solipath/Solipath-Install-Instructions/main/java/templates/template1.json"
-                && path == PathBuf::from("/something/template1.json")
+            .expect_download_first_available_file_then_parse_to_string()
+            .withf(move |files| {
+                files[0] == (
+                    "https://raw.githubusercontent.com/Solipath/Solipath-Install-Instructions/main/java/templates/template1.json".to_string(),
+                    PathBuf::from("/something/template1.json"),
+                ) && files[3].0.ends_with("templates/template1.yaml")
+                    && files[4].0.ends_with("templates/template1.yml")
             })
             .times(1)
-            .return_const(r#"{"downloads": [{"url": "${key1}.com", "destination_directory": "/${key2}"}]}"#);
+            .return_const((
+                PathBuf::from("/something/template1.json"),
+                r#"{"downloads": [{"url": "${key1}.com", "destination_directory": "/${key2}"}]}"#.to_string(),
+            ));
 
         let template_variable_replacer = TemplateVariableReplacer::new();
 
@@ -141,10 +226,12 @@ mod test {
             Arc::new(mock_file_downloader),
             Arc::new(mock_directory_finder),
             Arc::new(template_variable_replacer),
+            Arc::new(platform_retriever("linux", "x86_64")),
         );
         let instructions = template_retriever
             .retrieve_instructions_from_template(&dependency, &template)
-            .await;
+            .await
+            .unwrap();
         let expected = DependencyInstructions::new(
             dependency,
             serde_json::from_str::<InstallInstructions>(
@@ -152,6 +239,135 @@ mod test {
             )
             .unwrap(),
         );
-        assert_eq!(instructions, expected);
+        assert_eq!(instructions, vec![expected]);
+    }
+
+    #[tokio::test]
+    async fn builtin_variables_are_available_and_explicit_variables_win() {
+        let dependency = Dependency::new("node", "20.11.1");
+        let template = serde_json::from_str::<Template>(
+            r#"{"name": "release", "variables": {"name": "nodejs"}, "aliases": {"x86_64": "x64"}}"#,
+        )
+        .unwrap();
+        let mut mock_directory_finder = MockSolipathDirectoryFinderTrait::new();
+        mock_directory_finder
+            .expect_get_dependency_template_directory()
+            .return_const(PathBuf::from("/something"));
+        let mut mock_file_downloader = MockFileToStringDownloaderTrait::new();
+        mock_file_downloader
+            .expect_download_first_available_file_then_parse_to_string()
+            .return_const((
+                PathBuf::from("/something/release.json"),
+                r#"{"downloads": [{"url": "${name}-v${version}-${os}-${arch}.${archive_ext}", "destination_directory": "${version.major}"}]}"#.to_string(),
+            ));
+        let template_retriever = TemplateRetriever::new(
+            Arc::new(mock_file_downloader),
+            Arc::new(mock_directory_finder),
+            Arc::new(TemplateVariableReplacer::new()),
+            Arc::new(platform_retriever("linux", "x86_64")),
+        );
+
+        let instructions = template_retriever
+            .retrieve_instructions_from_template(&dependency, &template)
+            .await
+            .unwrap();
+
+        let expected = DependencyInstructions::new(
+            dependency,
+            serde_json::from_str::<InstallInstructions>(
+                r#"{"downloads": [{"url": "nodejs-v20.11.1-linux-x64.tar.gz", "destination_directory": "20"}]}"#,
+            )
+            .unwrap(),
+        );
+        assert_eq!(instructions, vec![expected]);
+    }
+
+    fn retriever_for_files(files: Vec<(&'static str, &'static str)>) -> TemplateRetriever {
+        let mut mock_directory_finder = MockSolipathDirectoryFinderTrait::new();
+        mock_directory_finder
+            .expect_get_dependency_template_directory()
+            .return_const(PathBuf::from("/solipath/java/templates"));
+        mock_directory_finder
+            .expect_get_common_template_directory()
+            .return_const(PathBuf::from("/solipath/_common/templates"));
+        let mut mock_file_downloader = MockFileToStringDownloaderTrait::new();
+        mock_file_downloader
+            .expect_download_first_available_file_then_parse_to_string()
+            .returning(move |candidates| {
+                let (url, path) = candidates[0].clone();
+                let (_, content) = files
+                    .iter()
+                    .find(|(file_url, _)| url.ends_with(file_url))
+                    .unwrap_or_else(|| panic!("unexpected template {}", url));
+                (path, content.to_string())
+            });
+        TemplateRetriever::new_with_alternate_url(
+            "https://registry".to_string(),
+            Arc::new(mock_file_downloader),
+            Arc::new(mock_directory_finder),
+            Arc::new(TemplateVariableReplacer::new()),
+            Arc::new(platform_retriever("linux", "x86_64")),
+        )
+    }
+
+    #[tokio::test]
+    async fn templates_can_include_other_templates_and_common_templates() {
+        let dependency = Dependency::new("java", "17");
+        let template = serde_json::from_str::<Template>(r#"{"name": "release", "variables": {"repo": "temurin"}}"#).unwrap();
+        let template_retriever = retriever_for_files(vec![
+            (
+                "/java/templates/release.json",
+                r#"{"templates": [{"name": "common:github-release", "variables": {"repo": "adoptium/${repo}"}}, {"name": "windows-only", "platform_filters": [{"os": "windows"}]}], "environment_variables": [{"name": "JAVA_HOME", "relative_path": "jdk"}]}"#,
+            ),
+            (
+                "/_common/templates/github-release.json",
+                r#"{"downloads": [{"url": "https://github.com/${repo}/releases/${name}-${version}.${archive_ext}", "destination_directory": "jdk"}]}"#,
+            ),
+        ]);
+
+        let instructions = template_retriever
+            .retrieve_instructions_from_template(&dependency, &template)
+            .await
+            .unwrap();
+
+        assert_eq!(2, instructions.len());
+        assert_eq!(1, instructions[0].get_templates().len());
+        assert_eq!(
+            vec!["https://github.com/adoptium/temurin/releases/java-17.tar.gz"],
+            instructions.get_downloads().iter().map(|(_, download)| download.get_url()).collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn templates_that_include_each_other_are_an_error() {
+        let dependency = Dependency::new("java", "17");
+        let template = serde_json::from_str::<Template>(r#"{"name": "first"}"#).unwrap();
+        let template_retriever = retriever_for_files(vec![
+            ("/java/templates/first.json", r#"{"templates": [{"name": "second"}]}"#),
+            ("/java/templates/second.json", r#"{"templates": [{"name": "first"}]}"#),
+        ]);
+
+        let error = template_retriever
+            .retrieve_instructions_from_template(&dependency, &template)
+            .await
+            .unwrap_err();
+
+        assert_eq!("templates include each other: first -> second -> first", error.to_string());
+    }
+
+    #[tokio::test]
+    async fn unparsable_template_is_an_error_naming_the_template() {
+        let dependency = Dependency::new("java", "17");
+        let template = serde_json::from_str::<Template>(r#"{"name": "broken"}"#).unwrap();
+        let template_retriever = retriever_for_files(vec![("/java/templates/broken.json", r#"{"downloads": "#)]);
+
+        let error = template_retriever
+            .retrieve_instructions_from_template(&dependency, &template)
+            .await
+            .unwrap_err();
+
+        assert!(error
+            .to_string()
+            .starts_with("failed to parse /solipath/java/templates/broken.json for template broken of java 17:"));
     }
 }
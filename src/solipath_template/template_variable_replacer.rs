@@ -1,13 +1,25 @@
 #[cfg(test)]
 use mockall::automock;
 
+use std::collections::HashMap;
+
+use anyhow::{bail, Context, Result};
+
 use crate::solipath_instructions::data::template::Template;
+use crate::solipath_instructions::file_format::FileFormat;
 
 #[cfg_attr(test, automock)]
 pub trait TemplateVariableReplacerTrait {
-    fn replace_variables(&self, input: &str, template: &Template) -> String;
+    fn replace_variables(&self, input: &str, template: &Template, file_format: FileFormat) -> Result<String>;
 }
 
+// placeholders that are filled in when environment variables are set, rather than when the template is expanded
+const RUNTIME_PLACEHOLDERS: [&str; 2] = ["downloads", "SOLIPATH_HOME"];
+const RUNTIME_PLACEHOLDER_PREFIXES: [&str; 2] = ["env:", "dep:"];
+
+// ${key}, ${key:-default} and ${key|filter|filter:argument:argument} are replaced, $${...} is left as ${...}.
+// {{#if key == "value"}}...{{else}}...{{/if}} keeps one of its branches, the condition can also use != or just a key,
+// which is true when the variable is set and not empty
 pub struct TemplateVariableReplacer;
 
 impl TemplateVariableReplacer {
@@ -17,14 +29,232 @@ impl TemplateVariableReplacer {
 }
 
 impl TemplateVariableReplacerTrait for TemplateVariableReplacer {
-    fn replace_variables(&self, input: &str, template: &Template) -> String {
-        let mut output = input.to_string();
-        template.get_variables().iter().for_each(|(key, value)| {
-            let string_to_replace = format!("${{{}}}", key);
-            output = output.replace(&string_to_replace, &value);
-        });
-        output
+    fn replace_variables(&self, input: &str, template: &Template, file_format: FileFormat) -> Result<String> {
+        let variables = template.get_variables();
+        let output = render_if_blocks(input, variables)?;
+        let mut unresolved = Vec::new();
+        let output = substitute_placeholders(&output, variables, file_format, &mut unresolved)?;
+        if !unresolved.is_empty() {
+            unresolved.sort();
+            unresolved.dedup();
+            bail!("template variables were not set: {}", unresolved.join(", "));
+        }
+        Ok(output)
+    }
+}
+
+fn render_if_blocks(input: &str, variables: &HashMap<String, String>) -> Result<String> {
+    let Some(block_start) = input.find("{{#if ") else {
+        return Ok(input.to_string());
+    };
+    let condition_start = block_start + "{{#if ".len();
+    let condition_end = condition_start
+        + input[condition_start..].find("}}").context("{{#if is missing its closing }}")?;
+    let body_start = condition_end + "}}".len();
+    let (else_start, body_end) = find_block_end(&input[body_start..])?;
+    let (true_branch, false_branch) = match else_start {
+        Some(else_start) => (
+            &input[body_start..body_start + else_start],
+            &input[body_start + else_start + "{{else}}".len()..body_start + body_end],
+        ),
+        None => (&input[body_start..body_start + body_end], ""),
+    };
+    let branch = if evaluate_condition(&input[condition_start..condition_end], variables)? {
+        true_branch
+    } else {
+        false_branch
+    };
+    let rest = &input[body_start + body_end + "{{/if}}".len()..];
+    Ok(format!(
+        "{}{}{}",
+        &input[..block_start],
+        render_if_blocks(branch, variables)?,
+        render_if_blocks(rest, variables)?
+    ))
+}
+
+// returns where the top level {{else}} and the matching {{/if}} start, skipping over nested blocks
+fn find_block_end(body: &str) -> Result<(Option<usize>, usize)> {
+    let mut depth = 0;
+    let mut else_start = None;
+    let mut position = 0;
+    while position < body.len() {
+        let remaining = &body[position..];
+        if remaining.starts_with("{{#if ") {
+            depth += 1;
+        } else if remaining.starts_with("{{/if}}") {
+            if depth == 0 {
+                return Ok((else_start, position));
+            }
+            depth -= 1;
+        } else if remaining.starts_with("{{else}}") && depth == 0 {
+            else_start = Some(position);
+        }
+        position += remaining.chars().next().map_or(1, char::len_utf8);
+    }
+    bail!("{{{{#if}}}} is missing its {{{{/if}}}}")
+}
+
+fn evaluate_condition(condition: &str, variables: &HashMap<String, String>) -> Result<bool> {
+    let comparison = condition
+        .split_once("==")
+        .map(|(key, value)| (key, value, true))
+        .or_else(|| condition.split_once("!=").map(|(key, value)| (key, value, false)));
+    match comparison {
+        Some((key, expected, equal)) => {
+            let key = key.trim();
+            let value = variables
+                .get(key)
+                .with_context(|| format!("template variables were not set: {}", key))?;
+            Ok((value == unquote(expected.trim())) == equal)
+        }
+        None => Ok(variables.get(condition.trim()).is_some_and(|value| !value.is_empty())),
+    }
+}
+
+// inside a json string the quotes around the value are escaped
+fn unquote(text: &str) -> &str {
+    ["\\\"", "\"", "'"]
+        .iter()
+        .find_map(|quote| text.strip_prefix(quote).and_then(|text| text.strip_suffix(quote)))
+        .unwrap_or(text)
+}
+
+fn substitute_placeholders(
+    input: &str,
+    variables: &HashMap<String, String>,
+    file_format: FileFormat,
+    unresolved: &mut Vec<String>,
+) -> Result<String> {
+    let mut output = String::with_capacity(input.len());
+    let mut remaining = input;
+    while let Some(start) = remaining.find("${") {
+        let escaped = remaining[..start].ends_with('$');
+        output.push_str(&remaining[..if escaped { start - 1 } else { start }]);
+        let Some(length) = remaining[start..].find('}') else {
+            output.push_str(&remaining[start..]);
+            return Ok(output);
+        };
+        let placeholder = &remaining[start..start + length + 1];
+        let expression = &placeholder[2..placeholder.len() - 1];
+        if escaped || is_runtime_placeholder(expression, variables) {
+            output.push_str(placeholder);
+        } else if let Some(value) = evaluate_expression(expression, variables, unresolved)? {
+            let escaped_value = escape(&value, file_format, string_style(&output, file_format))
+                .with_context(|| format!("could not write the value of ${{{}}} into the template", expression))?;
+            output.push_str(&escaped_value);
+        }
+        remaining = &remaining[start + length + 1..];
+    }
+    output.push_str(remaining);
+    Ok(output)
+}
+
+fn is_runtime_placeholder(expression: &str, variables: &HashMap<String, String>) -> bool {
+    !variables.contains_key(expression)
+        && (RUNTIME_PLACEHOLDERS.contains(&expression)
+            || RUNTIME_PLACEHOLDER_PREFIXES.iter().any(|prefix| expression.starts_with(prefix)))
+}
+
+fn evaluate_expression(
+    expression: &str,
+    variables: &HashMap<String, String>,
+    unresolved: &mut Vec<String>,
+) -> Result<Option<String>> {
+    let mut parts = expression.split('|');
+    let variable = parts.next().unwrap_or_default();
+    let (key, default) = match variable.split_once(":-") {
+        Some((key, default)) => (key.trim(), Some(default)),
+        None => (variable.trim(), None),
+    };
+    let Some(mut value) = variables.get(key).map(String::to_string).or(default.map(str::to_string)) else {
+        unresolved.push(key.to_string());
+        return Ok(None);
+    };
+    for filter in parts {
+        value = apply_filter(&value, filter)?;
+    }
+    Ok(Some(value))
+}
+
+fn apply_filter(value: &str, filter: &str) -> Result<String> {
+    let mut arguments = filter.split(':');
+    let filter_name = arguments.next().unwrap_or_default().trim();
+    Ok(match filter_name {
+        "major" => get_version_part(value, 0),
+        "minor" => get_version_part(value, 1),
+        "patch" => get_version_part(value, 2),
+        "lower" => value.to_lowercase(),
+        "upper" => value.to_uppercase(),
+        "replace" => match (arguments.next(), arguments.next()) {
+            (Some(from), Some(to)) if !from.is_empty() => value.replace(from, to),
+            _ => bail!("the replace filter needs what to replace and what to replace it with, e.g. replace:+:%2B"),
+        },
+        _ => bail!(
+            "unknown template filter '{}', expected one of major, minor, patch, lower, upper, replace",
+            filter_name
+        ),
+    })
+}
+
+pub fn get_version_part(version: &str, index: usize) -> String {
+    version.split('.').nth(index).unwrap_or("0").to_string()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum StringStyle {
+    DoubleQuoted,
+    SingleQuoted,
+    Plain,
+}
+
+// json values are always inside double quotes, toml and yaml values can also sit in 'literal' strings or be written
+// without quotes, so the line written so far is scanned for a string that is still open
+fn string_style(output: &str, file_format: FileFormat) -> StringStyle {
+    if matches!(file_format, FileFormat::Json | FileFormat::Jsonc) {
+        return StringStyle::DoubleQuoted;
     }
+    let line = &output[output.rfind('\n').map(|index| index + 1).unwrap_or(0)..];
+    let mut style = StringStyle::Plain;
+    let mut previous = ' ';
+    let mut characters = line.chars();
+    while let Some(character) = characters.next() {
+        match (&style, character) {
+            (StringStyle::DoubleQuoted, '\\') => {
+                characters.next();
+            }
+            (StringStyle::DoubleQuoted, '"') | (StringStyle::SingleQuoted, '\'') => style = StringStyle::Plain,
+            // a quote only starts a string at the start of a value, it can be part of a plain yaml value
+            (StringStyle::Plain, '"') if previous.is_whitespace() || ":=[{,".contains(previous) => {
+                style = StringStyle::DoubleQuoted
+            }
+            (StringStyle::Plain, '\'') if previous.is_whitespace() || ":=[{,".contains(previous) => {
+                style = StringStyle::SingleQuoted
+            }
+            _ => {}
+        }
+        previous = character;
+    }
+    style
+}
+
+// plain values are written as they are, so they should not need quoting in the format they are used in
+fn escape(value: &str, file_format: FileFormat, style: StringStyle) -> Result<String> {
+    Ok(match (file_format, style) {
+        (FileFormat::Json | FileFormat::Jsonc, _) => {
+            let quoted = serde_json::to_string(value).expect("strings can always be written as json");
+            quoted[1..quoted.len() - 1].to_string()
+        }
+        (_, StringStyle::DoubleQuoted) => value.replace('\\', "\\\\").replace('"', "\\\""),
+        (FileFormat::Yaml, StringStyle::SingleQuoted) => value.replace('\'', "''"),
+        (FileFormat::Toml, StringStyle::SingleQuoted) => {
+            if value.contains('\'') {
+                bail!("toml 'literal' strings cannot contain a single quote, use a \"double quoted\" string instead");
+            }
+            value.to_string()
+        }
+        (_, StringStyle::Plain) => value.to_string(),
+    })
 }
 
 #[cfg(test)]
@@ -32,13 +262,19 @@ mod test {
 
     use super::*;
 
+    fn replace(input: &str, variables: &str) -> Result<String> {
+        let template =
+            serde_json::from_str::<Template>(&format!(r#"{{"name": "something", "variables": {}}}"#, variables)).unwrap();
+        TemplateVariableReplacer::new().replace_variables(input, &template, FileFormat::Json)
+    }
+
     #[test]
     fn returns_same_string_if_no_variables() {
         let variable_replacer = TemplateVariableReplacer::new();
         let template = serde_json::from_str::<Template>(r#"{"name": "something"}"#).unwrap();
         let input = r#"{"downloads": [{"url": "google.com", "destination_directory": "/something"}]}"#;
         let expected = r#"{"downloads": [{"url": "google.com", "destination_directory": "/something"}]}"#;
-        assert_eq!(variable_replacer.replace_variables(input, &template), expected);
+        assert_eq!(variable_replacer.replace_variables(input, &template, FileFormat::Json).unwrap(), expected);
     }
 
     #[test]
@@ -48,7 +284,7 @@ mod test {
             serde_json::from_str::<Template>(r#"{"name": "something", "variables": {"key1": "the-link"}}"#).unwrap();
         let input = r#"{"downloads": [{"url": "${key1}.com", "destination_directory": "/something"}]}"#;
         let expected = r#"{"downloads": [{"url": "the-link.com", "destination_directory": "/something"}]}"#;
-        assert_eq!(variable_replacer.replace_variables(input, &template), expected);
+        assert_eq!(variable_replacer.replace_variables(input, &template, FileFormat::Json).unwrap(), expected);
     }
 
     #[test]
@@ -60,6 +296,90 @@ mod test {
         .unwrap();
         let input = r#"{"downloads": [{"url": "${key1}.com", "destination_directory": "/${key2}"}]}"#;
         let expected = r#"{"downloads": [{"url": "the-link.com", "destination_directory": "/the/path"}]}"#;
-        assert_eq!(variable_replacer.replace_variables(input, &template), expected);
+        assert_eq!(variable_replacer.replace_variables(input, &template, FileFormat::Json).unwrap(), expected);
+    }
+
+    #[test]
+    fn defaults_filters_and_escaping() {
+        let output = replace(
+            r#"{"url": "${host:-example.com}/jdk-${version|replace:+:%2B}/${version|major}", "file": "${name|upper}", "note": "${note}"}"#,
+            r#"{"version": "17.0.2+8", "name": "jdk", "note": "say \"hi\" C:\\jdk"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            r#"{"url": "example.com/jdk-17.0.2%2B8/17", "file": "JDK", "note": "say \"hi\" C:\\jdk"}"#,
+            output
+        );
+        assert_eq!("say \"hi\" C:\\jdk", serde_json::from_str::<serde_json::Value>(&output).unwrap()["note"]);
+    }
+
+    fn replace_in_format(input: &str, file_format: FileFormat) -> String {
+        let template = serde_json::from_str::<Template>(
+            r#"{"name": "something", "variables": {"note": "say \"hi\" C:\\jdk"}}"#,
+        )
+        .unwrap();
+        TemplateVariableReplacer::new().replace_variables(input, &template, file_format).unwrap()
+    }
+
+    #[test]
+    fn toml_values_are_escaped() {
+        let output = replace_in_format(r#"note = "${note}""#, FileFormat::Toml);
+
+        assert_eq!(r#"note = "say \"hi\" C:\\jdk""#, output);
+        assert_eq!("say \"hi\" C:\\jdk", toml::from_str::<toml::Table>(&output).unwrap()["note"].as_str().unwrap());
+    }
+
+    #[test]
+    fn yaml_values_are_escaped() {
+        let output = replace_in_format(r#"note: "${note}""#, FileFormat::Yaml);
+
+        assert_eq!(r#"note: "say \"hi\" C:\\jdk""#, output);
+        assert_eq!("say \"hi\" C:\\jdk", serde_yaml::from_str::<serde_yaml::Value>(&output).unwrap()["note"].as_str().unwrap());
+    }
+
+    #[test]
+    fn single_quoted_and_plain_values_keep_their_backslashes() {
+        let template = serde_json::from_str::<Template>(
+            r#"{"name": "something", "variables": {"path": "C:\\it's \"here\"", "plain": "C:\\jdk"}}"#,
+        )
+        .unwrap();
+        let replace_yaml = |input: &str| TemplateVariableReplacer::new().replace_variables(input, &template, FileFormat::Yaml);
+
+        let output = replace_yaml("quoted: '${path}'\nplain: ${plain}\nmixed: don't ${plain}").unwrap();
+
+        let parsed = serde_yaml::from_str::<serde_yaml::Value>(&output).unwrap();
+        assert_eq!("C:\\it's \"here\"", parsed["quoted"].as_str().unwrap());
+        assert_eq!("C:\\jdk", parsed["plain"].as_str().unwrap());
+        assert_eq!("don't C:\\jdk", parsed["mixed"].as_str().unwrap());
+        let output = TemplateVariableReplacer::new()
+            .replace_variables("plain = '${plain}'", &template, FileFormat::Toml)
+            .unwrap();
+        assert_eq!("C:\\jdk", toml::from_str::<toml::Table>(&output).unwrap()["plain"].as_str().unwrap());
+        assert!(TemplateVariableReplacer::new()
+            .replace_variables("path = '${path}'", &template, FileFormat::Toml)
+            .is_err());
+    }
+
+    #[test]
+    fn if_blocks_keep_one_branch() {
+        let input = r#"{{#if os == "windows"}}zip{{else}}{{#if arm}}arm-{{/if}}tar.gz{{/if}} {{#if os != 'windows'}}unix{{/if}}"#;
+
+        assert_eq!("zip ", replace(input, r#"{"os": "windows"}"#).unwrap());
+        assert_eq!("arm-tar.gz unix", replace(input, r#"{"os": "linux", "arm": "yes"}"#).unwrap());
+        assert_eq!("tar.gz unix", replace(input, r#"{"os": "linux"}"#).unwrap());
+        assert_eq!(
+            r#"{"file": "jdk.zip"}"#,
+            replace(r#"{"file": "jdk.{{#if os == \"windows\"}}zip{{/if}}"}"#, r#"{"os": "windows"}"#).unwrap()
+        );
+    }
+
+    #[test]
+    fn unresolved_variables_are_listed_and_runtime_placeholders_are_kept() {
+        let error = replace(r#"{"url": "${host}/${file}/${host}"}"#, "{}").unwrap_err();
+        let kept = replace(r#"{"value": "${downloads}/${env:HOME}/${dep:java:downloads}/$${PATH}"}"#, "{}").unwrap();
+
+        assert_eq!("template variables were not set: file, host", error.to_string());
+        assert_eq!(r#"{"value": "${downloads}/${env:HOME}/${dep:java:downloads}/${PATH}"}"#, kept);
     }
 }
@@ -1,16 +1,18 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::process::ExitStatus;
 use std::process::Stdio;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Duration;
+use solipath_lib::solipath_environment_variable::resolved_environment::ResolvedEnvironment;
 use solipath_lib::solipath_execute::command_with_path_executor::CommandWithPathExecutor;
 use solipath_lib::solipath_instructions::data::dependency::Dependency;
-use solipath_lib::solipath_platform::current_platform_retriever::CurrentPlatformRetriever;
+use solipath_lib::solipath_platform::current_platform_retriever::{CurrentPlatformRetriever, CurrentPlatformRetrieverTrait};
 use tempfile::tempdir;
 
 use solipath_lib::solipath_shell::command_executor::CommandExecutorTrait;
-use solipath_lib::solipath_directory::solipath_directory_finder::SolipathDirectoryFinderTrait;
+use solipath_lib::solipath_directory::solipath_directory_finder::{platform_directory_name, SolipathDirectoryFinderTrait};
 
 //tests in this file are integration tests that pull down a couple hundred megabytes of data.
 //I don't want to run these every time. These can be run with "cargo test -- --features=expensive_tests"
@@ -31,7 +33,8 @@ async fn install_node_integration_test() {
     let dependency_list = vec![Dependency::new("node", "15")];
     let exit_status = command_with_path_executor
         .set_path_and_execute_command(dependency_list, &arguments)
-        .await;
+        .await
+        .unwrap();
 
     let output = command_executor.get_output();
 
@@ -56,7 +59,8 @@ async fn install_java_integration_test() {
     let dependency_list = vec![Dependency::new("java", "17")];
     let exit_status = command_with_path_executor
         .set_path_and_execute_command(dependency_list, &arguments)
-        .await;
+        .await
+        .unwrap();
 
     let output = command_executor.get_output();
     assert!(output.starts_with("openjdk 17"));
@@ -77,6 +81,10 @@ impl SolipathDirectoryFinderTrait for IntegrationTestSolipathDirectoryFinder {
     fn get_base_solipath_directory(&self) -> PathBuf {
         self.base_dir.clone()
     }
+
+    fn get_platform_directory_name(&self) -> String {
+        platform_directory_name(CurrentPlatformRetriever::new().get_current_platform())
+    }
 }
 
 struct IntegrationTestCommandExecutor {
@@ -96,7 +104,7 @@ impl IntegrationTestCommandExecutor {
 }
 
 impl CommandExecutorTrait for IntegrationTestCommandExecutor {
-    fn execute_command(&self, commands: &[String]) -> ExitStatus {
+    fn execute_command(&self, commands: &[String], environment: &ResolvedEnvironment) -> ExitStatus {
         let mut command = if std::env::consts::OS == "windows" {
             let mut command = Command::new("cmd");
             command.arg("/C").args(commands);
@@ -106,6 +114,7 @@ impl CommandExecutorTrait for IntegrationTestCommandExecutor {
             command.args(&commands[1..]);
             command
         };
+        environment.apply_to(&mut command);
         command.stdout(Stdio::piped());
         command.stdin(Stdio::piped());
 
@@ -114,7 +123,11 @@ impl CommandExecutorTrait for IntegrationTestCommandExecutor {
         ExitStatus::default()
     }
 
-    fn execute_single_string_command(&self, _: String)->ExitStatus {
+    fn execute_single_string_command(&self, _: String, _: &ResolvedEnvironment)->ExitStatus {
         ExitStatus::default()
     }
+
+    fn execute_logged_single_string_command(&self, _: String, _: &ResolvedEnvironment, _: &Path, _: Option<Duration>)->anyhow::Result<ExitStatus> {
+        Ok(ExitStatus::default())
+    }
 }